@@ -1,6 +1,9 @@
+pub mod batch;
+pub mod cache;
 pub mod connect;
 pub mod discovery;
 pub mod io;
+pub mod pins;
 pub mod repl;
 pub mod run;
 pub mod specials;
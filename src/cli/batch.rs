@@ -0,0 +1,109 @@
+use eyre::{Result, eyre};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::UnixStream;
+
+use crate::display::Display;
+use crate::protocol::Message;
+
+use super::pins::Pins;
+use super::turn::{is_cancelled, run_turn};
+
+/// Prompts from a batch file: a JSON array of strings for a `.json` file, or else one prompt
+/// per non-empty line, so a text batch may well start with `[draft] ...`.
+fn read_prompts(text: &str, json: bool) -> Result<Vec<String>> {
+    if json {
+        return serde_json::from_str(text)
+            .map_err(|error| eyre!("batch file is not a JSON array of strings: {error}"));
+    }
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Run every prompt in `path` as an independent one-shot turn over one hub connection.
+/// Answers stream to stdout as usual; with `out_dir` each is also saved as `0001.md`, `0002.md`, ...
+/// A failed prompt is reported and skipped; Ctrl-C stops the whole batch.
+pub async fn run_batch(
+    stream: &mut UnixStream,
+    display: Arc<Display>,
+    history: Vec<Message>,
+    pins: &Pins,
+    path: &Path,
+    out_dir: Option<&Path>,
+    mut connected_in: Duration,
+) -> Result<()> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|error| eyre!("cannot read batch file {}: {error}", path.display()))?;
+    let json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    let prompts = read_prompts(&text, json)?;
+    if let Some(dir) = out_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut failed = 0;
+    for (index, prompt) in prompts.iter().enumerate() {
+        let mut turn_history = history.clone();
+        turn_history.push(Message::User(prompt.clone()));
+        display.record_prompt(prompt).await;
+
+        let connect = std::mem::take(&mut connected_in);
+        let answer = match run_turn(stream, display.clone(), turn_history, pins, connect).await {
+            Ok(answer) => answer,
+            Err(error) if is_cancelled(&error) => return Ok(()),
+            Err(error) => {
+                failed += 1;
+                eprintln!("please: prompt {} failed: {error}", index + 1);
+                // The connection may be what broke; start the next prompt on a fresh one.
+                let connecting_at = Instant::now();
+                *stream = super::connect::obtain_control_stream().await?;
+                connected_in = connecting_at.elapsed();
+                continue;
+            }
+        };
+        if let Some(dir) = out_dir {
+            let file = dir.join(format!("{:04}.md", index + 1));
+            std::fs::write(&file, answer)
+                .map_err(|error| eyre!("cannot write {}: {error}", file.display()))?;
+        }
+        if crate::config::get().output != crate::config::OutputFormat::Json {
+            println!();
+        }
+    }
+    if failed > 0 {
+        return Err(eyre!("{failed} of {} prompts failed", prompts.len()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompts_come_from_lines_or_a_json_array() {
+        let prompts = read_prompts("summarize a.rs\n\n  explain b.rs  \n", false).unwrap();
+        assert_eq!(prompts, ["summarize a.rs", "explain b.rs"]);
+        let prompts = read_prompts(r#"["one\ntwo", "three"]"#, true).unwrap();
+        assert_eq!(prompts, ["one\ntwo", "three"]);
+        assert!(read_prompts("[not json", true).is_err());
+    }
+
+    #[test]
+    fn text_batches_may_start_with_a_bracket() {
+        let prompts = read_prompts(
+            "[draft] summarize x
+explain y
+",
+            false,
+        )
+        .unwrap();
+        assert_eq!(prompts, ["[draft] summarize x", "explain y"]);
+    }
+}
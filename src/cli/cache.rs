@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use crate::protocol::Frame;
+
+/// Replies to earlier requests, stored as the frames the hub streamed for them.
+/// Each subturn is cached on its own, so tools still run and their fresh results
+/// decide whether the next subturn can be replayed as well.
+pub struct ResponseCache {
+    dir: PathBuf,
+    /// Identifies the weights the hub would load, so switching or rewriting them misses the cache.
+    model: String,
+}
+
+impl ResponseCache {
+    /// Open the cache when enabled in the config.
+    pub fn open() -> Option<Self> {
+        if !crate::config::get().cache {
+            return None;
+        }
+        let model = crate::hub::select_model_path()
+            .and_then(|path| crate::inference::model_identity(&path))
+            .unwrap_or_default();
+        Some(Self {
+            dir: crate::inference::cache_dir()?,
+            model,
+        })
+    }
+
+    /// Key for an encoded request, which already carries the history and sampling options.
+    pub fn key(&self, request: &[u8]) -> String {
+        crate::inference::content_key(&self.model, request)
+    }
+
+    pub fn load(&self, key: &str) -> Option<Vec<Frame>> {
+        let bytes = std::fs::read(self.dir.join(key)).ok()?;
+        match postcard::from_bytes(&bytes) {
+            Ok(frames) => Some(frames),
+            Err(error) => {
+                tracing::debug!(%key, %error, "cache: ignoring unreadable entry");
+                None
+            }
+        }
+    }
+
+    /// Store frames for a key; failures only cost a future cache hit.
+    pub fn store(&self, key: &str, frames: &[Frame]) {
+        let Ok(bytes) = postcard::to_allocvec(frames) else {
+            return;
+        };
+        let _ = std::fs::create_dir_all(&self.dir);
+        // Write aside and rename so a concurrent reader never sees a partial entry.
+        let staging = self.dir.join(format!("{key}.{}.tmp", std::process::id()));
+        if std::fs::write(&staging, bytes).is_ok()
+            && std::fs::rename(&staging, self.dir.join(key)).is_err()
+        {
+            let _ = std::fs::remove_file(&staging);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_frames_replay_under_the_same_key_only() {
+        let dir = std::env::temp_dir().join(format!("please-cache-{}", std::process::id()));
+        let cache = ResponseCache {
+            dir: dir.clone(),
+            model: "model.gguf:1:0".into(),
+        };
+        let key = cache.key(b"request");
+        assert_ne!(key, cache.key(b"other request"));
+
+        cache.store(&key, &[Frame::Answer("hi".into()), Frame::Stop]);
+        let frames = cache.load(&key).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(matches!(&frames[..], [Frame::Answer(text), Frame::Stop] if text == "hi"));
+        assert!(cache.load(&cache.key(b"other request")).is_none());
+    }
+}
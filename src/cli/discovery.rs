@@ -32,6 +32,14 @@ fn candidate_roots() -> Vec<PathBuf> {
     roots
 }
 
+/// Build output and dependency trees never hold weights and can be huge; same set as `list_files`.
+fn is_excluded_dir(name: &str) -> bool {
+    matches!(
+        name,
+        "target" | "node_modules" | "dist" | "build" | "lib" | "out"
+    )
+}
+
 fn collect_local_gguf_candidates(root: &Path, max_depth: usize, out: &mut Vec<Candidate>) {
     if max_depth < 1 {
         return;
@@ -49,17 +57,34 @@ fn collect_local_gguf_candidates(root: &Path, max_depth: usize, out: &mut Vec<Ca
                     mtime: meta.modified().unwrap_or(UNIX_EPOCH),
                 });
             }
-        } else if meta.is_dir() {
+        } else if meta.is_dir() && !is_excluded_dir(&entry.file_name().to_string_lossy()) {
             collect_local_gguf_candidates(&path, max_depth - 1, out);
         }
     }
 }
 
+/// Stable link that `please load` points at the model it downloaded last.
+pub fn current_model_link_path() -> Option<PathBuf> {
+    let home = std::env::var("HOME").ok()?;
+    Some(
+        Path::new(&home)
+            .join(".please")
+            .join("weights")
+            .join("current.gguf"),
+    )
+}
+
 pub fn choose_best_model_path() -> Option<PathBuf> {
+    // An explicit active model wins over guessing by size and age.
+    if let Some(link) = current_model_link_path().filter(|link| link.is_file()) {
+        tracing::trace!(path=%link.display(), "discovery: using the current model link");
+        return Some(link);
+    }
+
     let mut candidates: Vec<Candidate> = Vec::new();
 
     for root in candidate_roots() {
-        collect_local_gguf_candidates(&root, 4, &mut candidates);
+        collect_local_gguf_candidates(&root, crate::config::get().discovery_depth, &mut candidates);
     }
 
     if candidates.is_empty() {
@@ -74,3 +99,29 @@ pub fn choose_best_model_path() -> Option<PathBuf> {
 
     candidates.into_iter().next().map(|c| c.path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discovery_skips_build_trees_and_stops_at_the_depth() {
+        let dir = std::env::temp_dir().join(format!("please-discovery-{}", std::process::id()));
+        for sub in ["node_modules/pkg", "models", "a/b/c"] {
+            fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        for file in [
+            "node_modules/pkg/gpt-oss-20b.gguf",
+            "models/gpt-oss-20b.gguf",
+            "a/b/c/gpt-oss-120b.gguf",
+        ] {
+            fs::write(dir.join(file), b"weights").unwrap();
+        }
+
+        let mut found = Vec::new();
+        collect_local_gguf_candidates(&dir, 3, &mut found);
+        let _ = fs::remove_dir_all(&dir);
+        let found = found.into_iter().map(|c| c.path).collect::<Vec<_>>();
+        assert_eq!(found, [dir.join("models/gpt-oss-20b.gguf")]);
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::protocol::Message;
+
+/// Read at most this much of each pinned file so one large file cannot crowd out the context.
+const MAX_PINNED_BYTES: u64 = 64 * 1024;
+
+/// Files whose current content is shown to the model before every subturn of a session.
+#[derive(Debug, Clone, Default)]
+pub struct Pins {
+    paths: Vec<PathBuf>,
+}
+
+impl Pins {
+    pub fn new(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mut pins = Self::default();
+        for path in paths {
+            pins.pin(path);
+        }
+        pins
+    }
+
+    /// Add a path; pinning the same path twice keeps one entry.
+    pub fn pin(&mut self, path: PathBuf) {
+        if !self.paths.contains(&path) {
+            self.paths.push(path);
+        }
+    }
+
+    /// Remove a path; returns false if it was not pinned.
+    pub fn unpin(&mut self, path: &Path) -> bool {
+        let before = self.paths.len();
+        self.paths.retain(|pinned| pinned != path);
+        self.paths.len() != before
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Re-read every pinned file now and describe them in one developer message.
+    /// Files whose content already stands as a developer message in `history`,
+    /// such as the same file piped through stdin, are left out rather than sent twice.
+    pub fn render(&self, history: &[Message]) -> Option<Message> {
+        let mut text = String::from(
+            "The user pinned these files; this is their current content, read just now.\n",
+        );
+        let mut rendered_any = false;
+        for path in &self.paths {
+            match read_prefix(path) {
+                Ok((content, _)) if is_already_present(history, &content) => continue,
+                Ok((content, truncated)) => {
+                    text.push_str(&format!("\n`{}`:\n```\n{content}", path.display()));
+                    if !content.is_empty() && !content.ends_with('\n') {
+                        text.push('\n');
+                    }
+                    text.push_str("```\n");
+                    if truncated {
+                        text.push_str(&format!(
+                            "(only the first {MAX_PINNED_BYTES} bytes are shown)\n"
+                        ));
+                    }
+                }
+                Err(error) => {
+                    text.push_str(&format!("\n`{}`: cannot read: {error}\n", path.display()));
+                }
+            }
+            rendered_any = true;
+        }
+        rendered_any.then_some(Message::Developer(text))
+    }
+}
+
+fn is_already_present(history: &[Message], content: &str) -> bool {
+    let content = content.trim();
+    !content.is_empty()
+        && history
+            .iter()
+            .any(|message| matches!(message, Message::Developer(text) if text.trim() == content))
+}
+
+/// Show pinned files right after the leading system and developer preamble.
+/// While their content is unchanged the rendered prompt keeps the same prefix from turn to turn,
+/// so the hub can reuse it instead of tokenizing and evaluating the files again.
+pub fn inject_pinned(messages: &mut Vec<Message>, pins: &Pins) {
+    let Some(pinned) = pins.render(messages) else {
+        return;
+    };
+    let at = messages
+        .iter()
+        .position(|message| !matches!(message, Message::System(_) | Message::Developer(_)))
+        .unwrap_or(messages.len());
+    messages.insert(at, pinned);
+}
+
+fn read_prefix(path: &Path) -> std::io::Result<(String, bool)> {
+    let file = std::fs::File::open(path)?;
+    let mut buf = Vec::new();
+    file.take(MAX_PINNED_BYTES + 1).read_to_end(&mut buf)?;
+    let truncated = buf.len() as u64 > MAX_PINNED_BYTES;
+    buf.truncate(MAX_PINNED_BYTES as usize);
+    Ok((String::from_utf8_lossy(&buf).to_string(), truncated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_content_is_read_fresh_kept_up_front_and_not_repeated() {
+        let path = std::env::temp_dir().join(format!("please-pin-{}.txt", std::process::id()));
+        let pins = Pins::new([path.clone(), path.clone()]);
+        assert_eq!(pins.paths().len(), 1);
+
+        std::fs::write(&path, "first").unwrap();
+        assert!(
+            matches!(pins.render(&[]), Some(Message::Developer(text)) if text.contains("first"))
+        );
+
+        let mut messages = vec![
+            Message::System("system".into()),
+            Message::User("edit it".into()),
+            Message::Assistant("done".into()),
+            Message::User("again".into()),
+        ];
+        std::fs::write(&path, "second").unwrap();
+        inject_pinned(&mut messages, &pins);
+
+        let Message::Developer(text) = &messages[1] else {
+            panic!("expected pinned files right after the preamble");
+        };
+        assert!(text.contains("second") && !text.contains("first"));
+        assert!(matches!(messages[2], Message::User(_)));
+
+        // The same content piped in through stdin is not repeated.
+        let history = [Message::Developer("second\n".into())];
+        let rendered = pins.render(&history);
+        let _ = std::fs::remove_file(&path);
+        assert!(rendered.is_none());
+    }
+}
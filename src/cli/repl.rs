@@ -1,22 +1,27 @@
 use eyre::{Result, eyre};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::net::UnixStream;
 
 use crate::display::Display;
 use crate::protocol::Message;
 
 use super::connect::obtain_control_stream;
+use super::pins::Pins;
 use super::turn::run_turn;
 
 pub async fn interact_forever(
     stream: &mut UnixStream,
     display: Arc<Display>,
     history: Vec<Message>,
+    pins: Pins,
+    mut connected_in: Duration,
 ) -> Result<()> {
     use rustyline::error::ReadlineError::{Eof, Interrupted};
 
     let mut rl = rustyline::DefaultEditor::new().map_err(|e| eyre!(e))?;
     let mut history = history;
+    let mut pins = pins;
     loop {
         let line = match rl.readline(">> ") {
             Ok(line) => line,
@@ -25,18 +30,77 @@ pub async fn interact_forever(
         };
         let line = line.trim();
         if line.is_empty() {
-            break;
+            // An accidental Enter should not end the session; `/quit` or Ctrl-D does.
+            continue;
         }
         rl.add_history_entry(line).ok();
 
+        if matches!(line, "/quit" | "/exit") {
+            break;
+        }
+
+        if let Some(path) = line.strip_prefix("/pin ") {
+            pins.pin(path.trim().into());
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("/unpin ") {
+            if !pins.unpin(std::path::Path::new(path.trim())) {
+                eprintln!("not pinned: {}", path.trim());
+            }
+            continue;
+        }
+        if line == "/pins" {
+            for path in pins.paths() {
+                eprintln!("{}", path.display());
+            }
+            continue;
+        }
+
+        if line == "/more" {
+            // Resubmit the history as is; the hub continues a trailing answer in place.
+            let Some(Message::Assistant(_)) = history.last() else {
+                eprintln!("nothing to continue");
+                continue;
+            };
+            let connect = std::mem::take(&mut connected_in);
+            let turn = run_turn(stream, display.clone(), history.clone(), &pins, connect);
+            let more = match turn.await {
+                Ok(more) => more,
+                Err(error) if super::turn::is_cancelled(&error) => {
+                    eprintln!();
+                    let connecting_at = Instant::now();
+                    *stream = obtain_control_stream().await?;
+                    connected_in = connecting_at.elapsed();
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+            eprintln!();
+            if let Some(Message::Assistant(answer)) = history.last_mut() {
+                answer.push_str(&more);
+            }
+            continue;
+        }
+
         let mut turn_history = history.clone();
         turn_history.push(Message::User(line.to_string()));
+        display.record_prompt(line).await;
 
-        let answer = match run_turn(stream, display.clone(), turn_history.clone()).await {
+        let connect = std::mem::take(&mut connected_in);
+        let turn = run_turn(
+            stream,
+            display.clone(),
+            turn_history.clone(),
+            &pins,
+            connect,
+        );
+        let answer = match turn.await {
             Ok(answer) => answer,
             Err(error) if super::turn::is_cancelled(&error) => {
                 eprintln!();
+                let connecting_at = Instant::now();
                 *stream = obtain_control_stream().await?;
+                connected_in = connecting_at.elapsed();
                 continue;
             }
             Err(error) => return Err(error),
@@ -46,5 +110,6 @@ pub async fn interact_forever(
         history = turn_history;
         history.push(Message::Assistant(answer));
     }
+    display.show_session_summary().await;
     Ok(())
 }
@@ -1,4 +1,4 @@
-use eyre::Result;
+use eyre::{Result, eyre};
 use std::sync::Arc;
 
 use crate::cli::io;
@@ -8,6 +8,7 @@ use crate::history;
 use crate::protocol::Message;
 
 use super::connect::obtain_control_stream;
+use super::pins::Pins;
 use super::repl::interact_forever;
 use super::turn::run_turn;
 
@@ -19,9 +20,57 @@ fn start_display() -> Result<Arc<display::Display>> {
     Ok(display)
 }
 
+/// Run a `--context-cmd` through the shell and describe its output for the model.
+async fn command_context(command: &str) -> Message {
+    let argv = ["sh", "-c", command].map(str::to_string).to_vec();
+    let result = crate::tools::run_for_context(argv).await;
+    Message::Developer(describe_command_output(command, &result))
+}
+
+fn describe_command_output(command: &str, result: &serde_json::Value) -> String {
+    if let Some(error) = result.get("error").and_then(|error| error.as_str()) {
+        return format!("The user's context command `{command}` could not run: {error}");
+    }
+    let field = |name: &str| {
+        result
+            .get(name)
+            .and_then(|value| value.as_str())
+            .unwrap_or("")
+    };
+    let note = match result.get("exitCode").and_then(|code| code.as_i64()) {
+        _ if field("status") != "finished" => " (stopped before it finished)".to_string(),
+        Some(0) => String::new(),
+        Some(code) => format!(" (exit code {code})"),
+        None => " (killed by a signal)".to_string(),
+    };
+    let mut text = format!(
+        "The user ran `{command}` for context{note}. Its output:\n{}",
+        field("stdout").trim_end()
+    );
+    let stderr = field("stderr").trim_end();
+    if !stderr.is_empty() {
+        text.push_str("\nstderr:\n");
+        text.push_str(stderr);
+    }
+    text
+}
+
+/// The user turn when stdin is what the prompt is about: the prompt, then the piped input.
+fn prompt_with_stdin(prompt: &str, stdin: &str) -> String {
+    let stdin = stdin.trim();
+    if prompt.trim().is_empty() {
+        return stdin.to_string();
+    }
+    format!("{prompt}\n\nThe input, piped in on stdin:\n{stdin}")
+}
+
 /// CLI entrypoint: decide between hub mode, REPL, or one-shot batch prompt.
 /// Keeps top-level flow readable while deferring details to real implementations.
 pub async fn run() -> Result<()> {
+    // Split leading flags off the command line before anything reads the config.
+    let (config, words) = crate::config::Config::from_args(std::env::args().skip(1));
+    crate::config::init(config);
+
     // Start display; all user-visible output goes through it
     let display = start_display()?;
 
@@ -37,20 +86,43 @@ pub async fn run() -> Result<()> {
     let stdout_redirection_path =
         (!stdout_is_tty).then(|| io::stdout_redirection_path().unwrap_or_default());
     let stdin_content = io::read_whole_stdin()?;
-    let mut history = history::make_history(stdin_content, stdout_redirection_path);
-
-    // Build prompt from positional CLI args; if none provided, leave empty to enable REPL.
-    // Collect positional args into a single prompt. If none provided, drop into REPL.
-    let prompt = {
-        let mut args = std::env::args();
-        let _ = args.next(); // binary name
-        let collected: String = args.collect::<Vec<String>>().join(" ");
-        collected
+    let has_stdin = stdin_content
+        .as_deref()
+        .is_some_and(|content| !content.trim().is_empty());
+    // Batches keep stdin as context: it is shared by every prompt in the file.
+    let stdin_is_subject = crate::config::get().stdin_role == crate::config::StdinRole::Subject
+        && crate::config::get().batch.is_none();
+    let (stdin_context, stdin_subject) = if stdin_is_subject {
+        (None, stdin_content.filter(|_| has_stdin))
+    } else {
+        (stdin_content, None)
     };
+    let mut history = history::make_history(stdin_context, stdout_redirection_path);
+    for command in &crate::config::get().context_cmds {
+        history.push(command_context(command).await);
+    }
+
+    // Collect positional args (after flags) into a single prompt. If none provided, drop into REPL.
+    let prompt = words.join(" ");
+    let pins = Pins::new(crate::config::get().pinned.iter().cloned());
+    let interactive = stdout_is_tty && stderr_is_tty && stdin_is_tty && prompt.is_empty();
+
+    // Asking the model about nothing only yields a puzzling answer.
+    if !interactive
+        && prompt.trim().is_empty()
+        && !has_stdin
+        && crate::config::get().batch.is_none()
+    {
+        return Err(eyre!(
+            "no prompt provided; try `please fix the failing test` or `please help`"
+        ));
+    }
 
     // Connect to the hub, maybe starting a new hub process if necessary.
     let little_snake = display.start_spinning().await;
+    let connecting_at = std::time::Instant::now();
     let stream = obtain_control_stream().await;
+    let connected_in = connecting_at.elapsed();
     little_snake.stop().await;
 
     // If there are no weights, show the onboarding and exit.
@@ -62,14 +134,33 @@ pub async fn run() -> Result<()> {
         Ok(stream) => stream,
     };
 
+    if let Some(batch) = &crate::config::get().batch {
+        let out_dir = crate::config::get().batch_out.as_deref();
+        return super::batch::run_batch(
+            &mut stream,
+            display,
+            history,
+            &pins,
+            batch,
+            out_dir,
+            connected_in,
+        )
+        .await;
+    }
+
     // Choose between interactive and batch mode.
     // Step into interactive mode only when both stdout and stderr are teletype devices and the user provided no prompt.
-    if stdout_is_tty && stderr_is_tty && stdin_is_tty && prompt.is_empty() {
-        interact_forever(&mut stream, display, history).await?
+    if interactive {
+        interact_forever(&mut stream, display, history, pins, connected_in).await?
     } else {
         // One-shot: append the user turn to the initial history and infer once.
-        history.push(Message::User(prompt.to_string()));
-        match run_turn(&mut stream, display, history).await {
+        let user_turn = match &stdin_subject {
+            Some(stdin) => prompt_with_stdin(&prompt, stdin),
+            None => prompt.to_string(),
+        };
+        history.push(Message::User(user_turn));
+        display.record_prompt(&prompt).await;
+        match run_turn(&mut stream, display, history, &pins, connected_in).await {
             Ok(_) => {}
             Err(error) if super::turn::is_cancelled(&error) => return Ok(()),
             Err(error) => return Err(error),
@@ -78,3 +169,31 @@ pub async fn run() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn piped_subjects_follow_the_prompt_in_the_user_turn() {
+        assert_eq!(
+            prompt_with_stdin("summarize", "line one\nline two\n"),
+            "summarize\n\nThe input, piped in on stdin:\nline one\nline two"
+        );
+        assert_eq!(prompt_with_stdin(" ", "just this\n"), "just this");
+    }
+
+    #[test]
+    fn command_output_is_described_with_its_exit_status() {
+        let result = serde_json::json!({
+            "status": "finished",
+            "exitCode": 1,
+            "stdout": "a1 fix\n",
+            "stderr": "warning\n",
+        });
+        assert_eq!(
+            describe_command_output("git log", &result),
+            "The user ran `git log` for context (exit code 1). Its output:\na1 fix\nstderr:\nwarning"
+        );
+    }
+}
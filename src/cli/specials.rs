@@ -1,6 +1,12 @@
 use eyre::Result;
 
+mod config;
+mod doctor;
+mod hub;
 mod load;
+mod tools;
+mod update;
+mod verify;
 
 /// Handle special one-shot CLI commands like `--help`, `--version`, or `load`.
 /// Returns true if a special action was handled and the program should exit.
@@ -52,9 +58,55 @@ pub async fn handle_specials_if_needed() -> Result<bool> {
         return Ok(true);
     }
 
+    if matches!(arg.as_str(), "hub") {
+        let action = args.next();
+        hub::run_hub_command(action.as_deref()).await?;
+        return Ok(true);
+    }
+
+    // Flags may follow, as in `please config --no-tools`; any other word makes it a prompt.
+    if matches!(arg.as_str(), "config") {
+        let rest = args.collect::<Vec<_>>();
+        if !rest.iter().all(|word| word.starts_with("--")) {
+            return Ok(false);
+        }
+        config::run_config(rest);
+        return Ok(true);
+    }
+
+    if matches!(arg.as_str(), "doctor") && args.len() == 0 {
+        doctor::run_doctor().await?;
+        return Ok(true);
+    }
+
     if matches!(arg.as_str(), "load" | "download") {
-        let which = args.next();
-        load::run_load(which.as_deref()).await?;
+        let (flags, rest): (Vec<String>, Vec<String>) = args.partition(|a| a.starts_with("--"));
+        let keep_shards = flags.iter().any(|flag| flag == "--keep-shards");
+        let verify = !flags.iter().any(|flag| flag == "--no-verify");
+        load::run_load(rest.first().map(String::as_str), keep_shards, verify).await?;
+        return Ok(true);
+    }
+
+    if matches!(arg.as_str(), "tools") && args.len() == 0 {
+        tools::run_tools();
+        return Ok(true);
+    }
+
+    // Only on its own, so that a prompt like `please update the README` still reaches the model.
+    if matches!(arg.as_str(), "update") && args.len() == 0 {
+        update::run_update().await?;
+        return Ok(true);
+    }
+
+    // Alone or with a model name; `please verify the tests pass` is a prompt.
+    if matches!(arg.as_str(), "verify") {
+        let rest = args.collect::<Vec<_>>();
+        let which = match &rest[..] {
+            [] => None,
+            [which] if load::is_model_name(which) => Some(which.as_str()),
+            _ => return Ok(false),
+        };
+        verify::run_verify(which).await?;
         return Ok(true);
     }
 
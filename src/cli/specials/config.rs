@@ -0,0 +1,15 @@
+use crate::config::Config;
+
+/// Print every resolved setting as `key=value (source)`, one per line.
+/// Flags given after `config` are applied, so `please config --no-tools` shows their effect.
+pub fn run_config(args: impl IntoIterator<Item = String>) {
+    let (config, _) = Config::from_args(args);
+    for setting in config.settings() {
+        println!("{}={} ({})", setting.key, setting.value, setting.source);
+    }
+    // The model a hub would load: the one it recorded last, otherwise discovery's pick.
+    match crate::hub::select_model_path() {
+        Some(path) => println!("model={} (hub)", path.display()),
+        None => println!("model=unset (hub)"),
+    }
+}
@@ -0,0 +1,220 @@
+use eyre::Result;
+use std::io::Read;
+use std::path::Path;
+
+use crate::cli::connect::{ConnectError, try_connect_to_hub};
+
+/// Outcome of one diagnostic check.
+enum Verdict {
+    Pass,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    name: &'static str,
+    verdict: Verdict,
+    detail: String,
+    /// What the user can do about a warning or failure.
+    hint: Option<&'static str>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            verdict: Verdict::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, hint: &'static str) -> Self {
+        Self {
+            name,
+            verdict: Verdict::Warn,
+            detail: detail.into(),
+            hint: Some(hint),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: &'static str) -> Self {
+        Self {
+            name,
+            verdict: Verdict::Fail,
+            detail: detail.into(),
+            hint: Some(hint),
+        }
+    }
+}
+
+/// Entry point for `please doctor`: check the environment and suggest fixes.
+pub async fn run_doctor() -> Result<()> {
+    let checks = [
+        check_home_dir(),
+        check_weights(),
+        check_gpu(),
+        check_hub().await,
+        check_backend(),
+    ];
+
+    let mut failed = false;
+    for check in &checks {
+        let label = match check.verdict {
+            Verdict::Pass => "ok  ",
+            Verdict::Warn => "warn",
+            Verdict::Fail => "FAIL",
+        };
+        failed |= matches!(check.verdict, Verdict::Fail);
+        println!("{label} {}: {}", check.name, check.detail);
+        if let Some(hint) = check.hint {
+            println!("     {hint}");
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn please_dir() -> std::path::PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| String::from("."));
+    Path::new(&home).join(".please")
+}
+
+fn check_home_dir() -> Check {
+    const NAME: &str = "~/.please";
+    let dir = please_dir();
+    let meta = match std::fs::metadata(&dir) {
+        Ok(meta) => meta,
+        Err(_) => {
+            return Check::pass(
+                NAME,
+                format!("{} will be created on first use", dir.display()),
+            );
+        }
+    };
+    if !meta.is_dir() {
+        return Check::fail(
+            NAME,
+            format!("{} is not a directory", dir.display()),
+            "move the file away so please can create its directory",
+        );
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = meta.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            return Check::warn(
+                NAME,
+                format!("{} is accessible by others (mode {mode:o})", dir.display()),
+                "run `chmod 700 ~/.please`",
+            );
+        }
+    }
+    let probe = dir.join(".please-write-probe");
+    if std::fs::write(&probe, b"").is_err() {
+        return Check::fail(
+            NAME,
+            format!("{} is not writable", dir.display()),
+            "fix ownership with `chown -R $USER ~/.please`",
+        );
+    }
+    let _ = std::fs::remove_file(&probe);
+    Check::pass(NAME, format!("{} is private and writable", dir.display()))
+}
+
+fn check_weights() -> Check {
+    const NAME: &str = "weights";
+    let Some(path) = crate::cli::discovery::choose_best_model_path() else {
+        return Check::fail(NAME, "no gpt-oss .gguf file found", "run `please load`");
+    };
+    if !has_gguf_magic(&path) {
+        return Check::fail(
+            NAME,
+            format!("{} is not a valid GGUF file", path.display()),
+            "delete it and run `please load` again",
+        );
+    }
+    Check::pass(NAME, path.display().to_string())
+}
+
+/// GGUF files start with the ASCII magic `GGUF`; anything else is a truncated or foreign file.
+fn has_gguf_magic(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok()
+        && magic == *b"GGUF"
+}
+
+fn check_gpu() -> Check {
+    const NAME: &str = "gpu";
+    match crate::inference::vram_free_bytes() {
+        Some(free) => Check::pass(NAME, format!("{:.1} GB free", free as f64 / 1e9)),
+        None => Check::warn(
+            NAME,
+            "no GPU memory detected",
+            "inference will be slow; check GPU drivers (nvidia-smi, rocm-smi)",
+        ),
+    }
+}
+
+async fn check_hub() -> Check {
+    const NAME: &str = "hub";
+    let path = crate::hub::socket_path();
+    match try_connect_to_hub(&path).await {
+        Ok(_) => Check::pass(NAME, format!("running at {}", path.display())),
+        Err(ConnectError::Missing { .. }) => {
+            Check::pass(NAME, "not running; it starts on the next prompt")
+        }
+        Err(ConnectError::NoListener { path }) => Check::warn(
+            NAME,
+            format!("stale socket at {}", path.display()),
+            "it is cleaned up when the hub starts; `please run` starts one in the foreground",
+        ),
+        Err(error @ ConnectError::PermissionDenied { .. }) => Check::fail(
+            NAME,
+            error.to_string(),
+            "the socket belongs to another user; fix ownership of ~/.please",
+        ),
+        Err(error @ ConnectError::NotSocket { .. }) => Check::fail(
+            NAME,
+            error.to_string(),
+            "remove the file at the socket path",
+        ),
+    }
+}
+
+fn check_backend() -> Check {
+    const NAME: &str = "llama.cpp";
+    match gg::llama_backend::LlamaBackend::init() {
+        Ok(_backend) => Check::pass(NAME, "backend initializes"),
+        Err(error) => Check::fail(
+            NAME,
+            format!("backend failed to initialize: {error}"),
+            "reinstall please; the bundled llama.cpp may not match this machine",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gguf_magic_distinguishes_weights_from_other_files() {
+        let dir = std::env::temp_dir().join(format!("please-doctor-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let weights = dir.join("model.gguf");
+        let partial = dir.join("partial.gguf");
+        std::fs::write(&weights, b"GGUF\x03\x00\x00\x00").unwrap();
+        std::fs::write(&partial, b"<html>").unwrap();
+
+        assert!(has_gguf_magic(&weights));
+        assert!(!has_gguf_magic(&partial));
+        assert!(!has_gguf_magic(&dir.join("missing.gguf")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,41 @@
+use eyre::{Result, eyre};
+
+use crate::cli::connect::try_connect_to_hub;
+use crate::protocol::{Frame, read_frame_from_stream, write_frame_to_stream};
+
+/// Entry point for `please hub <action>`: control an already running hub.
+pub async fn run_hub_command(action: Option<&str>) -> Result<()> {
+    let request = match action.unwrap_or("status") {
+        "status" => Frame::Status,
+        "reload" => Frame::Reload,
+        other => {
+            return Err(eyre!(
+                "please hub: unknown action `{other}`; expected `status` or `reload`"
+            ));
+        }
+    };
+
+    let path = crate::hub::socket_path();
+    let mut stream = match try_connect_to_hub(&path).await {
+        Ok(stream) => stream,
+        Err(error) => {
+            eprintln!("please hub: no running hub ({error})");
+            return Ok(());
+        }
+    };
+
+    write_frame_to_stream(&mut stream, &request).await?;
+    let mut store = Vec::with_capacity(4096);
+    loop {
+        let frame: Frame = read_frame_from_stream(&mut stream, &mut store, None, None)
+            .await
+            .map_err(|error| eyre!(error))?;
+        match frame {
+            Frame::Log(line) => println!("{}", line.trim_end()),
+            Frame::Stop => break,
+            Frame::Error(message) => return Err(eyre!(message)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -1,12 +1,17 @@
 use eyre::{Result, eyre};
-use futures_util::{StreamExt, future::try_join_all};
-use std::io::Write;
+use futures_util::{StreamExt, future::join_all};
+use sha2::{Digest, Sha256};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::io::AsyncWriteExt;
 
+mod progress;
+
+use progress::Progress;
+
+use super::verify::fetch_expected;
+
 /// Return the local directory where model weight files are stored.
-fn weights_dir() -> std::path::PathBuf {
+pub(super) fn weights_dir() -> std::path::PathBuf {
     let home_directory = std::env::var("HOME").unwrap_or_else(|_| String::from("."));
     std::path::Path::new(&home_directory)
         .join(".please")
@@ -26,14 +31,62 @@ fn ensure_dir(path: &std::path::Path) -> Result<()> {
     Ok(())
 }
 
-/// Pick the appropriate repository and shard list based on a user-friendly alias.
-fn pick_repository(which: Option<&str>) -> (&'static str, &'static [&'static str]) {
-    let key = which.map(|s| s.trim()).unwrap_or("20b");
-    let key = key
-        .trim_matches(|c: char| !c.is_ascii_alphanumeric())
-        .to_ascii_lowercase();
+/// Format a byte count as gigabytes for user-facing messages.
+fn human_gb(bytes: u64) -> String {
+    format!("{:.1} GB", bytes as f64 / 1e9)
+}
+
+/// Free bytes available to unprivileged users on the filesystem holding `path`.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+fn free_bytes(path: &std::path::Path) -> Option<u64> {
+    let stats = nix::sys::statvfs::statvfs(path).ok()?;
+    Some(stats.blocks_available() as u64 * stats.fragment_size() as u64)
+}
+
+#[cfg(not(unix))]
+fn free_bytes(_path: &std::path::Path) -> Option<u64> {
+    None
+}
+
+/// Check that a file can be created in `dir`, so a read-only mount fails before downloading.
+fn ensure_writable(dir: &std::path::Path) -> Result<()> {
+    let probe = dir.join(".please-write-probe");
+    std::fs::write(&probe, b"")
+        .map_err(|e| eyre!("please load: cannot write to {}: {e}", dir.display()))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
 
-    match key.as_str() {
+/// Error out when `needed` bytes will not fit into `available`.
+fn ensure_enough_space(needed: u64, available: u64) -> Result<()> {
+    if needed > available {
+        return Err(eyre!(
+            "please load: need {} free, have {}",
+            human_gb(needed),
+            human_gb(available)
+        ));
+    }
+    Ok(())
+}
+
+/// Atomically point `link` at `target`, replacing any previous link.
+#[cfg(unix)]
+fn point_link_at(link: &std::path::Path, target: &std::path::Path) -> std::io::Result<()> {
+    let staging = link.with_extension("gguf.tmp");
+    let _ = std::fs::remove_file(&staging);
+    std::os::unix::fs::symlink(target, &staging)?;
+    std::fs::rename(&staging, link)
+}
+
+#[cfg(not(unix))]
+fn point_link_at(_link: &std::path::Path, _target: &std::path::Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Pick the appropriate repository and shard list based on a user-friendly alias.
+pub(super) fn pick_repository(which: Option<&str>) -> (&'static str, &'static [&'static str]) {
+    match model_key(which.unwrap_or("20b")).as_str() {
         "120b" | "120" | "big" | "large" => (
             "ggml-org/gpt-oss-120b-GGUF",
             &[
@@ -46,21 +99,43 @@ fn pick_repository(which: Option<&str>) -> (&'static str, &'static [&'static str
     }
 }
 
+/// Whether `which` names one of the models `pick_repository` knows, rather than falling back.
+pub(super) fn is_model_name(which: &str) -> bool {
+    matches!(
+        model_key(which).as_str(),
+        "20b" | "20" | "small" | "120b" | "120" | "big" | "large"
+    )
+}
+
+fn model_key(which: &str) -> String {
+    which
+        .trim()
+        .trim_matches(|c: char| !c.is_ascii_alphanumeric())
+        .to_ascii_lowercase()
+}
+
 /// Build a configured HTTP client with a descriptive User-Agent.
-fn build_http_client() -> Result<reqwest::Client> {
+pub(super) fn build_http_client(redirect: reqwest::redirect::Policy) -> Result<reqwest::Client> {
     let mut headers = reqwest::header::HeaderMap::new();
     headers.insert(
         reqwest::header::USER_AGENT,
         reqwest::header::HeaderValue::from_static(concat!("please/", env!("CARGO_PKG_VERSION"))),
     );
-    let client = reqwest::Client::builder()
+    let mut builder = reqwest::Client::builder()
         .default_headers(headers)
-        .tls_backend_rustls()
-        .build()?;
+        .redirect(redirect)
+        .tls_backend_rustls();
+    // reqwest already honors HTTPS_PROXY, ALL_PROXY and NO_PROXY; an explicit proxy replaces them.
+    if let Some(proxy) = &crate::config::get().proxy {
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|e| eyre!("please load: bad PLEASE_PROXY `{proxy}`: {e}"))?;
+        builder = builder.proxy(proxy);
+    }
+    let client = builder.build()?;
     Ok(client)
 }
 
-fn shard_url(repository: &str, shard: &str) -> String {
+pub(super) fn shard_url(repository: &str, shard: &str) -> String {
     format!("https://huggingface.co/{repository}/resolve/main/{shard}")
 }
 
@@ -103,33 +178,8 @@ impl ContentRange {
     }
 }
 
-struct Progress {
-    total: Option<u64>,
-    downloaded: AtomicU64,
-}
-
-impl Progress {
-    fn new(total: Option<u64>) -> Self {
-        Self {
-            total,
-            downloaded: AtomicU64::new(0),
-        }
-    }
-
-    fn add(&self, delta: u64) {
-        let downloaded = self.downloaded.fetch_add(delta, Ordering::Relaxed) + delta;
-        if let Some(total) = self.total {
-            let pct = (downloaded as f64 / total as f64) * 100.0;
-            eprint!("\rplease load: {downloaded}/{total} bytes ({pct:.1}%)");
-        } else {
-            eprint!("\rplease load: {downloaded} bytes");
-        }
-        let _ = std::io::stderr().flush();
-    }
-}
-
 /// Derive a multi-shard target file name by stripping "-<n>-of-<m>" if present.
-fn derive_multishard_target_name(shard_name: &str) -> String {
+pub(super) fn derive_multishard_target_name(shard_name: &str) -> String {
     let of_pos = match shard_name.find("-of-") {
         Some(i) => i,
         None => return shard_name.to_string(),
@@ -197,13 +247,54 @@ async fn get(client: &reqwest::Client, url: &str) -> Result<reqwest::Response> {
     Ok(response)
 }
 
+/// Hash the first `len` bytes of a file, so a resumed download is checked as a whole.
+async fn hash_prefix(path: &std::path::Path, len: u64) -> Result<Sha256> {
+    if len == 0 {
+        return Ok(Sha256::new());
+    }
+    let path = path.to_path_buf();
+    let hasher = tokio::task::spawn_blocking(move || -> std::io::Result<Sha256> {
+        use std::io::Read;
+
+        let mut hasher = Sha256::new();
+        let file = std::fs::File::open(&path)?;
+        std::io::copy(&mut file.take(len), &mut hasher)?;
+        Ok(hasher)
+    })
+    .await??;
+    Ok(hasher)
+}
+
+/// Fail when a finished file does not hash to `want`, removing it so the next run starts clean.
+async fn ensure_digest(path: &std::path::Path, hasher: Sha256, want: &str) -> Result<()> {
+    let got = format!("{:x}", hasher.finalize());
+    if got == want {
+        return Ok(());
+    }
+    let _ = tokio::fs::remove_file(path).await;
+    Err(eyre!(
+        "please load: {} is damaged: sha256 {got}, expected {want}; removed it, run `please load` again",
+        path.display()
+    ))
+}
+
+/// Flips to true on Ctrl-C. Downloads then stop between chunks and sync what they wrote,
+/// so the next run resumes from an offset that is fully on disk.
+type Interrupt = tokio::sync::watch::Receiver<bool>;
+
+const INTERRUPTED: &str = "please load: interrupted; run it again to resume";
+
 /// Download a remote file to `target_path`, resuming from a local partial file when possible.
 /// Robustly handles servers that ignore ranges or respond with 416, and verifies final size when known.
+/// With `sha256`, the whole file, resumed part included, must hash to that digest.
 async fn download_with_resume(
     client: reqwest::Client,
     url: String,
     target_path: std::path::PathBuf,
     progress: Arc<Progress>,
+    shard: usize,
+    sha256: Option<String>,
+    mut interrupt: Interrupt,
 ) -> Result<()> {
     // Determine current size if a partially downloaded file already exists.
     let mut start_offset = 0u64;
@@ -223,7 +314,11 @@ async fn download_with_resume(
             .and_then(|s| s.parse::<u64>().ok());
         if let Some(total) = total_bytes {
             if start_offset == total {
-                eprintln!("please load: already present at {}", target_path.display());
+                progress.resume(shard, total);
+                if let Some(want) = &sha256 {
+                    ensure_digest(&target_path, hash_prefix(&target_path, total).await?, want)
+                        .await?;
+                }
                 return Ok(());
             }
             if start_offset > total {
@@ -253,7 +348,11 @@ async fn download_with_resume(
             let range = ContentRange::parse(content_range_header);
             if let Some(total) = range.total {
                 if start_offset == total {
-                    eprintln!("please load: already present at {}", target_path.display());
+                    progress.resume(shard, total);
+                    if let Some(want) = &sha256 {
+                        ensure_digest(&target_path, hash_prefix(&target_path, total).await?, want)
+                            .await?;
+                    }
                     return Ok(());
                 }
                 if start_offset > total {
@@ -323,15 +422,36 @@ async fn download_with_resume(
         }
     }
 
+    progress.resume(shard, start_offset);
+    let mut hasher = match &sha256 {
+        Some(_) => Some(hash_prefix(&target_path, start_offset).await?),
+        None => None,
+    };
+
     // Open file in the appropriate mode.
     let mut file_handle = open_for_resume(&target_path, start_offset).await?;
 
     let mut stream = response.bytes_stream();
-    while let Some(chunk) = stream.next().await {
+    loop {
+        // Only the wait for the next chunk is raced, so a write is never cut in half.
+        let chunk = tokio::select! {
+            chunk = stream.next() => chunk,
+            Ok(_) = interrupt.wait_for(|interrupted| *interrupted) => {
+                file_handle.flush().await?;
+                file_handle.sync_all().await?;
+                return Err(eyre!(INTERRUPTED));
+            }
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
         let chunk = chunk?;
         let delta = chunk.len() as u64;
         file_handle.write_all(&chunk).await?;
-        progress.add(delta);
+        if let Some(hasher) = &mut hasher {
+            hasher.update(&chunk);
+        }
+        progress.add(shard, delta);
     }
 
     file_handle.flush().await?;
@@ -351,6 +471,10 @@ async fn download_with_resume(
         }
     }
 
+    if let (Some(hasher), Some(want)) = (hasher, &sha256) {
+        ensure_digest(&target_path, hasher, want).await?;
+    }
+
     Ok(())
 }
 
@@ -375,11 +499,19 @@ async fn stitch_shards(
 }
 
 /// Entry point: resolve repository, download shards in parallel, and stitch them into the final file.
-pub async fn run_load(which: Option<&str>) -> Result<()> {
+/// With `keep_shards`, the parts stay on disk so a stitch can be redone without downloading again.
+/// With `verify`, every downloaded file is checked against the SHA-256 the repository publishes.
+pub async fn run_load(which: Option<&str>, keep_shards: bool, verify: bool) -> Result<()> {
     let (repository, shards) = pick_repository(which);
     let weights_directory_path = weights_dir();
-    ensure_dir(&weights_directory_path)?;
-    let client = build_http_client()?;
+    ensure_dir(&weights_directory_path).map_err(|e| {
+        eyre!(
+            "please load: cannot prepare {}: {e}",
+            weights_directory_path.display()
+        )
+    })?;
+    ensure_writable(&weights_directory_path)?;
+    let client = build_http_client(reqwest::redirect::Policy::default())?;
 
     let shard_count = shards.len();
     let first_shard = shards[0];
@@ -429,56 +561,119 @@ pub async fn run_load(which: Option<&str>) -> Result<()> {
         final_name, final_dir
     );
 
-    let total_bytes = {
-        let mut total = Some(0u64);
-        for (url, _) in &shard_jobs {
-            match client.head(url).send().await {
-                Ok(head) if head.status().is_success() => {
-                    if let Some(len) = head
-                        .headers()
-                        .get(reqwest::header::CONTENT_LENGTH)
-                        .and_then(|h| h.to_str().ok())
-                        .and_then(|s| s.parse::<u64>().ok())
-                    {
-                        if let Some(acc) = &mut total {
-                            *acc += len;
-                        }
-                    } else {
-                        total = None;
-                        break;
-                    }
-                }
-                _ => {
-                    total = None;
-                    break;
-                }
+    let mut shard_totals = Vec::with_capacity(shard_jobs.len());
+    for (url, _) in &shard_jobs {
+        let len = match client.head(url).send().await {
+            Ok(head) if head.status().is_success() => head
+                .headers()
+                .get(reqwest::header::CONTENT_LENGTH)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|s| s.parse::<u64>().ok()),
+            _ => None,
+        };
+        shard_totals.push(len);
+    }
+    let total_bytes: Option<u64> = shard_totals.iter().copied().sum();
+
+    let mut digests = vec![None; shard_jobs.len()];
+    if verify {
+        // The LFS headers carrying the digest are on the first response, not the CDN's.
+        let metadata_client = build_http_client(reqwest::redirect::Policy::none())?;
+        for ((url, path), digest) in shard_jobs.iter().zip(&mut digests) {
+            *digest = fetch_expected(&metadata_client, url).await.sha256;
+            if digest.is_none() {
+                return Err(eyre!(
+                    "please load: cannot read the sha256 of {} from huggingface; pass --no-verify to skip the check",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                ));
             }
         }
-        total
-    };
+    }
 
-    let progress = Arc::new(Progress::new(total_bytes));
+    // Resumed bytes are already on disk; stitching needs room for a second full copy.
+    if let (Some(total), Some(available)) = (total_bytes, free_bytes(&weights_directory_path)) {
+        let present: u64 = shard_jobs
+            .iter()
+            .filter_map(|(_, path)| std::fs::metadata(path).ok())
+            .map(|meta| meta.len())
+            .sum();
+        let stitched = if shard_count > 1 { total } else { 0 };
+        ensure_enough_space(total.saturating_sub(present) + stitched, available)?;
+    }
 
-    let download_tasks = shard_jobs.iter().map(|(url, path)| {
-        let client = client.clone();
-        let url = url.clone();
-        let path = path.clone();
+    let progress = Arc::new(Progress::new(
+        shard_jobs
+            .iter()
+            .zip(&shard_totals)
+            .map(|((_, path), total)| {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                (name.into_owned(), *total)
+            })
+            .collect(),
+    ));
+    let ticker = {
         let progress = Arc::clone(&progress);
-        async move { download_with_resume(client, url, path, progress).await }
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(progress::REDRAW_EVERY);
+            loop {
+                interval.tick().await;
+                progress.tick();
+            }
+        })
+    };
+
+    let (interrupt_tx, interrupt) = tokio::sync::watch::channel(false);
+    let listener = tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            let _ = interrupt_tx.send(true);
+        }
     });
 
-    try_join_all(download_tasks).await?;
+    let download_tasks =
+        shard_jobs
+            .iter()
+            .zip(digests)
+            .enumerate()
+            .map(|(shard, ((url, path), sha256))| {
+                let client = client.clone();
+                let url = url.clone();
+                let path = path.clone();
+                let progress = Arc::clone(&progress);
+                let interrupt = interrupt.clone();
+                async move {
+                    download_with_resume(client, url, path, progress, shard, sha256, interrupt)
+                        .await
+                }
+            });
+
+    // Wait for every download to settle so none is dropped in the middle of a write.
+    let downloaded = join_all(download_tasks).await;
+    ticker.abort();
+    progress.finish();
+    downloaded.into_iter().collect::<Result<Vec<()>>>()?;
 
     if shard_count > 1 {
         let shard_paths: Vec<std::path::PathBuf> =
             shard_jobs.iter().map(|(_, path)| path.clone()).collect();
-        stitch_shards(&target_path, &shard_paths).await?;
-        for shard_path in &shard_paths {
-            if let Err(e) = tokio::fs::remove_file(shard_path).await {
-                eprintln!(
-                    "please load: failed to remove {}: {e}",
-                    shard_path.display()
-                );
+        let mut interrupt = interrupt.clone();
+        tokio::select! {
+            stitched = stitch_shards(&target_path, &shard_paths) => stitched?,
+            Ok(_) = interrupt.wait_for(|interrupted| *interrupted) => {
+                // The shards are intact; a half-stitched file is redone from them next time.
+                let _ = tokio::fs::remove_file(&target_path).await;
+                return Err(eyre!(INTERRUPTED));
+            }
+        }
+        if keep_shards {
+            eprintln!("please load: keeping {} shard files", shard_count);
+        } else {
+            for shard_path in &shard_paths {
+                if let Err(e) = tokio::fs::remove_file(shard_path).await {
+                    eprintln!(
+                        "please load: failed to remove {}: {e}",
+                        shard_path.display()
+                    );
+                }
             }
         }
         eprintln!(
@@ -488,6 +683,19 @@ pub async fn run_load(which: Option<&str>) -> Result<()> {
         );
     }
 
+    listener.abort();
+
+    // Make the new weights the active model; the link is relative so the directory can move.
+    if let Some(link) = crate::cli::discovery::current_model_link_path() {
+        let target = std::path::Path::new(final_name);
+        if let Err(e) = point_link_at(&link, target) {
+            eprintln!("please load: failed to update {}: {e}", link.display());
+        }
+    }
+
+    // Let the next hub start pick up the new weights instead of the previously recorded ones.
+    crate::hub::forget_recorded_model();
+
     eprintln!("please load: done");
     Ok(())
 }
@@ -496,6 +704,50 @@ pub async fn run_load(which: Option<&str>) -> Result<()> {
 mod tests {
     use super::*;
 
+    #[cfg(unix)]
+    #[test]
+    fn current_link_follows_the_latest_load() {
+        let dir = std::env::temp_dir().join(format!("please-link-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("old.gguf"), b"old").unwrap();
+        std::fs::write(dir.join("new.gguf"), b"new").unwrap();
+        let link = dir.join("current.gguf");
+
+        point_link_at(&link, std::path::Path::new("old.gguf")).unwrap();
+        point_link_at(&link, std::path::Path::new("new.gguf")).unwrap();
+        let content = std::fs::read(&link).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(content, b"new");
+    }
+
+    #[tokio::test]
+    async fn resumed_files_are_hashed_whole_and_removed_on_mismatch() {
+        let path = std::env::temp_dir().join(format!("please-digest-{}", std::process::id()));
+        std::fs::write(&path, b"abc").unwrap();
+        let mut hasher = hash_prefix(&path, 1).await.unwrap();
+        hasher.update(b"bc");
+        let abc = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        ensure_digest(&path, hasher, abc).await.unwrap();
+
+        let hasher = hash_prefix(&path, 3).await.unwrap();
+        let error = ensure_digest(&path, hasher, &"0".repeat(64))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains(&format!("sha256 {abc}")));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn only_known_model_names_pick_a_repository() {
+        assert!(is_model_name("120b"));
+        assert!(is_model_name(" 20B "));
+        assert!(!is_model_name("the"));
+        assert_eq!(
+            pick_repository(Some("large")).0,
+            "ggml-org/gpt-oss-120b-GGUF"
+        );
+    }
+
     #[test]
     fn derive_multishard_strips_index_pattern() {
         let name = "gpt-oss-120b-mxfp4-00001-of-00003.gguf";
@@ -548,6 +800,16 @@ mod tests {
         assert_eq!(range.total, None);
     }
 
+    #[test]
+    fn space_check_reports_need_and_have() {
+        assert!(ensure_enough_space(10, 10).is_ok());
+        let error = ensure_enough_space(13_000_000_000, 2_500_000_000).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "please load: need 13.0 GB free, have 2.5 GB"
+        );
+    }
+
     #[test]
     fn content_range_rejects_invalid_unit() {
         let header = "items 0-9/100";
@@ -0,0 +1,289 @@
+//! Download progress for `please load`, redrawn on a timer instead of per chunk.
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// How often the progress is redrawn on a terminal (~10 Hz).
+pub(super) const REDRAW_EVERY: Duration = Duration::from_millis(100);
+
+/// How often a plain line is printed when stderr is not a terminal.
+const PLAIN_EVERY: Duration = Duration::from_secs(5);
+
+/// Speed is measured over this much recent history, so it follows the connection closely.
+const SPEED_WINDOW: Duration = Duration::from_secs(2);
+
+const BAR_WIDTH: usize = 20;
+
+struct Shard {
+    name: String,
+    total: Option<u64>,
+    /// Bytes that were already on disk when the download (re)started.
+    present: AtomicU64,
+    /// Bytes received in this run.
+    fetched: AtomicU64,
+}
+
+impl Shard {
+    fn downloaded(&self) -> u64 {
+        self.present.load(Ordering::Relaxed) + self.fetched.load(Ordering::Relaxed)
+    }
+}
+
+/// What the renderer remembers between redraws.
+struct Screen {
+    tty: bool,
+    /// Recent (time, bytes fetched) samples for measuring speed.
+    samples: VecDeque<(Instant, u64)>,
+    /// Lines drawn last time, to move back over them on a terminal.
+    lines_drawn: usize,
+    last_plain: Option<Instant>,
+}
+
+/// Shared by all shard downloads: they count bytes, and a ticker draws them.
+pub(super) struct Progress {
+    shards: Vec<Shard>,
+    screen: Mutex<Screen>,
+}
+
+impl Progress {
+    /// `shards` pairs each shard name with its size, when the server told us.
+    pub(super) fn new(shards: Vec<(String, Option<u64>)>) -> Self {
+        Self {
+            shards: shards
+                .into_iter()
+                .map(|(name, total)| Shard {
+                    name,
+                    total,
+                    present: AtomicU64::new(0),
+                    fetched: AtomicU64::new(0),
+                })
+                .collect(),
+            screen: Mutex::new(Screen {
+                tty: atty::is(atty::Stream::Stderr),
+                samples: VecDeque::new(),
+                lines_drawn: 0,
+                last_plain: None,
+            }),
+        }
+    }
+
+    /// Record the bytes a shard already has on disk before it starts receiving more.
+    pub(super) fn resume(&self, shard: usize, present: u64) {
+        self.shards[shard].present.store(present, Ordering::Relaxed);
+        self.shards[shard].fetched.store(0, Ordering::Relaxed);
+    }
+
+    pub(super) fn add(&self, shard: usize, delta: u64) {
+        self.shards[shard]
+            .fetched
+            .fetch_add(delta, Ordering::Relaxed);
+    }
+
+    fn total(&self) -> Option<u64> {
+        self.shards.iter().map(|shard| shard.total).sum()
+    }
+
+    /// Redraw if it is time to; called by the ticker.
+    pub(super) fn tick(&self) {
+        self.draw(Instant::now(), false);
+    }
+
+    /// Draw the final state and leave the cursor below it.
+    pub(super) fn finish(&self) {
+        self.draw(Instant::now(), true);
+    }
+
+    fn draw(&self, now: Instant, last: bool) {
+        let Ok(mut screen) = self.screen.lock() else {
+            return;
+        };
+        let fetched = self
+            .shards
+            .iter()
+            .map(|shard| shard.fetched.load(Ordering::Relaxed))
+            .sum();
+        screen.samples.push_back((now, fetched));
+        while let Some(&(at, _)) = screen.samples.front()
+            && now.duration_since(at) > SPEED_WINDOW
+        {
+            screen.samples.pop_front();
+        }
+        let speed = speed_of(&screen.samples);
+        let downloaded = self.shards.iter().map(Shard::downloaded).sum();
+        let status = status_line(downloaded, self.total(), speed);
+
+        let mut stderr = std::io::stderr().lock();
+        if screen.tty {
+            let shard_lines = if self.shards.len() > 1 {
+                self.shards.len()
+            } else {
+                0
+            };
+            let mut frame = String::new();
+            if screen.lines_drawn > 1 {
+                frame.push_str(&format!("\x1b[{}A", screen.lines_drawn - 1));
+            }
+            frame.push_str(&format!("\r\x1b[2K{status}"));
+            if shard_lines > 0 {
+                for shard in &self.shards {
+                    let line = shard_line(&shard.name, shard.downloaded(), shard.total);
+                    frame.push_str(&format!("\n\x1b[2K{line}"));
+                }
+            }
+            if last {
+                frame.push('\n');
+            }
+            let _ = stderr.write_all(frame.as_bytes());
+            screen.lines_drawn = if last { 0 } else { 1 + shard_lines };
+        } else if last
+            || screen
+                .last_plain
+                .is_none_or(|at| now.duration_since(at) >= PLAIN_EVERY)
+        {
+            let _ = writeln!(stderr, "{status}");
+            screen.last_plain = Some(now);
+        }
+        let _ = stderr.flush();
+    }
+}
+
+/// Bytes per second over the sampled window, once there is enough of it to judge.
+fn speed_of(samples: &VecDeque<(Instant, u64)>) -> Option<f64> {
+    let (first_at, first) = samples.front()?;
+    let (last_at, last) = samples.back()?;
+    let elapsed = last_at.duration_since(*first_at).as_secs_f64();
+    (elapsed >= 0.5).then(|| last.saturating_sub(*first) as f64 / elapsed)
+}
+
+/// The overall line: percentage, amounts, speed and time left, as far as they are known.
+fn status_line(downloaded: u64, total: Option<u64>, speed: Option<f64>) -> String {
+    let mut line = String::from("please load:");
+    match total {
+        Some(total) => line.push_str(&format!(
+            " {:5.1}% {} / {}",
+            percent(downloaded, total),
+            human_bytes(downloaded),
+            human_bytes(total)
+        )),
+        None => line.push_str(&format!(" {}", human_bytes(downloaded))),
+    }
+    if let Some(speed) = speed {
+        line.push_str(&format!("  {}/s", human_bytes(speed as u64)));
+        if let Some(total) = total
+            && speed >= 1.0
+        {
+            let left = total.saturating_sub(downloaded) as f64 / speed;
+            line.push_str(&format!(
+                "  ETA {}",
+                human_duration(Duration::from_secs_f64(left))
+            ));
+        }
+    }
+    line
+}
+
+/// One shard of a multi-shard download, with a bar when its size is known.
+fn shard_line(name: &str, downloaded: u64, total: Option<u64>) -> String {
+    match total {
+        Some(total) => {
+            let filled = ((percent(downloaded, total) / 100.0) * BAR_WIDTH as f64) as usize;
+            let filled = filled.min(BAR_WIDTH);
+            format!(
+                "  [{}{}] {:5.1}% {} / {}  {name}",
+                "#".repeat(filled),
+                "-".repeat(BAR_WIDTH - filled),
+                percent(downloaded, total),
+                human_bytes(downloaded),
+                human_bytes(total)
+            )
+        }
+        None => format!("  {}  {name}", human_bytes(downloaded)),
+    }
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        100.0
+    } else {
+        (part as f64 / whole as f64 * 100.0).min(100.0)
+    }
+}
+
+/// Format a byte count in decimal units, as download sizes are usually quoted.
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1000.0 {
+            break;
+        }
+        value /= 1000.0;
+        unit = next;
+    }
+    format!("{value:.1} {unit}")
+}
+
+fn human_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m{s:02}s"),
+        (h, m, _) => format!("{h}h{m:02}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_and_durations_read_naturally() {
+        assert_eq!(human_bytes(999), "999 B");
+        assert_eq!(human_bytes(1_500), "1.5 KB");
+        assert_eq!(human_bytes(12_800_000_000), "12.8 GB");
+        assert_eq!(human_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(human_duration(Duration::from_secs(185)), "3m05s");
+        assert_eq!(human_duration(Duration::from_secs(7_380)), "2h03m");
+    }
+
+    #[test]
+    fn status_shows_speed_and_eta_once_measured() {
+        assert_eq!(
+            status_line(2_000_000_000, Some(4_000_000_000), None),
+            "please load:  50.0% 2.0 GB / 4.0 GB"
+        );
+        assert_eq!(
+            status_line(2_000_000_000, Some(4_000_000_000), Some(100_000_000.0)),
+            "please load:  50.0% 2.0 GB / 4.0 GB  100.0 MB/s  ETA 20s"
+        );
+        assert_eq!(
+            status_line(1_500, None, Some(1_000.0)),
+            "please load: 1.5 KB  1.0 KB/s"
+        );
+    }
+
+    #[test]
+    fn shard_lines_carry_a_bar() {
+        assert_eq!(
+            shard_line("a.gguf", 250, Some(1_000)),
+            "  [#####---------------]  25.0% 250 B / 1.0 KB  a.gguf"
+        );
+        assert_eq!(shard_line("b.gguf", 250, None), "  250 B  b.gguf");
+    }
+
+    #[test]
+    fn speed_needs_half_a_second_of_samples() {
+        let start = Instant::now();
+        let mut samples = VecDeque::from([(start, 0)]);
+        samples.push_back((start + Duration::from_millis(200), 100));
+        assert_eq!(speed_of(&samples), None);
+        samples.push_back((start + Duration::from_secs(1), 1_000));
+        assert_eq!(speed_of(&samples), Some(1_000.0));
+    }
+}
@@ -0,0 +1,68 @@
+use crate::tools::common::{Param, ParamType};
+
+/// Print every tool the model is offered, with its description and parameters,
+/// as registered rather than as the guidance describes them.
+pub fn run_tools() {
+    let tools = crate::tools::all_tools();
+    if tools.is_empty() {
+        println!("no tools: they are disabled by PLEASE_NO_TOOLS, PLEASE_TOOLS or PLEASE_DISABLE");
+        return;
+    }
+    let mut names = tools.keys().copied().collect::<Vec<_>>();
+    names.sort_unstable();
+    for (index, name) in names.into_iter().enumerate() {
+        let (description, _, params) = &tools[name];
+        if index > 0 {
+            println!();
+        }
+        println!("{name}");
+        for line in description.lines() {
+            println!("    {line}");
+        }
+        for param in params {
+            println!("  {}", describe_param(param));
+        }
+    }
+}
+
+fn describe_param(param: &Param) -> String {
+    let kind = match param.param_type {
+        ParamType::String => "string".to_string(),
+        ParamType::Choice(choices) => format!("one of {}", choices.join("|")),
+        ParamType::Number => "number".to_string(),
+        ParamType::Boolean => "boolean".to_string(),
+        ParamType::Array => "list of strings".to_string(),
+        ParamType::Object => "object of strings".to_string(),
+    };
+    let required = if param.required { ", required" } else { "" };
+    format!("{} ({kind}{required}): {}", param.name, param.desc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params_show_their_type_and_whether_they_are_required() {
+        let param = Param {
+            name: "mode",
+            desc: "How to list.",
+            param_type: ParamType::Choice(&["flat", "tree"]),
+            required: false,
+        };
+        assert_eq!(
+            describe_param(&param),
+            "mode (one of flat|tree): How to list."
+        );
+        let param = Param {
+            name: "verify",
+            desc: "Command to run.",
+            param_type: ParamType::Array,
+            required: false,
+        };
+        assert_eq!(
+            describe_param(&param),
+            "verify (list of strings): Command to run."
+        );
+    }
+}
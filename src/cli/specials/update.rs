@@ -0,0 +1,72 @@
+use eyre::{Result, eyre};
+
+use super::load::build_http_client;
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/xhjkl/please/releases/latest";
+
+/// Entry point for `please update`: ask GitHub for the latest release and say whether it is newer.
+/// Nothing is checked unless the user runs this; nothing is downloaded or replaced.
+pub async fn run_update() -> Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    let client = build_http_client(reqwest::redirect::Policy::limited(5))?;
+    let response = client
+        .get(LATEST_RELEASE_URL)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| eyre!("please update: cannot reach github: {e}"))?;
+    if !response.status().is_success() {
+        return Err(eyre!(
+            "please update: github answered {} for the latest release",
+            response.status()
+        ));
+    }
+    let release: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+    let Some(tag) = release.get("tag_name").and_then(|tag| tag.as_str()) else {
+        return Err(eyre!("please update: the latest release has no tag"));
+    };
+    let latest = tag.trim_start_matches('v');
+
+    if is_newer(latest, current) {
+        println!("a newer version {latest} is available; this is {current}");
+        if let Some(url) = release.get("html_url").and_then(|url| url.as_str()) {
+            println!("     {url}");
+        }
+    } else {
+        println!("please {current} is up to date");
+    }
+    Ok(())
+}
+
+/// Compare dotted numeric versions; pre-release suffixes like `-rc1` are ignored.
+fn is_newer(candidate: &str, current: &str) -> bool {
+    fn parts(version: &str) -> Vec<u64> {
+        version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    }
+    let (mut candidate, mut current) = (parts(candidate), parts(current));
+    let len = candidate.len().max(current.len());
+    candidate.resize(len, 0);
+    current.resize(len, 0);
+    candidate > current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_compare_by_number_not_by_text() {
+        assert!(is_newer("0.1.10", "0.1.9"));
+        assert!(is_newer("0.2", "0.1.1"));
+        assert!(!is_newer("0.1.1", "0.1.1"));
+        assert!(!is_newer("0.1.1-rc1", "0.1.1"));
+        assert!(!is_newer("0.1.0", "0.1.1"));
+    }
+}
@@ -0,0 +1,157 @@
+use eyre::Result;
+use std::path::{Path, PathBuf};
+
+use super::load::{
+    build_http_client, derive_multishard_target_name, pick_repository, shard_url, weights_dir,
+};
+
+/// What the repository says a file should be.
+#[derive(Debug, Default)]
+pub(super) struct Expected {
+    pub(super) size: Option<u64>,
+    pub(super) sha256: Option<String>,
+}
+
+/// Entry point for `please verify [model]`: re-check downloaded weights without downloading them.
+/// Sizes and SHA-256 digests come from the repository's LFS metadata, so this needs the network
+/// but never fetches the weights themselves.
+pub async fn run_verify(which: Option<&str>) -> Result<()> {
+    let (repository, shards) = pick_repository(which);
+    let dir = weights_dir();
+    // Ask without following redirects: the LFS headers are on the first response, not the CDN's.
+    let client = build_http_client(reqwest::redirect::Policy::none())?;
+
+    let mut expected = Vec::with_capacity(shards.len());
+    for shard in shards {
+        expected.push(fetch_expected(&client, &shard_url(repository, shard)).await);
+    }
+    if expected.iter().all(|e| e.size.is_none()) {
+        println!("warn cannot read the expected sizes from huggingface; checking local files only");
+    }
+
+    let mut failed = false;
+    if shards.len() == 1 {
+        failed |= !check_file(&dir.join(shards[0]), &expected[0]).await;
+    } else {
+        let shard_paths = shards
+            .iter()
+            .map(|shard| dir.join(shard))
+            .collect::<Vec<_>>();
+        // Shards are only on disk after `please load --keep-shards`; the stitched file has no upstream digest.
+        if shard_paths.iter().all(|path| path.is_file()) {
+            for (path, expected) in shard_paths.iter().zip(&expected) {
+                failed |= !check_file(path, expected).await;
+            }
+        }
+        let stitched = Expected {
+            size: expected.iter().map(|e| e.size).sum(),
+            sha256: None,
+        };
+        failed |= !check_file(
+            &dir.join(derive_multishard_target_name(shards[0])),
+            &stitched,
+        )
+        .await;
+    }
+    if failed {
+        println!("     run `please load` again to download the damaged files");
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Read a file's size and digest from its LFS headers; needs a client that does not follow redirects.
+pub(super) async fn fetch_expected(client: &reqwest::Client, url: &str) -> Expected {
+    let Ok(response) = client.head(url).send().await else {
+        return Expected::default();
+    };
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    Expected {
+        size: header("x-linked-size")
+            .or_else(|| header("content-length").filter(|_| response.status().is_success()))
+            .and_then(|size| size.parse().ok()),
+        sha256: header("x-linked-etag").and_then(|etag| sha256_from_etag(&etag)),
+    }
+}
+
+/// LFS files carry their SHA-256 as the ETag, possibly quoted or marked weak.
+fn sha256_from_etag(etag: &str) -> Option<String> {
+    let digest = etag.trim().trim_start_matches("W/").trim_matches('"');
+    (digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| digest.to_ascii_lowercase())
+}
+
+/// Print one verdict line for a file; returns false when it is missing or damaged.
+async fn check_file(path: &Path, expected: &Expected) -> bool {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let size = match std::fs::metadata(path) {
+        Ok(meta) => meta.len(),
+        Err(error) => {
+            println!("FAIL {name}: {error}");
+            return false;
+        }
+    };
+    if let Some(want) = expected.size
+        && want != size
+    {
+        println!("FAIL {name}: {size} bytes on disk, expected {want}");
+        return false;
+    }
+    let Some(want) = &expected.sha256 else {
+        println!("ok   {name}: {size} bytes");
+        return true;
+    };
+    println!("     {name}: computing checksum...");
+    let path: PathBuf = path.to_path_buf();
+    match tokio::task::spawn_blocking(move || sha256_file(&path)).await {
+        Ok(Ok(digest)) if digest == *want => {
+            println!("ok   {name}: {size} bytes, sha256 matches");
+            true
+        }
+        Ok(Ok(digest)) => {
+            println!("FAIL {name}: sha256 {digest}, expected {want}");
+            false
+        }
+        Ok(Err(error)) => {
+            println!("FAIL {name}: {error}");
+            false
+        }
+        Err(error) => {
+            println!("FAIL {name}: {error}");
+            false
+        }
+    }
+}
+
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digests_are_read_from_lfs_etags_only() {
+        let digest = "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD";
+        assert_eq!(
+            sha256_from_etag(&format!("\"{digest}\"")).as_deref(),
+            Some(digest.to_ascii_lowercase().as_str())
+        );
+        assert_eq!(sha256_from_etag("W/\"3f2a-abc\""), None);
+    }
+}
@@ -1,12 +1,20 @@
 use eyre::{Result, eyre};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::net::UnixStream;
 
+use crate::config::{FinalAnswers, ReadApproval};
 use crate::display::Display;
-use crate::protocol::{Frame, Message, read_frame_from_stream};
-use crate::tools::{Stride, ToolKind, all_tools, kind_of, summarize_patch_for_preview};
-
+use crate::protocol::{Encoding, Frame, Message, encode_frame, read_frame_from_stream};
+use crate::tools::{
+    Stride, ToolKind, all_tools, count_patch_ops, is_sensitive_path, kind_of,
+    summarize_command_for_preview, summarize_new_file_for_preview, summarize_overwrite_for_preview,
+    summarize_patch_failures, summarize_patch_for_preview, write_warnings,
+};
+
+use super::cache::ResponseCache;
 use super::connect::obtain_control_stream;
+use super::pins::{Pins, inject_pinned};
 
 #[derive(Debug)]
 pub struct TurnCancelled;
@@ -23,6 +31,34 @@ pub fn is_cancelled(error: &eyre::Report) -> bool {
     error.downcast_ref::<TurnCancelled>().is_some()
 }
 
+/// Where the time of one turn went, summed over its subturns.
+#[derive(Debug, Default)]
+pub struct TurnTimings {
+    /// Connecting to the hub: the connection the turn started on, if it was made for this turn,
+    /// and any reconnects after a dropped connection.
+    connect: Duration,
+    /// From sending a request to the first frame back, mostly prompt prefill.
+    first_token: Duration,
+    /// From the first frame to the end of the subturn.
+    generation: Duration,
+    /// Running tools, including waiting for the user to approve them.
+    tools: Duration,
+    subturns: usize,
+}
+
+impl TurnTimings {
+    fn summary(&self) -> String {
+        format!(
+            "connect {:.2}s, first token {:.2}s, generation {:.2}s, tools {:.2}s, {} subturn(s)",
+            self.connect.as_secs_f64(),
+            self.first_token.as_secs_f64(),
+            self.generation.as_secs_f64(),
+            self.tools.as_secs_f64(),
+            self.subturns,
+        )
+    }
+}
+
 /// Run a single turn attempt, preserving the full message history across reconnects.
 /// Send a prompt to the hub and multiplex streamed frames to display channels.
 /// Returns the final answer string.
@@ -31,12 +67,16 @@ pub async fn attempt_turn_on_stream(
     display: Arc<Display>,
     messages: &mut Vec<Message>,
     stride: Stride,
+    deadline: Option<Instant>,
+    pins: &Pins,
+    timings: &mut TurnTimings,
 ) -> Result<String> {
     use tokio::io::AsyncWriteExt;
 
     enum Phase {
         Answering,
         Thinking,
+        Commenting,
     }
 
     struct PendingToolCall {
@@ -45,6 +85,9 @@ pub async fn attempt_turn_on_stream(
     }
 
     let tools = all_tools();
+    let mut wound_down = false;
+    let mut final_answer = FinalAnswer::new(crate::config::get().final_answers);
+    let cache = ResponseCache::open();
 
     loop {
         let mut spinner = Some(display.start_spinning().await);
@@ -53,6 +96,8 @@ pub async fn attempt_turn_on_stream(
 
         // Send full structured message history to the hub for this subturn.
         let mut request_messages = messages.clone();
+        // Pinned files are re-read for every subturn and never stored in the history.
+        inject_pinned(&mut request_messages, pins);
         if must_settle_command {
             request_messages.push(Message::Developer(settle_command_prompt(
                 &running_command_pids,
@@ -60,50 +105,81 @@ pub async fn attempt_turn_on_stream(
         }
         let req = Frame::Request {
             messages: request_messages,
+            options: crate::config::get().request_options(),
         };
-        let body = postcard::to_allocvec(&req).map_err(|e| eyre!(e))?;
-        stream.write_all(&body).await?;
+        let body = encode_frame(Encoding::Postcard, &req)?;
+        let cache_key = cache.as_ref().map(|cache| cache.key(&body));
+        // On a hit the hub is not asked at all; the stored frames stand in for its reply.
+        let mut replay = cache
+            .as_ref()
+            .zip(cache_key.as_deref())
+            .and_then(|(cache, key)| cache.load(key))
+            .map(Vec::into_iter);
+        if replay.is_none() {
+            stream.write_all(&body).await?;
+        }
+        let mut recorded = Vec::new();
+        timings.subturns += 1;
+        let requested_at = Instant::now();
+        let mut first_frame_at = None;
 
         let mut store = Vec::with_capacity(4096);
         let mut phase = Phase::Answering;
-        let mut final_answer = String::new();
         let mut answer = String::new();
         let mut reasoning = String::new();
+        let mut commentary = String::new();
         let mut calls = Vec::new();
         let mut tool_parse_error = None;
+        let mut held_tail = String::new();
 
         // Stream frames for this subturn
         loop {
-            let frame: Frame = tokio::select! {
-                frame = read_frame_from_stream(stream, &mut store, None, None) => {
-                    frame.map_err(|error| eyre!(error))?
-                }
-                _ = tokio::signal::ctrl_c() => {
-                    let _ = stream.shutdown().await;
-                    stride.kill_running_commands().await;
-                    return Err(eyre!(TurnCancelled));
+            let frame: Frame = if let Some(replay) = replay.as_mut() {
+                replay.next().unwrap_or(Frame::Stop)
+            } else {
+                tokio::select! {
+                    frame = read_frame_from_stream(stream, &mut store, None, None) => {
+                        frame.map_err(|error| eyre!(error))?
+                    }
+                    _ = tokio::signal::ctrl_c() => {
+                        let _ = stream.shutdown().await;
+                        stride.kill_running_commands().await;
+                        return Err(eyre!(TurnCancelled));
+                    }
                 }
             };
+            if replay.is_none() && cache_key.is_some() && !matches!(frame, Frame::Log(_)) {
+                recorded.push(frame.clone());
+            }
             // Stop spinner before streaming output so its line clear cannot erase the first token.
             if let Some(spinner) = spinner.take() {
                 spinner.stop().await;
             }
+            if first_frame_at.is_none() {
+                let now = Instant::now();
+                timings.first_token += now - requested_at;
+                first_frame_at = Some(now);
+            }
             match frame {
                 Frame::Log(line) => {
                     let _ = display.show_log(&line).await;
                 }
                 Frame::Answer(delta) => {
+                    let delta = hold_degenerate_tail(&mut held_tail, &delta);
+                    if delta.is_empty() {
+                        continue;
+                    }
                     if must_settle_command {
-                        final_answer.push_str(&delta);
                         answer.push_str(&delta);
                         continue;
                     }
-                    if matches!(phase, Phase::Thinking) {
-                        let _ = display.end_thinking().await;
+                    match phase {
+                        Phase::Thinking => display.end_thinking().await,
+                        Phase::Commenting => display.end_commentary().await,
+                        Phase::Answering => {}
                     }
                     phase = Phase::Answering;
                     let _ = display.show_delta(&delta).await;
-                    final_answer.push_str(&delta);
                     answer.push_str(&delta);
                 }
                 Frame::Thinking(delta) => {
@@ -111,6 +187,9 @@ pub async fn attempt_turn_on_stream(
                         reasoning.push_str(&delta);
                         continue;
                     }
+                    if matches!(phase, Phase::Commenting) {
+                        display.end_commentary().await;
+                    }
                     if !matches!(phase, Phase::Thinking) {
                         let _ = display.start_thinking().await;
                     }
@@ -118,6 +197,21 @@ pub async fn attempt_turn_on_stream(
                     let _ = display.show_delta(&delta).await;
                     reasoning.push_str(&delta);
                 }
+                Frame::Commentary(delta) => {
+                    if must_settle_command {
+                        commentary.push_str(&delta);
+                        continue;
+                    }
+                    if matches!(phase, Phase::Thinking) {
+                        display.end_thinking().await;
+                    }
+                    if !matches!(phase, Phase::Commenting) {
+                        display.start_commentary().await;
+                    }
+                    phase = Phase::Commenting;
+                    display.show_delta(&delta).await;
+                    commentary.push_str(&delta);
+                }
                 Frame::ToolCall {
                     name,
                     arguments_json,
@@ -131,15 +225,29 @@ pub async fn attempt_turn_on_stream(
                 Frame::ToolCallParseError(error) => {
                     tool_parse_error = Some(error);
                 }
-                Frame::Stop => break,
-                Frame::Request { .. } => {}
+                Frame::Stop => {
+                    if !held_tail.is_empty() {
+                        tracing::debug!("dropping incomplete UTF-8 at the end of the answer");
+                    }
+                    if let Some(first_frame_at) = first_frame_at {
+                        timings.generation += first_frame_at.elapsed();
+                    }
+                    break;
+                }
+                Frame::Error(message) => return Err(eyre!(message)),
+                Frame::Request { .. } | Frame::Reload | Frame::Status => {}
             }
         }
 
-        if matches!(phase, Phase::Thinking) {
-            let _ = display.end_thinking().await;
+        match phase {
+            Phase::Thinking => display.end_thinking().await,
+            Phase::Commenting => display.end_commentary().await,
+            Phase::Answering => {}
         }
         let _ = display.end_answer().await;
+        if let (Some(cache), Some(key), None) = (&cache, &cache_key, &replay) {
+            cache.store(key, &recorded);
+        }
 
         let missing_required_control = must_settle_command
             && !calls
@@ -150,8 +258,13 @@ pub async fn attempt_turn_on_stream(
         if !reasoning.is_empty() && !missing_required_control {
             messages.push(Message::Reasoning(reasoning));
         }
+        // Keep notes apart from the answer so they are replayed on the commentary channel.
+        if !commentary.is_empty() && !must_settle_command {
+            messages.push(Message::Commentary(commentary));
+        }
         // Preserve assistant-visible content across subturns.
         if !answer.is_empty() && !must_settle_command {
+            final_answer.push(&answer);
             messages.push(Message::Assistant(answer));
         }
         if let Some(error) = tool_parse_error {
@@ -172,10 +285,29 @@ pub async fn attempt_turn_on_stream(
         if calls.is_empty() {
             // The turn is complete, return the final answer.
             stride.kill_running_commands().await;
-            return Ok(final_answer);
+            return Ok(final_answer.into_text());
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            stride.kill_running_commands().await;
+            if wound_down {
+                // The model was already told to answer; stop here rather than loop on refusals.
+                return Ok(final_answer.into_text());
+            }
+            wound_down = true;
+            for call in calls {
+                let tool_payload = serde_json::json!({
+                    "tool": call.name,
+                    "arguments": call.arguments,
+                    "result": { "error": "time budget exceeded" }
+                });
+                messages.push(Message::Tool(tool_payload.to_string()));
+            }
+            messages.push(Message::Developer(TIME_BUDGET_EXCEEDED_PROMPT.to_string()));
+            continue;
         }
 
         // Execute tools and append tool results to history, then continue the loop
+        let tools_started_at = Instant::now();
         for call in calls {
             let name = call.name;
             let args = call.arguments;
@@ -184,6 +316,16 @@ pub async fn attempt_turn_on_stream(
             // Show pretty formatted function call
             let _ = display.show_tool_call(&name, &args).await;
 
+            if crate::config::get().no_tools {
+                let tool_payload = serde_json::json!({
+                    "tool": name,
+                    "arguments": args,
+                    "result": { "error": "tools are disabled for this session" }
+                });
+                messages.push(Message::Tool(tool_payload.to_string()));
+                continue;
+            }
+
             if must_settle_command && !kind.is_control_command() {
                 let tool_payload = serde_json::json!({
                     "tool": name,
@@ -194,6 +336,13 @@ pub async fn attempt_turn_on_stream(
                 continue;
             }
 
+            if kind == ToolKind::AskUser {
+                let question = args.get("question").and_then(|v| v.as_str()).unwrap_or("");
+                let reply = display.ask_user(question).await;
+                messages.extend(ask_user_messages(&name, &args, reply));
+                continue;
+            }
+
             let approved = gate_risky_if_needed(&display, kind, &args).await;
             if !approved {
                 let tool_payload = serde_json::json!({
@@ -205,15 +354,15 @@ pub async fn attempt_turn_on_stream(
                 continue;
             }
 
-            let starts_command = kind.starts_command(&args);
-            // Only newly-started commands get a live output pane for streaming stdout/stderr.
-            let execution_pane = if starts_command {
+            let runs_command = kind.runs_command(&args);
+            // Starting a command or waiting on one opens a live pane for its stdout/stderr.
+            let execution_pane = if runs_command {
                 display.start_executing()
             } else {
                 None
             };
             let stride = stride.with_live_output(execution_pane.as_ref().map(|pane| pane.sender()));
-            let streamed = starts_command && execution_pane.is_some();
+            let streamed = runs_command && execution_pane.is_some();
             let result = tokio::select! {
                 result = crate::tools::invoke(&tools, stride.clone(), &name, args.clone()) => {
                     result.unwrap_or_else(|error| serde_json::json!({ "error": error }))
@@ -227,6 +376,9 @@ pub async fn attempt_turn_on_stream(
 
             drop(execution_pane);
 
+            if kind.has_command_output() {
+                display.show_result_size(&name, &result).await;
+            }
             if !streamed && kind.has_command_output() {
                 // For plain display mode, forward stdout/stderr all at once.
                 if let Some(obj) = result.as_object() {
@@ -236,10 +388,17 @@ pub async fn attempt_turn_on_stream(
                 }
             }
 
+            if !kind.has_command_output() {
+                // Command output is already shown above or streamed live.
+                display.show_tool_result(&name, &result).await;
+            }
+            display.record_tool_result(&name, &result).await;
+
             let tool_payload =
                 serde_json::json!({ "tool": name, "arguments": args.clone(), "result": result });
             messages.push(Message::Tool(tool_payload.to_string()));
         }
+        timings.tools += tools_started_at.elapsed();
         // Loop continues: send a new Request with updated history to get the assistant to use the tool results
     }
 }
@@ -248,12 +407,14 @@ pub async fn attempt_turn_on_stream(
 /// Send a prompt to the hub and multiplex streamed frames to display channels.
 /// This may reconnect to the hub if the connection is lost.
 /// Returns the final answer string.
+/// `connected_in` is how long opening `stream` took when it was opened for this turn, else zero.
 pub async fn run_turn(
     stream: &mut UnixStream,
     display: Arc<Display>,
     messages: Vec<Message>,
+    pins: &Pins,
+    connected_in: Duration,
 ) -> Result<String> {
-    use std::time::Duration;
     fn is_disconnect(e: &eyre::Report) -> bool {
         if let Some(pe) = e.downcast_ref::<crate::protocol::ProtocolError>() {
             return matches!(pe, crate::protocol::ProtocolError::Disconnect);
@@ -272,10 +433,28 @@ pub async fn run_turn(
     let mut attempt = 0;
     let mut messages = messages;
     let stride = Stride::default();
-
-    loop {
-        match attempt_turn_on_stream(stream, display.clone(), &mut messages, stride.clone()).await {
-            Ok(s) => return Ok(s),
+    // The budget covers the whole turn, including reconnects.
+    let deadline = crate::config::get()
+        .turn_budget
+        .map(|budget| Instant::now() + budget);
+    let mut timings = TurnTimings {
+        connect: connected_in,
+        ..TurnTimings::default()
+    };
+
+    let answer = loop {
+        match attempt_turn_on_stream(
+            stream,
+            display.clone(),
+            &mut messages,
+            stride.clone(),
+            deadline,
+            pins,
+            &mut timings,
+        )
+        .await
+        {
+            Ok(s) => break s,
             Err(e) => {
                 if !is_disconnect(&e) {
                     stride.kill_running_commands().await;
@@ -288,16 +467,61 @@ pub async fn run_turn(
 
                 tokio::time::sleep(Duration::from_millis(1u64 << attempt.min(6))).await;
 
+                let reconnecting_at = Instant::now();
                 let mut new_stream = obtain_control_stream().await?;
                 std::mem::swap(stream, &mut new_stream);
+                timings.connect += reconnecting_at.elapsed();
 
                 attempt += 1;
                 continue;
             }
         }
+    };
+
+    if crate::config::get().timings {
+        display.show_timings(&timings.summary()).await;
     }
+    Ok(answer)
 }
 
+/// The answer of a whole turn, built from the final-channel messages of its subturns.
+struct FinalAnswer {
+    mode: FinalAnswers,
+    text: String,
+}
+
+impl FinalAnswer {
+    fn new(mode: FinalAnswers) -> Self {
+        Self {
+            mode,
+            text: String::new(),
+        }
+    }
+
+    fn push(&mut self, message: &str) {
+        if message.trim().is_empty() {
+            return;
+        }
+        match self.mode {
+            FinalAnswers::Replace => self.text = message.to_string(),
+            FinalAnswers::Continue if self.text.is_empty() => self.text = message.to_string(),
+            FinalAnswers::Continue => {
+                // Answers from separate subturns would otherwise run into each other mid-sentence.
+                let kept = self.text.trim_end().len();
+                self.text.truncate(kept);
+                self.text.push_str("\n\n");
+                self.text.push_str(message.trim_start());
+            }
+        }
+    }
+
+    fn into_text(self) -> String {
+        self.text
+    }
+}
+
+const TIME_BUDGET_EXCEEDED_PROMPT: &str = "The time budget for this turn is exceeded. Do not call any more tools; answer now with what you have.";
+
 fn settle_command_prompt(pids: &[u32]) -> String {
     let pids = pids
         .iter()
@@ -310,30 +534,221 @@ fn settle_command_prompt(pids: &[u32]) -> String {
     )
 }
 
+/// The hub decodes each delta on its own, so bytes of a character cut off by the end of
+/// generation arrive as trailing `U+FFFD`. Hold those back until more answer text shows they
+/// were real content; whatever is still held when the subturn stops is the degenerate tail.
+fn hold_degenerate_tail(held: &mut String, delta: &str) -> String {
+    let mut text = std::mem::take(held);
+    text.push_str(delta);
+    let kept = text.trim_end_matches('\u{FFFD}').len();
+    held.push_str(&text[kept..]);
+    text.truncate(kept);
+    text
+}
+
+/// The call is answered with a pointer to the reply, which goes in as the user's own message.
+fn ask_user_messages(name: &str, args: &serde_json::Value, reply: Option<String>) -> Vec<Message> {
+    let Some(reply) = reply else {
+        let tool_payload = serde_json::json!({
+            "tool": name,
+            "arguments": args,
+            "result": { "error": "the user cannot be asked right now; proceed on your best assumption" }
+        });
+        return vec![Message::Tool(tool_payload.to_string())];
+    };
+    let tool_payload = serde_json::json!({
+        "tool": name,
+        "arguments": args,
+        "result": { "ok": true, "reply": "in the next user message" }
+    });
+    let reply = if reply.is_empty() {
+        "(no answer; use your best judgement)".to_string()
+    } else {
+        reply
+    };
+    vec![
+        Message::Tool(tool_payload.to_string()),
+        Message::User(reply),
+    ]
+}
+
+fn string_array(value: Option<&serde_json::Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|a| {
+            a.iter()
+                .filter_map(|s| s.as_str().map(|t| t.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Ask before running argv with the env and stdin it is given,
+/// calling out anything that looks like it writes beyond the workspace.
+async fn confirm_command(
+    display: &Display,
+    argv: &[String],
+    env: &[(String, String)],
+    stdin: Option<&str>,
+) -> bool {
+    let workspace = std::env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .unwrap_or_default();
+    let warnings = write_warnings(argv, &workspace);
+    let preview = summarize_command_for_preview(argv, env, stdin);
+    display
+        .confirm_run_command_execution(&preview, &warnings)
+        .await
+}
+
+/// The `env` object of a run_command call as name/value pairs, in name order.
+fn string_pairs(value: Option<&serde_json::Value>) -> Vec<(String, String)> {
+    let mut pairs = value
+        .and_then(|v| v.as_object())
+        .map(|object| {
+            object
+                .iter()
+                .map(|(name, value)| {
+                    let value = value
+                        .as_str()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| value.to_string());
+                    (name.clone(), value)
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    pairs.sort();
+    pairs
+}
+
 async fn gate_risky_if_needed(display: &Display, kind: ToolKind, args: &serde_json::Value) -> bool {
     match kind {
         ToolKind::RunCommand => {
-            let argv: Vec<String> = args
-                .get("argv")
-                .and_then(|v| v.as_array())
-                .map(|a| {
-                    a.iter()
-                        .filter_map(|s| s.as_str().map(|t| t.to_string()))
-                        .collect()
-                })
-                .unwrap_or_default();
+            let argv = string_array(args.get("argv"));
             if argv.is_empty() {
                 return true;
             }
-            display.confirm_run_command_execution(&argv).await
+            let env = string_pairs(args.get("env"));
+            let stdin = args.get("stdin").and_then(|v| v.as_str());
+            confirm_command(display, &argv, &env, stdin).await
         }
         ToolKind::ApplyPatch => {
+            // A dry run changes nothing, so there is nothing to approve.
+            if args.get("dry_run").and_then(|v| v.as_bool()) == Some(true) {
+                return true;
+            }
             let preview = match args.get("patch").and_then(|v| v.as_str()) {
-                Some(patch) => summarize_patch_for_preview(patch).unwrap_or_default(),
+                Some(patch) => {
+                    let mut preview = summarize_patch_for_preview(patch).unwrap_or_default();
+                    for failure in summarize_patch_failures(patch) {
+                        preview.push_str(&format!("\nwill fail: {failure}"));
+                    }
+                    preview
+                }
                 None => String::new(),
             };
+            if !display.confirm_apply_patch_edits(&preview).await {
+                return false;
+            }
+            // The verification command goes through the same gate as run_command.
+            let verify = string_array(args.get("verify"));
+            if !verify.is_empty() && !confirm_command(display, &verify, &[], None).await {
+                return false;
+            }
+            // Enormous patches are hard to review, so ask once more before touching that many files.
+            let max_files = crate::config::get().max_patch_files;
+            let files = args
+                .get("patch")
+                .and_then(|v| v.as_str())
+                .and_then(count_patch_ops)
+                .unwrap_or(0);
+            if files > max_files {
+                return display.confirm_large_patch(files, max_files).await;
+            }
+            true
+        }
+        ToolKind::CreateFile => {
+            let path = args
+                .get("path")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            let content = args
+                .get("content")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            let preview = summarize_new_file_for_preview(path, content);
             display.confirm_apply_patch_edits(&preview).await
         }
-        ToolKind::ControlCommand | ToolKind::Other => true,
+        ToolKind::WriteFile => {
+            let path = args
+                .get("path")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            let content = args
+                .get("content")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            let preview = summarize_overwrite_for_preview(path, content);
+            display.confirm_apply_patch_edits(&preview).await
+        }
+        ToolKind::ReadFiles => {
+            let config = crate::config::get();
+            let path = args.get("path").and_then(|v| v.as_str()).unwrap_or(".");
+            let ask = match config.read_approval {
+                ReadApproval::Off => false,
+                ReadApproval::Sensitive => is_sensitive_path(path, &config.sensitive_paths),
+                ReadApproval::All => true,
+            };
+            !ask || display.confirm_read(path).await
+        }
+        ToolKind::ControlCommand | ToolKind::AskUser | ToolKind::Other => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_answers_continue_or_replace_earlier_ones() {
+        let mut answer = FinalAnswer::new(FinalAnswers::Continue);
+        answer.push("Let me check.\n");
+        answer.push("   ");
+        answer.push("\nIt builds.");
+        assert_eq!(answer.into_text(), "Let me check.\n\nIt builds.");
+
+        let mut answer = FinalAnswer::new(FinalAnswers::Replace);
+        answer.push("Let me check.");
+        answer.push("It builds.");
+        assert_eq!(answer.into_text(), "It builds.");
+    }
+
+    #[test]
+    fn questions_are_answered_as_user_messages() {
+        let args = serde_json::json!({ "question": "Which crate?" });
+        let answered = ask_user_messages("ask_user", &args, Some("the cli one".to_string()));
+        assert!(matches!(
+            &answered[..],
+            [Message::Tool(_), Message::User(reply)] if reply == "the cli one"
+        ));
+        let unanswered = ask_user_messages("ask_user", &args, None);
+        assert!(matches!(
+            &unanswered[..],
+            [Message::Tool(payload)] if payload.contains("best assumption")
+        ));
+    }
+
+    #[test]
+    fn replacement_chars_are_dropped_only_at_the_tail() {
+        let mut held = String::new();
+        assert_eq!(hold_degenerate_tail(&mut held, "caf\u{FFFD}"), "caf");
+        assert_eq!(
+            hold_degenerate_tail(&mut held, "\u{FFFD} ok"),
+            "\u{FFFD}\u{FFFD} ok"
+        );
+        assert!(held.is_empty());
+        assert_eq!(hold_degenerate_tail(&mut held, "end\u{FFFD}"), "end");
+        assert_eq!(held, "\u{FFFD}");
     }
 }
@@ -0,0 +1,1201 @@
+//! Settings resolved once per process from command-line flags and environment variables.
+use crossterm::style::Color;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Expose no tools to the model and reject any tool call it attempts anyway.
+    pub no_tools: bool,
+    /// Offer only these tools when set, e.g. `PLEASE_TOOLS=read_file,list_files`.
+    pub tools: Option<Vec<String>>,
+    /// Tools never offered, e.g. `PLEASE_DISABLE=run_command,apply_patch`.
+    pub disabled_tools: Vec<String>,
+    /// Patches touching more files than this need an extra confirmation.
+    pub max_patch_files: usize,
+    /// Let patch updates and deletes fall back to an existing path that differs only in case.
+    pub patch_case_insensitive: bool,
+    /// Copy files aside as `<path>.please.bak.<unix time>` before a patch changes them.
+    pub patch_backups: bool,
+    /// Wall-clock budget for a whole turn; once spent, no more tools run and the model must answer.
+    pub turn_budget: Option<Duration>,
+    /// Stop commands the model runs once they have run this long, unless the call sets its own.
+    pub command_timeout: Option<Duration>,
+    /// Give up on a tool call that has not returned after this long, plus any wait it asked for.
+    pub tool_timeout: Duration,
+    /// Always pick the most likely token, for reproducible output.
+    pub greedy: bool,
+    /// How the hub samples when not greedy.
+    pub sampler: SamplerConfig,
+    /// Room left free when a full context is compacted.
+    pub window_slack: WindowSlack,
+    /// Readout sections to keep off the terminal.
+    pub hidden: Hidden,
+    /// Colors for each part of the display.
+    pub theme: Theme,
+    /// Channel names of the model, for fine-tunes that renamed the stock Harmony ones.
+    pub channels: crate::harmony::ChannelNames,
+    /// Soft-wrap answers on word boundaries when they go to a terminal.
+    pub wrap_answers: bool,
+    /// Style headings, bold text and code in answers shown on a terminal.
+    pub markdown: bool,
+    /// Wrap at this many columns instead of the terminal width.
+    pub wrap_width: Option<usize>,
+    /// Files given with `--pin`, shown to the model fresh before every subturn.
+    pub pinned: Vec<PathBuf>,
+    /// Whether piped stdin is background or the input the prompt is about (`--stdin-subject`).
+    pub stdin_role: StdinRole,
+    /// Whether stdout gets the plain answer or one JSON event per line (`--json`).
+    pub output: OutputFormat,
+    /// Include reasoning deltas among the JSON events.
+    pub json_reasoning: bool,
+    /// Shell commands given with `--context-cmd`, run once at startup for their output.
+    pub context_cmds: Vec<String>,
+    /// Append a markdown record of the session to this file.
+    pub transcript: Option<PathBuf>,
+    /// Include the model's reasoning in the transcript.
+    pub transcript_reasoning: bool,
+    /// Append the bare answers to this file, in addition to the terminal and any transcript.
+    pub answer_file: Option<PathBuf>,
+    /// Print where the time went after each turn.
+    pub timings: bool,
+    /// Recap what an interactive session did when it ends.
+    pub session_summary: bool,
+    /// Hold answers back and show long ones through `$PAGER` once complete.
+    pub pager: bool,
+    /// Cap on tokens generated per subturn.
+    pub max_tokens: Option<u32>,
+    /// Save the prefilled system preamble to `~/.please/cache` and load it on later hub starts.
+    pub prefix_cache: bool,
+    /// Strings given with `--stop`; the answer ends where one of them first appears.
+    pub stop: Vec<String>,
+    /// Model layers to offload to the GPU; unset offloads all of them unless free VRAM is short.
+    pub gpu_layers: Option<u32>,
+    /// Connections the hub serves at once; further clients are turned away.
+    pub max_connections: usize,
+    /// Inference contexts the hub keeps at once, shared by all connections; turns beyond that wait.
+    pub sessions: usize,
+    /// How answers from several subturns of one turn combine into the turn's answer.
+    pub final_answers: FinalAnswers,
+    /// Replay replies to identical requests from `~/.please/cache`; implies greedy sampling.
+    pub cache: bool,
+    /// Ask for an answer without an analysis pass and with minimal reasoning. How much time this
+    /// saves has not been measured; `PLEASE_TIMINGS` shows where a turn's time goes.
+    pub direct: bool,
+    /// Characters of each line shown in patch previews; 0 shows lines in full.
+    pub preview_line_chars: usize,
+    /// Which file reads need the user's approval first.
+    pub read_approval: ReadApproval,
+    /// Patterns of paths that count as sensitive, e.g. `PLEASE_SENSITIVE_PATHS=.env,*.key,secrets/`.
+    pub sensitive_paths: Vec<String>,
+    /// Proxy for `please load`, e.g. `http://proxy:3128` or `socks5h://127.0.0.1:1080`.
+    pub proxy: Option<String>,
+    /// How many directory levels below each root model discovery looks into.
+    pub discovery_depth: usize,
+    /// Run every prompt in this file as its own one-shot turn (`--batch <file>`).
+    pub batch: Option<PathBuf>,
+    /// Write each batch answer to a numbered file in this directory (`--batch-out <dir>`).
+    pub batch_out: Option<PathBuf>,
+    /// Keys of the settings that command-line flags changed, for reporting provenance.
+    set_by_flags: Vec<&'static str>,
+}
+
+/// Where the effective value of a setting came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    Env,
+    Flag,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::Env => write!(f, "env"),
+            Source::Flag => write!(f, "flag"),
+        }
+    }
+}
+
+/// One resolved setting as shown by `please config`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    pub key: &'static str,
+    pub value: String,
+    pub source: Source,
+}
+
+/// What to do when the model answers again after running tools in the same turn,
+/// e.g. `PLEASE_FINAL_ANSWERS=replace`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FinalAnswers {
+    /// Keep every answer, separated by a blank line.
+    #[default]
+    Continue,
+    /// Keep only the latest answer.
+    Replace,
+}
+
+impl FinalAnswers {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "continue" | "join" => Some(Self::Continue),
+            "replace" | "last" => Some(Self::Replace),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for FinalAnswers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FinalAnswers::Continue => write!(f, "continue"),
+            FinalAnswers::Replace => write!(f, "replace"),
+        }
+    }
+}
+
+/// What piped stdin is to the model, e.g. `PLEASE_STDIN=subject` for `cat notes.md | please summarize`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StdinRole {
+    /// Background the prompt may draw on, given ahead of it as a developer message.
+    #[default]
+    Context,
+    /// The input the prompt is about, sent in the user message right after the prompt.
+    Subject,
+}
+
+impl StdinRole {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "context" | "background" => Some(Self::Context),
+            "subject" | "input" | "prompt" => Some(Self::Subject),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for StdinRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StdinRole::Context => write!(f, "context"),
+            StdinRole::Subject => write!(f, "subject"),
+        }
+    }
+}
+
+/// What stdout carries, e.g. `PLEASE_OUTPUT=json` when another program reads it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The answer as plain text.
+    #[default]
+    Text,
+    /// Newline-delimited JSON events: answer deltas, tool calls and results, answer ends.
+    Json,
+}
+
+impl OutputFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "text" | "plain" => Some(Self::Text),
+            "json" | "ndjson" | "jsonl" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Reads to ask about before the model sees the content, e.g. `PLEASE_READ_APPROVAL=sensitive`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadApproval {
+    /// Reads within the workspace go ahead.
+    #[default]
+    Off,
+    /// Ask before reading paths that match the sensitive list; searches skip such files.
+    Sensitive,
+    /// Ask before every read and search.
+    All,
+}
+
+impl ReadApproval {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "off" | "none" | "0" => Some(Self::Off),
+            "sensitive" | "secrets" => Some(Self::Sensitive),
+            "all" | "strict" => Some(Self::All),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ReadApproval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadApproval::Off => write!(f, "off"),
+            ReadApproval::Sensitive => write!(f, "sensitive"),
+            ReadApproval::All => write!(f, "all"),
+        }
+    }
+}
+
+/// Sampling knobs, e.g. `PLEASE_MIROSTAT=0 PLEASE_TOP_K=20 PLEASE_TEMP=0.6`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerConfig {
+    /// Steer towards a target surprise with Mirostat v2 instead of cutting with top-k and top-p.
+    pub mirostat: bool,
+    /// Overrides the default temperature of the chosen chain.
+    pub temperature: Option<f32>,
+    pub top_k: i32,
+    pub top_p: f32,
+    /// Seed for the random samplers, so runs can be repeated; a fresh one per turn when unset.
+    /// Greedy sampling has no randomness and ignores it.
+    pub seed: Option<u32>,
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        Self {
+            mirostat: true,
+            temperature: None,
+            top_k: 40,
+            top_p: 0.9,
+            seed: None,
+        }
+    }
+}
+
+impl SamplerConfig {
+    /// Setting top-k or top-p without saying otherwise selects the chain that uses them.
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let top_k = env_parse("PLEASE_TOP_K");
+        let top_p = env_parse("PLEASE_TOP_P");
+        let mirostat = match std::env::var_os("PLEASE_MIROSTAT") {
+            Some(_) => env_flag("PLEASE_MIROSTAT"),
+            None => top_k.is_none() && top_p.is_none(),
+        };
+        Self {
+            mirostat,
+            temperature: env_parse("PLEASE_TEMP"),
+            top_k: top_k.unwrap_or(defaults.top_k),
+            top_p: top_p.unwrap_or(defaults.top_p),
+            seed: env_parse("PLEASE_SEED"),
+        }
+    }
+
+    /// These settings with the ones a request carries on top, as the hub samples that request.
+    pub fn with_overrides(self, options: &crate::protocol::RequestOptions) -> Self {
+        Self {
+            mirostat: options.mirostat.unwrap_or(self.mirostat),
+            temperature: options.temperature.or(self.temperature),
+            top_k: options.top_k.unwrap_or(self.top_k),
+            top_p: options.top_p.unwrap_or(self.top_p),
+            seed: options.seed.or(self.seed),
+        }
+    }
+}
+
+/// How many tokens of room a full context gets back when it is compacted, as a fraction of its
+/// size with a floor, e.g. `PLEASE_WINDOW_SLACK=0.01 PLEASE_WINDOW_SLACK_MIN=512`.
+/// More room means fewer compactions but less history kept.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowSlack {
+    pub fraction: f64,
+    pub min: usize,
+}
+
+impl Default for WindowSlack {
+    fn default() -> Self {
+        Self {
+            fraction: 1.0 / 32.0,
+            min: 128,
+        }
+    }
+}
+
+impl WindowSlack {
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            fraction: env_parse("PLEASE_WINDOW_SLACK")
+                .filter(|fraction: &f64| (0.0..1.0).contains(fraction))
+                .unwrap_or(defaults.fraction),
+            min: env_parse("PLEASE_WINDOW_SLACK_MIN").unwrap_or(defaults.min),
+        }
+    }
+}
+
+/// Parts of the readout a user can switch off one by one, e.g. `PLEASE_HIDE=thinking,toolcalls`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Hidden {
+    pub thinking: bool,
+    pub commentary: bool,
+    pub tool_calls: bool,
+    pub tool_output: bool,
+}
+
+impl Hidden {
+    /// Parse a comma-separated list of section names; unknown names are ignored.
+    pub fn parse(list: &str) -> Self {
+        let mut hidden = Self::default();
+        for name in list.split(',') {
+            match name
+                .trim()
+                .to_ascii_lowercase()
+                .replace(['-', '_'], "")
+                .as_str()
+            {
+                "thinking" | "reasoning" => hidden.thinking = true,
+                "commentary" | "notes" => hidden.commentary = true,
+                "toolcalls" | "calls" => hidden.tool_calls = true,
+                "tooloutput" | "output" => hidden.tool_output = true,
+                _ => {}
+            }
+        }
+        hidden
+    }
+
+    fn names(&self) -> String {
+        let names = [
+            (self.thinking, "thinking"),
+            (self.commentary, "commentary"),
+            (self.tool_calls, "toolcalls"),
+            (self.tool_output, "tooloutput"),
+        ];
+        names
+            .iter()
+            .filter(|(hidden, _)| *hidden)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Colors of the display by role, e.g. `PLEASE_THEME=light` or `PLEASE_THEME=reasoning=magenta,log=blue`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Technical readout, tool calls and their output headers.
+    pub log: Color,
+    pub reasoning: Color,
+    /// Commentary, timings and section markers.
+    pub notes: Color,
+    pub spinner: Color,
+    /// Patch previews, live command output and questions to the user.
+    pub preview: Color,
+    pub warning: Color,
+    /// Inline code and fenced code blocks in answers styled with `PLEASE_MARKDOWN`.
+    pub code: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            log: Color::DarkCyan,
+            reasoning: Color::DarkYellow,
+            notes: Color::DarkGrey,
+            spinner: Color::DarkGrey,
+            preview: Color::DarkYellow,
+            warning: Color::Red,
+            code: Color::DarkGreen,
+        }
+    }
+}
+
+impl Theme {
+    /// Dark colors that stay readable on a light background.
+    fn light() -> Self {
+        Self {
+            log: Color::DarkBlue,
+            reasoning: Color::DarkMagenta,
+            notes: Color::DarkGreen,
+            spinner: Color::DarkBlue,
+            preview: Color::DarkMagenta,
+            warning: Color::DarkRed,
+            code: Color::DarkCyan,
+        }
+    }
+
+    /// Parse a comma-separated list of a preset name and `role=color` overrides, applied in order.
+    /// Colors are names like `dark_cyan`, ANSI numbers `0`-`255`, or `#rrggbb`.
+    /// Unknown entries are ignored.
+    pub fn parse(list: &str) -> Self {
+        let mut theme = Self::default();
+        for entry in list.split(',') {
+            let entry = entry.trim().to_ascii_lowercase();
+            let Some((role, color)) = entry.split_once('=') else {
+                match entry.as_str() {
+                    "default" | "dark" => theme = Self::default(),
+                    "light" => theme = Self::light(),
+                    _ => {}
+                }
+                continue;
+            };
+            let Some(color) = parse_color(color) else {
+                continue;
+            };
+            match role.trim().replace(['-', '_'], "").as_str() {
+                "log" | "readout" => theme.log = color,
+                "reasoning" | "thinking" => theme.reasoning = color,
+                "notes" | "commentary" => theme.notes = color,
+                "spinner" => theme.spinner = color,
+                "preview" => theme.preview = color,
+                "warning" => theme.warning = color,
+                "code" => theme.code = color,
+                _ => {}
+            }
+        }
+        theme
+    }
+
+    fn names(&self) -> String {
+        let roles = [
+            ("log", self.log),
+            ("reasoning", self.reasoning),
+            ("notes", self.notes),
+            ("spinner", self.spinner),
+            ("preview", self.preview),
+            ("warning", self.warning),
+            ("code", self.code),
+        ];
+        roles
+            .iter()
+            .map(|(role, color)| format!("{role}={}", color_name(*color)))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+const COLOR_NAMES: [(&str, Color); 17] = [
+    ("reset", Color::Reset),
+    ("black", Color::Black),
+    ("dark_grey", Color::DarkGrey),
+    ("red", Color::Red),
+    ("dark_red", Color::DarkRed),
+    ("green", Color::Green),
+    ("dark_green", Color::DarkGreen),
+    ("yellow", Color::Yellow),
+    ("dark_yellow", Color::DarkYellow),
+    ("blue", Color::Blue),
+    ("dark_blue", Color::DarkBlue),
+    ("magenta", Color::Magenta),
+    ("dark_magenta", Color::DarkMagenta),
+    ("cyan", Color::Cyan),
+    ("dark_cyan", Color::DarkCyan),
+    ("white", Color::White),
+    ("grey", Color::Grey),
+];
+
+fn parse_color(text: &str) -> Option<Color> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |at: usize| u8::from_str_radix(hex.get(at..at + 2)?, 16).ok();
+        return Some(Color::Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        });
+    }
+    if let Ok(value) = text.parse::<u8>() {
+        return Some(Color::AnsiValue(value));
+    }
+    let name = text.replace('-', "_").replace("gray", "grey");
+    let name = match name.strip_prefix("dark") {
+        Some(rest) if !rest.starts_with('_') => format!("dark_{rest}"),
+        _ => name,
+    };
+    COLOR_NAMES
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, color)| *color)
+}
+
+fn color_name(color: Color) -> String {
+    match color {
+        Color::AnsiValue(value) => value.to_string(),
+        Color::Rgb { r, g, b } => format!("#{r:02x}{g:02x}{b:02x}"),
+        color => COLOR_NAMES
+            .iter()
+            .find(|(_, known)| *known == color)
+            .map_or_else(|| format!("{color:?}"), |(name, _)| name.to_string()),
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            no_tools: false,
+            tools: None,
+            disabled_tools: Vec::new(),
+            max_patch_files: 20,
+            patch_case_insensitive: false,
+            patch_backups: false,
+            turn_budget: None,
+            command_timeout: None,
+            tool_timeout: Duration::from_secs(600),
+            greedy: false,
+            sampler: SamplerConfig::default(),
+            window_slack: WindowSlack::default(),
+            hidden: Hidden::default(),
+            theme: Theme::default(),
+            channels: crate::harmony::ChannelNames::default(),
+            wrap_answers: false,
+            markdown: false,
+            wrap_width: None,
+            pinned: Vec::new(),
+            stdin_role: StdinRole::default(),
+            output: OutputFormat::default(),
+            json_reasoning: false,
+            context_cmds: Vec::new(),
+            transcript: None,
+            transcript_reasoning: false,
+            answer_file: None,
+            timings: false,
+            session_summary: false,
+            pager: false,
+            max_tokens: None,
+            prefix_cache: false,
+            stop: Vec::new(),
+            gpu_layers: None,
+            max_connections: 64,
+            sessions: 1,
+            final_answers: FinalAnswers::default(),
+            cache: false,
+            direct: false,
+            preview_line_chars: 400,
+            read_approval: ReadApproval::default(),
+            sensitive_paths: crate::tools::DEFAULT_SENSITIVE_PATTERNS
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect(),
+            proxy: None,
+            discovery_depth: 4,
+            batch: None,
+            batch_out: None,
+            set_by_flags: Vec::new(),
+        }
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+impl Config {
+    /// Settings the hub applies on behalf of this client, sent along with every request.
+    pub fn request_options(&self) -> crate::protocol::RequestOptions {
+        crate::protocol::RequestOptions {
+            // Cached replies are only sound when sampling is deterministic.
+            greedy: (self.greedy || self.cache).then_some(true),
+            max_tokens: self.max_tokens,
+            stop: self.stop.clone(),
+            // The hub reads its environment once, at start; these come from this invocation's.
+            mirostat: Some(self.sampler.mirostat),
+            temperature: self.sampler.temperature,
+            top_k: Some(self.sampler.top_k),
+            top_p: Some(self.sampler.top_p),
+            seed: self.sampler.seed,
+            reasoning: Some(crate::history::reasoning_level(self.direct).to_string()),
+            no_tools: Some(self.no_tools),
+            timings: self.timings.then_some(true),
+        }
+    }
+
+    /// Whether the model may call the tool `name`: tools are on, it is allowed and not disabled.
+    pub fn tool_enabled(&self, name: &str) -> bool {
+        !self.no_tools
+            && self
+                .tools
+                .as_ref()
+                .is_none_or(|tools| tools.iter().any(|tool| tool == name))
+            && !self.disabled_tools.iter().any(|tool| tool == name)
+    }
+
+    /// Every setting with its effective value and where that value came from.
+    pub fn settings(&self) -> Vec<Setting> {
+        fn shown<T: ToString>(value: Option<T>) -> String {
+            value.map_or_else(|| "unset".to_string(), |value| value.to_string())
+        }
+        let pinned = self
+            .pinned
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let rows: Vec<(&'static str, Option<&'static str>, String)> = vec![
+            (
+                "no_tools",
+                Some("PLEASE_NO_TOOLS"),
+                self.no_tools.to_string(),
+            ),
+            (
+                "tools",
+                Some("PLEASE_TOOLS"),
+                self.tools
+                    .as_ref()
+                    .map_or_else(|| "all".to_string(), |tools| tools.join(",")),
+            ),
+            (
+                "disabled_tools",
+                Some("PLEASE_DISABLE"),
+                self.disabled_tools.join(","),
+            ),
+            (
+                "max_patch_files",
+                Some("PLEASE_MAX_PATCH_FILES"),
+                self.max_patch_files.to_string(),
+            ),
+            (
+                "patch_case_insensitive",
+                Some("PLEASE_PATCH_CASE_INSENSITIVE"),
+                self.patch_case_insensitive.to_string(),
+            ),
+            (
+                "patch_backups",
+                Some("PLEASE_PATCH_BACKUPS"),
+                self.patch_backups.to_string(),
+            ),
+            (
+                "turn_budget_secs",
+                Some("PLEASE_TURN_BUDGET_SECS"),
+                shown(self.turn_budget.map(|budget| budget.as_secs())),
+            ),
+            (
+                "command_timeout_secs",
+                Some("PLEASE_COMMAND_TIMEOUT_SECS"),
+                shown(self.command_timeout.map(|timeout| timeout.as_secs())),
+            ),
+            (
+                "tool_timeout_secs",
+                Some("PLEASE_TOOL_TIMEOUT_SECS"),
+                self.tool_timeout.as_secs().to_string(),
+            ),
+            ("greedy", Some("PLEASE_GREEDY"), self.greedy.to_string()),
+            (
+                "mirostat",
+                Some("PLEASE_MIROSTAT"),
+                self.sampler.mirostat.to_string(),
+            ),
+            ("temp", Some("PLEASE_TEMP"), shown(self.sampler.temperature)),
+            (
+                "top_k",
+                Some("PLEASE_TOP_K"),
+                self.sampler.top_k.to_string(),
+            ),
+            (
+                "top_p",
+                Some("PLEASE_TOP_P"),
+                self.sampler.top_p.to_string(),
+            ),
+            ("seed", Some("PLEASE_SEED"), shown(self.sampler.seed)),
+            (
+                "window_slack",
+                Some("PLEASE_WINDOW_SLACK"),
+                self.window_slack.fraction.to_string(),
+            ),
+            (
+                "window_slack_min",
+                Some("PLEASE_WINDOW_SLACK_MIN"),
+                self.window_slack.min.to_string(),
+            ),
+            ("hide", Some("PLEASE_HIDE"), self.hidden.names()),
+            ("theme", Some("PLEASE_THEME"), self.theme.names()),
+            (
+                "channels",
+                Some("PLEASE_CHANNELS"),
+                self.channels.overrides(),
+            ),
+            ("wrap", Some("PLEASE_WRAP"), self.wrap_answers.to_string()),
+            (
+                "markdown",
+                Some("PLEASE_MARKDOWN"),
+                self.markdown.to_string(),
+            ),
+            (
+                "wrap_width",
+                Some("PLEASE_WRAP_WIDTH"),
+                shown(self.wrap_width),
+            ),
+            ("pinned", None, pinned),
+            (
+                "stdin_role",
+                Some("PLEASE_STDIN"),
+                self.stdin_role.to_string(),
+            ),
+            ("output", Some("PLEASE_OUTPUT"), self.output.to_string()),
+            (
+                "json_reasoning",
+                Some("PLEASE_JSON_REASONING"),
+                self.json_reasoning.to_string(),
+            ),
+            ("context_cmds", None, self.context_cmds.join("; ")),
+            (
+                "transcript",
+                Some("PLEASE_TRANSCRIPT"),
+                shown(self.transcript.as_ref().map(|path| path.display())),
+            ),
+            (
+                "transcript_reasoning",
+                Some("PLEASE_TRANSCRIPT_REASONING"),
+                self.transcript_reasoning.to_string(),
+            ),
+            (
+                "answer_file",
+                Some("PLEASE_ANSWER_FILE"),
+                shown(self.answer_file.as_ref().map(|path| path.display())),
+            ),
+            ("timings", Some("PLEASE_TIMINGS"), self.timings.to_string()),
+            (
+                "session_summary",
+                Some("PLEASE_SESSION_SUMMARY"),
+                self.session_summary.to_string(),
+            ),
+            ("pager", Some("PLEASE_PAGER"), self.pager.to_string()),
+            (
+                "max_tokens",
+                Some("PLEASE_MAX_TOKENS"),
+                shown(self.max_tokens),
+            ),
+            (
+                "prefix_cache",
+                Some("PLEASE_PREFIX_CACHE"),
+                self.prefix_cache.to_string(),
+            ),
+            ("stop", None, self.stop.join("; ")),
+            (
+                "gpu_layers",
+                Some("PLEASE_GPU_LAYERS"),
+                shown(self.gpu_layers),
+            ),
+            (
+                "max_connections",
+                Some("PLEASE_MAX_CONNECTIONS"),
+                self.max_connections.to_string(),
+            ),
+            (
+                "sessions",
+                Some("PLEASE_SESSIONS"),
+                self.sessions.to_string(),
+            ),
+            (
+                "final_answers",
+                Some("PLEASE_FINAL_ANSWERS"),
+                self.final_answers.to_string(),
+            ),
+            ("cache", Some("PLEASE_CACHE"), self.cache.to_string()),
+            ("direct", Some("PLEASE_DIRECT"), self.direct.to_string()),
+            (
+                "preview_line_chars",
+                Some("PLEASE_PREVIEW_LINE_CHARS"),
+                self.preview_line_chars.to_string(),
+            ),
+            (
+                "read_approval",
+                Some("PLEASE_READ_APPROVAL"),
+                self.read_approval.to_string(),
+            ),
+            (
+                "sensitive_paths",
+                Some("PLEASE_SENSITIVE_PATHS"),
+                self.sensitive_paths.join(","),
+            ),
+            ("proxy", Some("PLEASE_PROXY"), shown(self.proxy.as_ref())),
+            (
+                "discovery_depth",
+                Some("PLEASE_DISCOVERY_DEPTH"),
+                self.discovery_depth.to_string(),
+            ),
+            (
+                "batch",
+                None,
+                shown(self.batch.as_ref().map(|path| path.display())),
+            ),
+            (
+                "batch_out",
+                None,
+                shown(self.batch_out.as_ref().map(|path| path.display())),
+            ),
+        ];
+        rows.into_iter()
+            .map(|(key, env, value)| {
+                let source = if self.set_by_flags.contains(&key) {
+                    Source::Flag
+                } else if env
+                    .into_iter()
+                    .chain(derived_from(key).iter().copied())
+                    .any(|name| std::env::var_os(name).is_some())
+                {
+                    Source::Env
+                } else {
+                    Source::Default
+                };
+                Setting { key, value, source }
+            })
+            .collect()
+    }
+
+    /// Read settings from `PLEASE_*` environment variables only.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            no_tools: env_flag("PLEASE_NO_TOOLS"),
+            tools: env_list("PLEASE_TOOLS"),
+            disabled_tools: env_list("PLEASE_DISABLE").unwrap_or_default(),
+            max_patch_files: env_parse("PLEASE_MAX_PATCH_FILES")
+                .unwrap_or(defaults.max_patch_files),
+            patch_case_insensitive: env_flag("PLEASE_PATCH_CASE_INSENSITIVE"),
+            patch_backups: env_flag("PLEASE_PATCH_BACKUPS"),
+            turn_budget: env_parse("PLEASE_TURN_BUDGET_SECS").map(Duration::from_secs),
+            command_timeout: env_parse("PLEASE_COMMAND_TIMEOUT_SECS").map(Duration::from_secs),
+            tool_timeout: env_parse("PLEASE_TOOL_TIMEOUT_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.tool_timeout),
+            greedy: env_flag("PLEASE_GREEDY"),
+            sampler: SamplerConfig::from_env(),
+            window_slack: WindowSlack::from_env(),
+            hidden: std::env::var("PLEASE_HIDE")
+                .map(|list| Hidden::parse(&list))
+                .unwrap_or_default(),
+            theme: std::env::var("PLEASE_THEME")
+                .map(|list| Theme::parse(&list))
+                .unwrap_or_default(),
+            channels: std::env::var("PLEASE_CHANNELS")
+                .map(|list| crate::harmony::ChannelNames::parse(&list))
+                .unwrap_or_default(),
+            wrap_answers: env_flag("PLEASE_WRAP"),
+            markdown: env_flag("PLEASE_MARKDOWN"),
+            wrap_width: env_parse("PLEASE_WRAP_WIDTH"),
+            pinned: Vec::new(),
+            stdin_role: std::env::var("PLEASE_STDIN")
+                .ok()
+                .and_then(|name| StdinRole::parse(&name))
+                .unwrap_or(defaults.stdin_role),
+            output: std::env::var("PLEASE_OUTPUT")
+                .ok()
+                .and_then(|name| OutputFormat::parse(&name))
+                .unwrap_or(defaults.output),
+            json_reasoning: env_flag("PLEASE_JSON_REASONING"),
+            context_cmds: Vec::new(),
+            transcript: std::env::var_os("PLEASE_TRANSCRIPT").map(PathBuf::from),
+            transcript_reasoning: env_flag("PLEASE_TRANSCRIPT_REASONING"),
+            answer_file: std::env::var_os("PLEASE_ANSWER_FILE").map(PathBuf::from),
+            timings: env_flag("PLEASE_TIMINGS"),
+            session_summary: env_flag("PLEASE_SESSION_SUMMARY"),
+            pager: env_flag("PLEASE_PAGER"),
+            max_tokens: env_parse("PLEASE_MAX_TOKENS"),
+            prefix_cache: env_flag("PLEASE_PREFIX_CACHE"),
+            stop: Vec::new(),
+            gpu_layers: env_parse("PLEASE_GPU_LAYERS"),
+            max_connections: env_parse("PLEASE_MAX_CONNECTIONS")
+                .unwrap_or(defaults.max_connections),
+            sessions: env_parse("PLEASE_SESSIONS")
+                .unwrap_or(defaults.sessions)
+                .max(1),
+            final_answers: std::env::var("PLEASE_FINAL_ANSWERS")
+                .ok()
+                .and_then(|name| FinalAnswers::parse(&name))
+                .unwrap_or(defaults.final_answers),
+            cache: env_flag("PLEASE_CACHE"),
+            direct: env_flag("PLEASE_DIRECT"),
+            preview_line_chars: env_parse("PLEASE_PREVIEW_LINE_CHARS")
+                .unwrap_or(defaults.preview_line_chars),
+            read_approval: std::env::var("PLEASE_READ_APPROVAL")
+                .ok()
+                .and_then(|name| ReadApproval::parse(&name))
+                .unwrap_or(defaults.read_approval),
+            sensitive_paths: env_list("PLEASE_SENSITIVE_PATHS").unwrap_or(defaults.sensitive_paths),
+            proxy: std::env::var("PLEASE_PROXY")
+                .ok()
+                .filter(|proxy| !proxy.trim().is_empty()),
+            discovery_depth: env_parse("PLEASE_DISCOVERY_DEPTH")
+                .unwrap_or(defaults.discovery_depth),
+            batch: None,
+            batch_out: None,
+            set_by_flags: Vec::new(),
+        }
+    }
+
+    /// Layer leading `--flags` over the environment.
+    /// Returns the config and the remaining words, which make up the prompt.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> (Self, Vec<String>) {
+        let mut config = Self::from_env();
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.peek() {
+            match arg.as_str() {
+                "--no-tools" => {
+                    config.no_tools = true;
+                    config.set_by_flags.push("no_tools");
+                }
+                "--direct" => {
+                    config.direct = true;
+                    config.set_by_flags.push("direct");
+                }
+                "--cache" => {
+                    config.cache = true;
+                    config.set_by_flags.push("cache");
+                }
+                "--stdin-subject" => {
+                    config.stdin_role = StdinRole::Subject;
+                    config.set_by_flags.push("stdin_role");
+                }
+                "--stdin-context" => {
+                    config.stdin_role = StdinRole::Context;
+                    config.set_by_flags.push("stdin_role");
+                }
+                "--json" => {
+                    config.output = OutputFormat::Json;
+                    config.set_by_flags.push("output");
+                }
+                "--json-reasoning" => {
+                    config.json_reasoning = true;
+                    config.set_by_flags.push("json_reasoning");
+                }
+                "--pin" => {
+                    args.next();
+                    if let Some(path) = args.peek() {
+                        config.pinned.push(PathBuf::from(path));
+                        config.set_by_flags.push("pinned");
+                    }
+                }
+                "--context-cmd" => {
+                    args.next();
+                    if let Some(command) = args.peek() {
+                        config.context_cmds.push(command.clone());
+                        config.set_by_flags.push("context_cmds");
+                    }
+                }
+                "--stop" => {
+                    args.next();
+                    if let Some(stop) = args.peek() {
+                        config.stop.push(stop.clone());
+                        config.set_by_flags.push("stop");
+                    }
+                }
+                "--batch" => {
+                    args.next();
+                    if let Some(path) = args.peek() {
+                        config.batch = Some(PathBuf::from(path));
+                        config.set_by_flags.push("batch");
+                    }
+                }
+                "--batch-out" => {
+                    args.next();
+                    if let Some(path) = args.peek() {
+                        config.batch_out = Some(PathBuf::from(path));
+                        config.set_by_flags.push("batch_out");
+                    }
+                }
+                "--" => {
+                    args.next();
+                    break;
+                }
+                _ => break,
+            }
+            args.next();
+        }
+        (config, args.collect())
+    }
+}
+
+/// Install the process-wide config. Later calls are ignored.
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// Process-wide config; falls back to the environment when `init` was never called (e.g. the hub).
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::from_env)
+}
+
+/// Variables that change a setting without naming it, such as `PLEASE_TOP_K` turning Mirostat off.
+fn derived_from(key: &str) -> &'static [&'static str] {
+    match key {
+        "mirostat" => &["PLEASE_TOP_K", "PLEASE_TOP_P"],
+        _ => &[],
+    }
+}
+
+/// Treat a set variable as enabled unless it spells out a negative.
+fn env_flag(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|value| {
+        !matches!(
+            value.trim().to_ascii_lowercase().as_str(),
+            "0" | "false" | "no" | "off"
+        )
+    })
+}
+
+/// Parse a variable into any `FromStr` type, ignoring unset or malformed values.
+fn env_parse<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(name).ok()?.trim().parse().ok()
+}
+
+/// A comma-separated list, without blank entries, when the variable is set.
+fn env_list(name: &str) -> Option<Vec<String>> {
+    let list = std::env::var(name).ok()?;
+    Some(
+        list.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn leading_flags_are_split_from_the_prompt() {
+        let (config, rest) = Config::from_args(words("--no-tools explain this"));
+        assert!(config.no_tools);
+        assert_eq!(rest, words("explain this"));
+    }
+
+    #[test]
+    fn the_clients_sampler_settings_replace_the_hubs() {
+        let client = Config {
+            sampler: SamplerConfig {
+                mirostat: false,
+                temperature: Some(0.3),
+                top_k: 7,
+                top_p: 0.5,
+                seed: Some(42),
+            },
+            ..Config::default()
+        };
+        let hub = SamplerConfig::default();
+        assert_eq!(
+            hub.with_overrides(&client.request_options()),
+            client.sampler
+        );
+        assert_eq!(
+            hub.with_overrides(&crate::protocol::RequestOptions::default()),
+            hub
+        );
+    }
+
+    #[test]
+    fn timings_are_only_sent_back_when_asked_for() {
+        assert_eq!(Config::default().request_options().timings, None);
+        assert_eq!(crate::protocol::RequestOptions::default().timings, None);
+        let config = Config {
+            timings: true,
+            ..Config::default()
+        };
+        assert_eq!(config.request_options().timings, Some(true));
+    }
+
+    #[test]
+    fn flags_after_the_prompt_starts_are_prompt_words() {
+        let (_, rest) = Config::from_args(words("explain --no-tools"));
+        assert_eq!(rest, words("explain --no-tools"));
+    }
+
+    #[test]
+    fn hidden_sections_are_read_from_a_loose_list() {
+        let hidden = Hidden::parse("Thinking, tool-calls,bogus");
+        assert_eq!(
+            hidden,
+            Hidden {
+                thinking: true,
+                tool_calls: true,
+                ..Hidden::default()
+            }
+        );
+    }
+
+    #[test]
+    fn theme_overrides_apply_over_a_preset() {
+        let theme =
+            Theme::parse("light, reasoning=dark-gray,log=#0a0B0c,spinner=244,warning=bogus");
+        assert_eq!(theme.reasoning, Color::DarkGrey);
+        assert_eq!(
+            theme.log,
+            Color::Rgb {
+                r: 10,
+                g: 11,
+                b: 12
+            }
+        );
+        assert_eq!(theme.spinner, Color::AnsiValue(244));
+        assert_eq!(theme.warning, Theme::light().warning);
+        assert_eq!(Theme::parse(&theme.names()), theme);
+    }
+
+    #[test]
+    fn context_commands_accumulate_in_order() {
+        let args = [
+            "--context-cmd",
+            "git log --oneline -20",
+            "--context-cmd",
+            "git status",
+            "go",
+        ];
+        let (config, rest) = Config::from_args(args.map(str::to_string));
+        assert_eq!(config.context_cmds, ["git log --oneline -20", "git status"]);
+        assert_eq!(rest, ["go"]);
+    }
+
+    #[test]
+    fn pin_takes_the_next_word_as_a_path() {
+        let (config, rest) = Config::from_args(words("--pin src/main.rs --pin README.md fix it"));
+        assert_eq!(
+            config.pinned,
+            [PathBuf::from("src/main.rs"), PathBuf::from("README.md")]
+        );
+        assert_eq!(rest, words("fix it"));
+    }
+
+    #[test]
+    fn settings_changed_by_flags_report_their_source() {
+        let (config, _) = Config::from_args(words("--no-tools hi"));
+        let no_tools = config
+            .settings()
+            .into_iter()
+            .find(|setting| setting.key == "no_tools")
+            .unwrap();
+        assert_eq!(no_tools.value, "true");
+        assert_eq!(no_tools.source, Source::Flag);
+    }
+
+    #[test]
+    fn json_output_is_chosen_by_flag() {
+        let (config, rest) = Config::from_args(words("--json --json-reasoning why"));
+        assert_eq!(config.output, OutputFormat::Json);
+        assert!(config.json_reasoning);
+        assert_eq!(rest, words("why"));
+        assert_eq!(OutputFormat::parse(" JSONL "), Some(OutputFormat::Json));
+        assert_eq!(OutputFormat::parse("yaml"), None);
+    }
+
+    #[test]
+    fn stdin_role_flags_override_the_environment() {
+        let (config, rest) = Config::from_args(words("--stdin-subject summarize"));
+        assert_eq!(config.stdin_role, StdinRole::Subject);
+        assert_eq!(rest, words("summarize"));
+        assert_eq!(StdinRole::parse(" Context "), Some(StdinRole::Context));
+        assert_eq!(StdinRole::parse("bogus"), None);
+    }
+
+    #[test]
+    fn double_dash_ends_flags() {
+        let (_, rest) = Config::from_args(words("-- --no-tools is a flag"));
+        assert_eq!(rest, words("--no-tools is a flag"));
+    }
+}
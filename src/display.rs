@@ -1,11 +1,20 @@
+mod highlight;
+mod markdown;
+mod pager;
 mod pane;
+mod results;
+mod sinks;
 mod spinner;
+mod summary;
+mod transcript;
+mod wrap;
 
 pub use pane::ExecutionPane;
 pub use spinner::Spinner;
 
-use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
-use std::sync::RwLock;
+use crossterm::style::{Print, ResetColor, SetForegroundColor};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
 
 #[derive(Clone, Copy)]
 struct Caps {
@@ -15,6 +24,11 @@ struct Caps {
     can_prompt_user: bool,
     /// Show hub technical readout when available.
     should_show_readout: bool,
+    /// We can style answers on stdout as Markdown; stdout is a terminal and `PLEASE_MARKDOWN` is on.
+    markdown: bool,
+    /// Sections the user switched off with `PLEASE_HIDE`.
+    hidden: crate::config::Hidden,
+    theme: crate::config::Theme,
 }
 
 #[derive(Default, Clone, Copy, PartialEq, Eq)]
@@ -22,6 +36,7 @@ enum Phase {
     #[default]
     Answering,
     Thinking,
+    Commenting,
     Executing,
 }
 
@@ -29,13 +44,28 @@ enum Phase {
 pub struct Display {
     caps: Caps,
     phase: RwLock<Phase>,
+    /// Present only when answers go to a terminal and wrapping is enabled.
+    answer_wrap: Mutex<Option<wrap::SoftWrap>>,
+    /// Present only when the answer is styled as Markdown; takes the wrapped text.
+    answer_markdown: Mutex<Option<markdown::Markdown>>,
+    /// Files that get a copy of the session: the `PLEASE_TRANSCRIPT` record and the
+    /// `PLEASE_ANSWER_FILE` answers, whichever are configured.
+    sinks: Vec<Mutex<Box<dyn sinks::Sink>>>,
+    /// Answer held back for the pager; present only when paging is enabled.
+    answer_page: Option<Mutex<String>>,
+    /// Answers go out as JSON events through a sink instead of as text on stdout.
+    json_output: bool,
+    /// Set when visible reasoning ended, so the answer that follows gets its own marker.
+    answer_marker_due: AtomicBool,
+    /// What the session did so far, for the recap at its end.
+    summary: Mutex<summary::SessionSummary>,
 }
 
 impl Display {
     /// Return a guard that will stop the spinner when dropped.
     pub async fn start_spinning(&self) -> Spinner {
         if self.caps.colorful {
-            Spinner::start()
+            Spinner::start(self.caps.theme.spinner)
         } else {
             Spinner::start_empty()
         }
@@ -50,7 +80,7 @@ impl Display {
         if self.caps.colorful {
             let _ = crossterm::execute!(
                 std::io::stderr(),
-                SetForegroundColor(Color::DarkCyan),
+                SetForegroundColor(self.caps.theme.log),
                 Print("| "),
                 Print(line),
                 ResetColor,
@@ -63,13 +93,61 @@ impl Display {
 
     /// Switch display mode to presenting the reasoning process.
     pub async fn start_thinking(&self) {
+        self.show_marker("reasoning");
         *self.phase.write().unwrap() = Phase::Thinking;
     }
 
+    /// Print a dim section header to stderr so reasoning and answer stay apart on a shared terminal.
+    fn show_marker(&self, title: &str) {
+        if self.caps.colorful && !self.caps.hidden.thinking {
+            let _ = crossterm::execute!(
+                std::io::stderr(),
+                SetForegroundColor(self.caps.theme.notes),
+                Print(format!("── {title} ──\n")),
+                ResetColor,
+            );
+        }
+    }
+
+    /// Run `f` against every file sink.
+    fn record(&self, f: impl Fn(&mut dyn sinks::Sink)) {
+        for sink in &self.sinks {
+            f(&mut **sink.lock().unwrap());
+        }
+    }
+
+    /// Add the user's prompt to the file sinks; it is not echoed to the terminal.
+    pub async fn record_prompt(&self, prompt: &str) {
+        self.summary.lock().unwrap().prompt();
+        self.record(|sink| sink.prompt(prompt));
+    }
+
+    /// Add a tool's result to the file sinks regardless of what the terminal shows.
+    pub async fn record_tool_result(&self, name: &str, result: &serde_json::Value) {
+        self.summary.lock().unwrap().tool_result(name, result);
+        let rendered = results::render_tool_result(name, result);
+        self.record(|sink| sink.tool_result(name, &rendered, result));
+    }
+
     /// Switch display mode to presenting the final answer.
     pub async fn end_thinking(&self) {
         let phase = { *self.phase.read().unwrap() };
-        if self.caps.colorful && phase == Phase::Thinking {
+        if self.caps.colorful && phase == Phase::Thinking && !self.caps.hidden.thinking {
+            let _ = crossterm::execute!(std::io::stderr(), Print("\n"));
+            self.answer_marker_due.store(true, Ordering::Relaxed);
+        }
+        *self.phase.write().unwrap() = Phase::Answering;
+    }
+
+    /// Switch display mode to presenting assistant notes that are not the final answer.
+    pub async fn start_commentary(&self) {
+        *self.phase.write().unwrap() = Phase::Commenting;
+    }
+
+    /// Close the assistant notes and go back to presenting the final answer.
+    pub async fn end_commentary(&self) {
+        let phase = { *self.phase.read().unwrap() };
+        if phase == Phase::Commenting && !self.caps.hidden.commentary {
             let _ = crossterm::execute!(std::io::stderr(), Print("\n"));
         }
         *self.phase.write().unwrap() = Phase::Answering;
@@ -77,26 +155,84 @@ impl Display {
 
     /// Switch display mode to taking user input.
     pub async fn end_answer(&self) {
-        let _ = crossterm::execute!(std::io::stdout(), Print("\n"));
+        self.answer_marker_due.store(false, Ordering::Relaxed);
+        self.record(|sink| sink.flush());
+        if let Some(page) = &self.answer_page {
+            let text = std::mem::take(&mut *page.lock().unwrap());
+            pager::page_or_print(&text);
+        }
+        if self.json_output {
+            return;
+        }
+        let mut rest = self
+            .answer_wrap
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(|wrap| wrap.finish())
+            .unwrap_or_default();
+        if let Some(markdown) = self.answer_markdown.lock().unwrap().as_mut() {
+            rest = markdown.push(&rest) + &markdown.finish();
+        }
+        let _ = crossterm::execute!(std::io::stdout(), Print(rest), Print("\n"));
     }
 
     /// Append a text piece to the currently active inference output.
     pub async fn show_delta(&self, s: &str) {
         let phase = { *self.phase.read().unwrap() };
+        match phase {
+            Phase::Answering => self.record(|sink| sink.answer_delta(s)),
+            Phase::Thinking => self.record(|sink| sink.reasoning_delta(s)),
+            Phase::Commenting | Phase::Executing => {}
+        }
         match phase {
             Phase::Thinking => {
+                if self.caps.colorful && !self.caps.hidden.thinking {
+                    let _ = crossterm::execute!(
+                        std::io::stderr(),
+                        SetForegroundColor(self.caps.theme.reasoning),
+                        Print(s),
+                        ResetColor,
+                    );
+                }
+            }
+            Phase::Commenting => {
+                // Notes are not part of the answer, so keep them off `stdout`.
+                if self.caps.hidden.commentary {
+                    return;
+                }
                 if self.caps.colorful {
                     let _ = crossterm::execute!(
                         std::io::stderr(),
-                        SetForegroundColor(Color::DarkYellow),
+                        SetForegroundColor(self.caps.theme.notes),
                         Print(s),
                         ResetColor,
                     );
+                } else {
+                    eprint!("{s}");
                 }
             }
             Phase::Answering => {
-                // `stdout` should be free from control sequences so it can be piped.
-                let _ = crossterm::execute!(std::io::stdout(), Print(s));
+                if self.answer_marker_due.swap(false, Ordering::Relaxed) {
+                    self.show_marker("answer");
+                }
+                if self.json_output {
+                    return;
+                }
+                if let Some(page) = &self.answer_page {
+                    page.lock().unwrap().push_str(s);
+                    return;
+                }
+                // `stdout` should be free from control sequences so it can be piped;
+                // Markdown styling is only ever on when it is a terminal.
+                let mut text = match self.answer_wrap.lock().unwrap().as_mut() {
+                    Some(wrap) => wrap.push(s),
+                    None => s.to_string(),
+                };
+                if let Some(markdown) = self.answer_markdown.lock().unwrap().as_mut() {
+                    text = markdown.push(&text);
+                }
+                let _ = crossterm::execute!(std::io::stdout(), Print(text));
             }
             Phase::Executing => {
                 // should never happen
@@ -104,13 +240,32 @@ impl Display {
         }
     }
 
+    /// Show the per-turn timing breakdown below the answer.
+    pub async fn show_timings(&self, summary: &str) {
+        if self.caps.colorful {
+            let _ = crossterm::execute!(
+                std::io::stderr(),
+                SetForegroundColor(self.caps.theme.notes),
+                Print(summary),
+                ResetColor,
+                Print("\n"),
+            );
+        } else {
+            eprintln!("{summary}");
+        }
+    }
+
     /// Show a pretty-formatted tool/function call with its JSON arguments.
     pub async fn show_tool_call(&self, name: &str, args: &serde_json::Value) {
         let args = serde_json::to_string(args).unwrap_or_else(|_| args.to_string());
+        self.record(|sink| sink.tool_call(name, &args));
+        if self.caps.hidden.tool_calls {
+            return;
+        }
         if self.caps.colorful {
             let _ = crossterm::execute!(
                 std::io::stderr(),
-                SetForegroundColor(Color::DarkCyan),
+                SetForegroundColor(self.caps.theme.log),
                 Print(name),
                 Print(args),
                 ResetColor,
@@ -122,15 +277,37 @@ impl Display {
         }
     }
 
+    /// Show a tool's result in the technical readout, rendered according to its shape.
+    pub async fn show_tool_result(&self, name: &str, result: &serde_json::Value) {
+        if !self.caps.should_show_readout || self.caps.hidden.tool_output {
+            return;
+        }
+        self.show_result_size(name, result).await;
+        let rendered = results::render_tool_result(name, result);
+        for line in rendered.lines() {
+            self.show_log(line).await;
+        }
+    }
+
+    /// Note how much a large tool result adds to the context.
+    pub async fn show_result_size(&self, name: &str, result: &serde_json::Value) {
+        if !self.caps.should_show_readout {
+            return;
+        }
+        if let Some(note) = results::size_note(name, result) {
+            self.show_log(&note).await;
+        }
+    }
+
     /// Show stdout/stderr from a tool invocation.
     pub async fn show_tool_output(&self, name: &str, stdout: &str, stderr: &str) {
-        if stdout.is_empty() && stderr.is_empty() {
+        if (stdout.is_empty() && stderr.is_empty()) || self.caps.hidden.tool_output {
             return;
         }
         if self.caps.colorful {
             let _ = crossterm::execute!(
                 std::io::stderr(),
-                SetForegroundColor(Color::DarkCyan),
+                SetForegroundColor(self.caps.theme.log),
                 Print(format!("{name} output:")),
                 ResetColor,
                 Print("\n"),
@@ -147,13 +324,39 @@ impl Display {
         eprintln!();
     }
 
-    /// Ask the user to confirm executing a command represented by argv.
+    /// Ask the user to confirm executing a command, shown as its argv, env and stdin.
+    /// The command is always printed here, even when tool calls are hidden.
+    /// Warnings about risky writes follow it, in red when colorful.
     /// Returns true only if approved.
-    pub async fn confirm_run_command_execution(&self, _argv: &[String]) -> bool {
+    pub async fn confirm_run_command_execution(&self, preview: &str, warnings: &[String]) -> bool {
         if !self.caps.can_prompt_user {
             eprintln!("rejecting run_command in non-interactive mode");
             return false;
         }
+        if self.caps.colorful {
+            let _ = crossterm::execute!(
+                std::io::stderr(),
+                SetForegroundColor(self.caps.theme.preview),
+                Print(preview),
+                ResetColor,
+                Print("\n"),
+            );
+        } else {
+            eprintln!("{preview}");
+        }
+        for warning in warnings {
+            if self.caps.colorful {
+                let _ = crossterm::execute!(
+                    std::io::stderr(),
+                    SetForegroundColor(self.caps.theme.warning),
+                    Print(format!("warning: this command {warning}")),
+                    ResetColor,
+                    Print("\n"),
+                );
+            } else {
+                eprintln!("warning: this command {warning}");
+            }
+        }
         let _ = crossterm::execute!(std::io::stderr(), Print("Proceed? [y/N] "));
         yes_or_no()
     }
@@ -164,10 +367,19 @@ impl Display {
             eprintln!("rejecting apply_patch in non-interactive mode");
             return false;
         }
-        if self.caps.colorful {
+        if self.caps.colorful && preview.starts_with("--- ") {
+            let _ = crossterm::execute!(
+                std::io::stderr(),
+                Print("\n"),
+                Print(highlight::color_diff(preview, self.caps.theme.warning)),
+                SetForegroundColor(self.caps.theme.preview),
+                Print("Proceed? [y/N] "),
+                ResetColor,
+            );
+        } else if self.caps.colorful {
             let _ = crossterm::execute!(
                 std::io::stderr(),
-                SetForegroundColor(Color::DarkYellow),
+                SetForegroundColor(self.caps.theme.preview),
                 Print("\n"),
                 Print(preview),
                 Print("\nProceed? [y/N] "),
@@ -178,6 +390,94 @@ impl Display {
         yes_or_no()
     }
 
+    /// Ask once more before applying a patch that touches more files than the configured limit.
+    pub async fn confirm_large_patch(&self, files: usize, max_files: usize) -> bool {
+        if !self.caps.can_prompt_user {
+            eprintln!("rejecting apply_patch touching {files} files in non-interactive mode");
+            return false;
+        }
+        let question = format!(
+            "This patch touches {files} files, more than the limit of {max_files}. Really proceed? [y/N] "
+        );
+        if self.caps.colorful {
+            let _ = crossterm::execute!(
+                std::io::stderr(),
+                SetForegroundColor(self.caps.theme.preview),
+                Print(question),
+                ResetColor,
+            );
+        } else {
+            eprint!("{question}");
+        }
+        yes_or_no()
+    }
+
+    /// Ask the user whether the model may read a file or search under a path.
+    pub async fn confirm_read(&self, path: &str) -> bool {
+        if !self.caps.can_prompt_user {
+            eprintln!("rejecting read of {path} in non-interactive mode");
+            return false;
+        }
+        let question = format!("Let the model read {path}? [y/N] ");
+        if self.caps.colorful {
+            let _ = crossterm::execute!(
+                std::io::stderr(),
+                SetForegroundColor(self.caps.theme.preview),
+                Print(question),
+                ResetColor,
+            );
+        } else {
+            eprint!("{question}");
+        }
+        yes_or_no()
+    }
+
+    /// Put the model's question to the user and read one line of reply.
+    /// Returns `None` when nobody can answer: without a terminal, or when stdin is closed.
+    pub async fn ask_user(&self, question: &str) -> Option<String> {
+        if !self.caps.can_prompt_user {
+            return None;
+        }
+        let question = question.trim();
+        if self.caps.colorful {
+            let _ = crossterm::execute!(
+                std::io::stderr(),
+                SetForegroundColor(self.caps.theme.preview),
+                Print(question),
+                ResetColor,
+                Print("\n> "),
+            );
+        } else {
+            eprint!("{question}\n> ");
+        }
+        let mut reply = String::new();
+        match std::io::stdin().read_line(&mut reply) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(reply.trim().to_string()),
+        }
+    }
+
+    /// Recap turns, modified files, commands and tool errors when `PLEASE_SESSION_SUMMARY` is on.
+    pub async fn show_session_summary(&self) {
+        if !crate::config::get().session_summary {
+            return;
+        }
+        let Some(text) = self.summary.lock().unwrap().render() else {
+            return;
+        };
+        if self.caps.colorful {
+            let _ = crossterm::execute!(
+                std::io::stderr(),
+                SetForegroundColor(self.caps.theme.notes),
+                Print(text),
+                ResetColor,
+                Print("\n"),
+            );
+        } else {
+            eprintln!("{text}");
+        }
+    }
+
     /// Explain to the user how to get weights.
     pub async fn show_onboarding(&self) {
         if self.caps.colorful {
@@ -213,6 +513,28 @@ fn yes_or_no() -> bool {
     first_char.eq_ignore_ascii_case(&'y')
 }
 
+/// Open the file sinks the config asks for; one that cannot be opened is reported and skipped.
+fn open_sinks(config: &crate::config::Config) -> Vec<Mutex<Box<dyn sinks::Sink>>> {
+    let mut opened: Vec<Mutex<Box<dyn sinks::Sink>>> = Vec::new();
+    if let Some(path) = &config.transcript {
+        match transcript::Transcript::open(path, config.transcript_reasoning) {
+            Ok(transcript) => opened.push(Mutex::new(Box::new(transcript))),
+            Err(error) => eprintln!("cannot write transcript {}: {error}", path.display()),
+        }
+    }
+    if let Some(path) = &config.answer_file {
+        match sinks::AnswerFile::open(path) {
+            Ok(answers) => opened.push(Mutex::new(Box::new(answers))),
+            Err(error) => eprintln!("cannot write answers to {}: {error}", path.display()),
+        }
+    }
+    if config.output == crate::config::OutputFormat::Json {
+        let events = sinks::JsonEvents::new(std::io::stdout(), config.json_reasoning);
+        opened.push(Mutex::new(Box::new(events)));
+    }
+    opened
+}
+
 /// Create a streaming display. Prefer colorful UI on TTY stderr; fallback to plain printing.
 pub fn make_display() -> Display {
     let stderr_is_tty = atty::is(atty::Stream::Stderr);
@@ -222,14 +544,43 @@ pub fn make_display() -> Display {
     let hub_runs_in_foreground =
         ["run", "start"].contains(&std::env::args().nth(1).unwrap_or_default().as_str());
 
+    // Wrapping, paging and styling are for reading in a terminal; piped answers stay byte-for-byte.
+    let config = crate::config::get();
+    let json_output = config.output == crate::config::OutputFormat::Json;
+    let stdout_is_tty = atty::is(atty::Stream::Stdout) && !json_output;
+    let answer_page = (config.pager && stdout_is_tty).then(|| Mutex::new(String::new()));
     let caps = Caps {
         colorful: stderr_is_tty,
         can_prompt_user: stdin_is_tty && stderr_is_tty,
         should_show_readout: hub_runs_in_foreground
             || std::env::var("PLEASE_LOG_EVERYTHING").is_ok(),
+        // The pager gets the answer as written.
+        markdown: config.markdown && stdout_is_tty && answer_page.is_none(),
+        hidden: config.hidden,
+        theme: config.theme,
     };
+    let answer_markdown = caps
+        .markdown
+        .then(|| markdown::Markdown::new(caps.theme.code));
+    let answer_wrap = (config.wrap_answers && stdout_is_tty && answer_page.is_none())
+        .then(|| {
+            config.wrap_width.or_else(|| {
+                crossterm::terminal::size()
+                    .ok()
+                    .map(|(cols, _)| cols as usize)
+            })
+        })
+        .flatten()
+        .map(wrap::SoftWrap::new);
     Display {
         caps,
         phase: RwLock::new(Phase::Answering),
+        answer_wrap: Mutex::new(answer_wrap),
+        answer_markdown: Mutex::new(answer_markdown),
+        sinks: open_sinks(config),
+        answer_page,
+        json_output,
+        answer_marker_due: AtomicBool::new(false),
+        summary: Mutex::new(summary::SessionSummary::default()),
     }
 }
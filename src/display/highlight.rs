@@ -0,0 +1,245 @@
+//! Light syntax highlighting for code in answers and patch previews.
+//! Lines are colored one at a time, so strings and comments spanning lines show plain.
+use crossterm::style::{Color, Stylize};
+
+const KEYWORD: Color = Color::Magenta;
+const STRING: Color = Color::Green;
+const COMMENT: Color = Color::DarkGrey;
+const NUMBER: Color = Color::Cyan;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Language {
+    Rust,
+    Python,
+    Shell,
+    JavaScript,
+    Go,
+    C,
+    Json,
+    Toml,
+}
+
+impl Language {
+    /// The language named by a fence info string like `rust` or `py title="x"`.
+    pub(super) fn from_fence(info: &str) -> Option<Self> {
+        let name = info.split_whitespace().next()?.to_ascii_lowercase();
+        Self::from_name(&name)
+    }
+
+    /// The language of a file, judged by its extension.
+    pub(super) fn from_path(path: &str) -> Option<Self> {
+        let (_, extension) = path.rsplit_once('.')?;
+        Self::from_name(&extension.to_ascii_lowercase())
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "rust" | "rs" => Self::Rust,
+            "python" | "py" => Self::Python,
+            "sh" | "bash" | "zsh" | "shell" | "console" => Self::Shell,
+            "javascript" | "js" | "jsx" | "mjs" | "typescript" | "ts" | "tsx" => Self::JavaScript,
+            "go" => Self::Go,
+            "c" | "h" | "cpp" | "cc" | "hpp" | "c++" => Self::C,
+            "json" => Self::Json,
+            "toml" => Self::Toml,
+            _ => return None,
+        })
+    }
+
+    /// Keywords and literals worth coloring, separated by spaces.
+    fn keywords(self) -> &'static str {
+        match self {
+            Self::Rust => concat!(
+                "as async await break const continue crate dyn else enum false fn for ",
+                "if impl in let loop match mod move mut pub ref return self Self ",
+                "static struct super trait true type unsafe use where while",
+            ),
+            Self::Python => concat!(
+                "and as assert async await break class continue def del elif else ",
+                "except False finally for from if import in is lambda None not or pass ",
+                "raise return True try while with yield",
+            ),
+            Self::Shell => concat!(
+                "case do done elif else esac export fi for function if in local return ",
+                "then until while",
+            ),
+            Self::JavaScript => concat!(
+                "async await break case catch class const continue default else export ",
+                "extends false finally for from function if import interface let new ",
+                "null return switch this throw true try type typeof undefined var ",
+                "while",
+            ),
+            Self::Go => concat!(
+                "break case chan const continue default defer else false for func go ",
+                "if import interface map nil package range return select struct switch ",
+                "true type var",
+            ),
+            Self::C => concat!(
+                "auto bool break case char class const continue default do double else ",
+                "enum false float for if include int long namespace nullptr return ",
+                "sizeof static struct switch template true typedef unsigned void while",
+            ),
+            Self::Json => "false null true",
+            Self::Toml => "false true",
+        }
+    }
+
+    fn line_comment(self) -> Option<&'static str> {
+        match self {
+            Self::Rust | Self::JavaScript | Self::Go | Self::C => Some("//"),
+            Self::Python | Self::Shell | Self::Toml => Some("#"),
+            Self::Json => None,
+        }
+    }
+
+    /// Rust uses `'` for lifetimes as well as characters, so only `"` starts a string there.
+    fn quotes(self) -> &'static [char] {
+        match self {
+            Self::Rust | Self::Json => &['"'],
+            Self::Go | Self::JavaScript => &['"', '\'', '`'],
+            _ => &['"', '\''],
+        }
+    }
+}
+
+/// Color keywords, strings, numbers and comments in one line of code.
+pub(super) fn highlight_line(language: Language, line: &str) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        if let Some(marker) = language.line_comment()
+            && rest.starts_with(marker)
+        {
+            out.push_str(&rest.with(COMMENT).to_string());
+            break;
+        }
+        let end = if language.quotes().contains(&c) {
+            string_end(rest, c)
+        } else if c.is_ascii_digit() {
+            rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_'))
+                .unwrap_or(rest.len())
+        } else if c.is_alphabetic() || c == '_' {
+            rest.find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len())
+        } else {
+            c.len_utf8()
+        };
+        let (token, after) = rest.split_at(end);
+        let color = if language.quotes().contains(&c) {
+            Some(STRING)
+        } else if c.is_ascii_digit() {
+            Some(NUMBER)
+        } else if language
+            .keywords()
+            .split(' ')
+            .any(|keyword| keyword == token)
+        {
+            Some(KEYWORD)
+        } else {
+            None
+        };
+        match color {
+            Some(color) => out.push_str(&token.with(color).to_string()),
+            None => out.push_str(token),
+        }
+        rest = after;
+    }
+    out
+}
+
+/// Where the string opened by `quote` at the start of `text` ends, past its closing quote.
+fn string_end(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (at, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return at + c.len_utf8(),
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// Color a unified-diff preview: headers bold, added lines green, removed lines red,
+/// and context highlighted by the language of the file named in the `+++` header, else grey.
+/// `will fail:` notes appended by the approval gate take the warning color.
+pub(super) fn color_diff(preview: &str, warning: Color) -> String {
+    let mut language = None;
+    let mut out = String::new();
+    for line in preview.lines() {
+        let styled = if let Some(path) = line.strip_prefix("+++ ") {
+            language = Language::from_path(path.trim());
+            line.bold().to_string()
+        } else if line.starts_with("--- ") {
+            line.bold().to_string()
+        } else if line.starts_with("@@") {
+            line.with(Color::Cyan).to_string()
+        } else if line.starts_with('+') {
+            line.with(Color::Green).to_string()
+        } else if line.starts_with('-') {
+            line.with(Color::Red).to_string()
+        } else if line.starts_with("will fail: ") {
+            line.with(warning).to_string()
+        } else {
+            match (language, line.strip_prefix(' ')) {
+                (Some(language), Some(code)) => format!(" {}", highlight_line(language, code)),
+                _ => line.with(Color::DarkGrey).to_string(),
+            }
+        };
+        out.push_str(&styled);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages_come_from_fences_and_extensions() {
+        assert_eq!(Language::from_fence("rust"), Some(Language::Rust));
+        assert_eq!(Language::from_fence("Py title=x"), Some(Language::Python));
+        assert_eq!(Language::from_fence(""), None);
+        assert_eq!(Language::from_fence("brainfuck"), None);
+        assert_eq!(Language::from_path("src/main.rs"), Some(Language::Rust));
+        assert_eq!(
+            Language::from_path("web/app.tsx"),
+            Some(Language::JavaScript)
+        );
+        assert_eq!(Language::from_path("Makefile"), None);
+    }
+
+    #[test]
+    fn keywords_strings_numbers_and_comments_are_colored() {
+        let line = r#"let s = "a \" b"; // 42 is a comment"#;
+        let expected = format!(
+            "{} s = {}; {}",
+            "let".with(KEYWORD),
+            r#""a \" b""#.with(STRING),
+            "// 42 is a comment".with(COMMENT)
+        );
+        assert_eq!(highlight_line(Language::Rust, line), expected);
+        assert_eq!(
+            highlight_line(Language::Python, "x = 10"),
+            format!("x = {}", "10".with(NUMBER))
+        );
+        // Identifiers that merely contain a keyword are left alone.
+        assert_eq!(highlight_line(Language::Rust, "letter"), "letter");
+    }
+
+    #[test]
+    fn diffs_color_changes_and_highlight_context() {
+        let preview = "--- a.rs\n+++ a.rs\n@@\n fn main() {}\n-old\n+new\nwill fail: update a.rs";
+        let colored = color_diff(preview, Color::Red);
+        let lines = colored.lines().collect::<Vec<_>>();
+        assert_eq!(lines[3], format!(" {} main() {{}}", "fn".with(KEYWORD)));
+        assert_eq!(lines[4], "-old".with(Color::Red).to_string());
+        assert_eq!(lines[5], "+new".with(Color::Green).to_string());
+        assert_eq!(
+            lines[6],
+            "will fail: update a.rs".with(Color::Red).to_string()
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Basic Markdown styling for answers streamed to a terminal.
+use crossterm::style::{Color, Stylize};
+
+use super::highlight::{Language, highlight_line};
+
+/// Styles streamed answer text one line at a time: headings and `**bold**` in bold,
+/// `inline code` and fenced code blocks in the code color, or highlighted when the fence
+/// names a language we know.
+/// A line is held back until it ends, since its first characters decide how it looks.
+pub(super) struct Markdown {
+    code: Color,
+    line: String,
+    in_fence: bool,
+    /// The language of the open fence, if it named one we know.
+    fence_language: Option<Language>,
+}
+
+impl Markdown {
+    pub(super) fn new(code: Color) -> Self {
+        Self {
+            code,
+            line: String::new(),
+            in_fence: false,
+            fence_language: None,
+        }
+    }
+
+    /// Accept a streamed piece and return the styled lines it completed.
+    pub(super) fn push(&mut self, piece: &str) -> String {
+        let mut out = String::new();
+        for c in piece.chars() {
+            if c == '\n' {
+                let line = std::mem::take(&mut self.line);
+                out.push_str(&self.render_line(&line));
+                out.push('\n');
+            } else {
+                self.line.push(c);
+            }
+        }
+        out
+    }
+
+    /// Return the unfinished last line, styled, and start over for the next answer.
+    pub(super) fn finish(&mut self) -> String {
+        let line = std::mem::take(&mut self.line);
+        let out = self.render_line(&line);
+        self.in_fence = false;
+        out
+    }
+
+    fn render_line(&mut self, line: &str) -> String {
+        if let Some(info) = line.trim_start().strip_prefix("```") {
+            self.in_fence = !self.in_fence;
+            self.fence_language = Language::from_fence(info.trim_start_matches('`'));
+            return line.with(self.code).dim().to_string();
+        }
+        if self.in_fence {
+            return match self.fence_language {
+                Some(language) => highlight_line(language, line),
+                None => line.with(self.code).to_string(),
+            };
+        }
+        let hashes = line.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&hashes) && line[hashes..].starts_with(' ') {
+            return self
+                .render_inline(line[hashes..].trim_start())
+                .bold()
+                .to_string();
+        }
+        self.render_inline(line)
+    }
+
+    /// Style `code` spans and `**bold**` runs; markers without a partner stay as typed.
+    fn render_inline(&self, line: &str) -> String {
+        let mut out = String::new();
+        let mut rest = line;
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('`')
+                && let Some(end) = after.find('`')
+            {
+                out.push_str(&after[..end].with(self.code).to_string());
+                rest = &after[end + 1..];
+            } else if let Some(after) = rest.strip_prefix("**")
+                && let Some(end) = after.find("**")
+                && end > 0
+            {
+                out.push_str(&after[..end].bold().to_string());
+                rest = &after[end + 2..];
+            } else {
+                let first = rest.chars().next().map_or(1, char::len_utf8);
+                let next = rest[first..]
+                    .find(['`', '*'])
+                    .map_or(rest.len(), |at| at + first);
+                out.push_str(&rest[..next]);
+                rest = &rest[next..];
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(text: &str) -> String {
+        let mut markdown = Markdown::new(Color::DarkGreen);
+        let mut out = String::new();
+        // Split mid-line to check that pieces are stitched back together.
+        for piece in text.as_bytes().chunks(3) {
+            out.push_str(&markdown.push(std::str::from_utf8(piece).unwrap()));
+        }
+        out + &markdown.finish()
+    }
+
+    #[test]
+    fn headings_bold_and_code_are_styled() {
+        let code = |s: &str| s.with(Color::DarkGreen).to_string();
+        assert_eq!(
+            render("## Plan\nrun `make` **now**\n"),
+            format!("{}\nrun {} {}\n", "Plan".bold(), code("make"), "now".bold())
+        );
+        assert_eq!(render("2 * 3 is `6"), "2 * 3 is `6");
+        assert_eq!(render("#hashtag"), "#hashtag");
+    }
+
+    #[test]
+    fn fenced_blocks_are_colored_verbatim() {
+        let code = |s: &str| s.with(Color::DarkGreen).to_string();
+        let fence = |s: &str| s.with(Color::DarkGreen).dim().to_string();
+        assert_eq!(
+            render("```text\nlet **x** = 1;\n```\ndone"),
+            format!(
+                "{}\n{}\n{}\ndone",
+                fence("```text"),
+                code("let **x** = 1;"),
+                fence("```")
+            )
+        );
+    }
+
+    #[test]
+    fn fences_naming_a_language_are_highlighted() {
+        assert_eq!(
+            render("```rust\nlet x = 1;\n```\n"),
+            format!(
+                "{}\n{}\n{}\n",
+                "```rust".with(Color::DarkGreen).dim(),
+                highlight_line(Language::Rust, "let x = 1;"),
+                "```".with(Color::DarkGreen).dim()
+            )
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! Hand long answers to the user's pager once they are complete.
+use std::io::Write;
+
+/// Print `text`, going through `$PAGER` when it is taller than the terminal.
+/// Falls back to printing directly whenever the pager cannot be used.
+pub(super) fn page_or_print(text: &str) {
+    let rows = crossterm::terminal::size()
+        .map(|(_, rows)| rows as usize)
+        .unwrap_or(usize::MAX);
+    if text.lines().count() >= rows && paged(text).is_ok() {
+        return;
+    }
+    print!("{text}");
+    let _ = std::io::stdout().flush();
+}
+
+fn paged(text: &str) -> std::io::Result<()> {
+    let argv = pager_argv(std::env::var("PAGER").ok().as_deref());
+    let mut child = std::process::Command::new(&argv[0])
+        .args(&argv[1..])
+        .stdin(std::process::Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The user may quit the pager early; a broken pipe is not an error then.
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// Split `$PAGER` into argv, defaulting to `less -R` so colors survive.
+fn pager_argv(pager: Option<&str>) -> Vec<String> {
+    let argv = pager
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if argv.is_empty() {
+        vec!["less".to_string(), "-R".to_string()]
+    } else {
+        argv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pager_falls_back_to_less_and_keeps_arguments() {
+        assert_eq!(pager_argv(None), ["less", "-R"]);
+        assert_eq!(pager_argv(Some("  ")), ["less", "-R"]);
+        assert_eq!(pager_argv(Some("most -s")), ["most", "-s"]);
+    }
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
+use crossterm::style::{Print, ResetColor, SetForegroundColor};
 use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
 use tokio::task::JoinHandle;
 
@@ -33,7 +33,7 @@ impl Drop for ExecutionPane {
 
 impl Display {
     pub fn start_executing(self: &Arc<Self>) -> Option<ExecutionPane> {
-        if !self.caps.colorful {
+        if !self.caps.colorful || self.caps.hidden.tool_output {
             return None;
         }
         *self.phase.write().unwrap() = Phase::Executing;
@@ -60,7 +60,7 @@ impl Display {
         }
         let _ = crossterm::execute!(
             std::io::stderr(),
-            SetForegroundColor(Color::DarkYellow),
+            SetForegroundColor(self.caps.theme.preview),
             Print(chunk),
             ResetColor
         );
@@ -0,0 +1,114 @@
+//! Human-readable rendering of tool results for the technical readout.
+use serde_json::Value;
+
+/// Render a tool result according to the shape that tool is known to return.
+/// Unknown tools and unexpected shapes fall back to compact JSON.
+pub(super) fn render_tool_result(name: &str, result: &Value) -> String {
+    if let Some(error) = result.get("error") {
+        let error = error
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string());
+        return format!("error: {error}");
+    }
+    match (name, result) {
+        ("read_file", Value::String(content)) => content.clone(),
+        ("list_files", Value::Array(entries)) => entries
+            .iter()
+            .map(|entry| {
+                // Detailed listings are objects; their path is what reads well.
+                entry
+                    .as_str()
+                    .or_else(|| entry.get("path").and_then(Value::as_str))
+                    .map(str::to_string)
+                    .unwrap_or_else(|| entry.to_string())
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ("run_command" | "control_command", Value::Object(fields)) => {
+            let text = |key: &str| fields.get(key).and_then(Value::as_str).unwrap_or("");
+            let mut out = String::new();
+            out.push_str(text("status"));
+            if let Some(code) = fields.get("exitCode").and_then(Value::as_i64) {
+                out.push_str(&format!(" (exit code {code})"));
+            }
+            for key in ["stdout", "stderr"] {
+                let stream = text(key);
+                if !stream.is_empty() {
+                    out.push_str(&format!("\n{key}:\n{}", stream.trim_end()));
+                }
+            }
+            out
+        }
+        _ => serde_json::to_string(result).unwrap_or_else(|_| result.to_string()),
+    }
+}
+
+/// Results at least this large get a size note, since they take a visible bite of the context.
+const NOTABLE_RESULT_BYTES: usize = 4 * 1024;
+
+/// A note such as `(read 42KB)` for results large enough to matter, measured as sent to the model.
+pub(super) fn size_note(name: &str, result: &Value) -> Option<String> {
+    let bytes = serde_json::to_string(result).map_or(0, |text| text.len());
+    if bytes < NOTABLE_RESULT_BYTES {
+        return None;
+    }
+    let size = if bytes >= 1024 * 1024 {
+        format!("{:.1}MB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{}KB", bytes.div_ceil(1024))
+    };
+    Some(match name {
+        "read_file" => format!("(read {size})"),
+        _ => format!("({name} returned {size})"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn file_content_is_shown_verbatim() {
+        let out = render_tool_result("read_file", &json!("fn main() {\n    \"hi\"\n}\n"));
+        assert_eq!(out, "fn main() {\n    \"hi\"\n}\n");
+    }
+
+    #[test]
+    fn listings_are_shown_one_per_line() {
+        let out = render_tool_result("list_files", &json!(["src/", "src/main.rs"]));
+        assert_eq!(out, "src/\nsrc/main.rs");
+    }
+
+    #[test]
+    fn command_output_is_shown_as_terminal_text() {
+        let result =
+            json!({ "status": "finished", "exitCode": 1, "stdout": "", "stderr": "boom\n" });
+        let out = render_tool_result("run_command", &result);
+        assert_eq!(out, "finished (exit code 1)\nstderr:\nboom");
+    }
+
+    #[test]
+    fn errors_and_unknown_shapes_stay_readable() {
+        let out = render_tool_result("read_file", &json!({ "error": "not found" }));
+        assert_eq!(out, "error: not found");
+        let out = render_tool_result("apply_patch", &json!({ "ok": true }));
+        assert_eq!(out, r#"{"ok":true}"#);
+    }
+
+    #[test]
+    fn only_large_results_get_a_size_note() {
+        assert_eq!(size_note("read_file", &json!("short")), None);
+        let big = json!("x".repeat(42 * 1024 - 2));
+        assert_eq!(size_note("read_file", &big).as_deref(), Some("(read 42KB)"));
+        assert_eq!(
+            size_note(
+                "run_command",
+                &json!({ "stdout": "x".repeat(2 * 1024 * 1024) })
+            )
+            .as_deref(),
+            Some("(run_command returned 2.0MB)")
+        );
+    }
+}
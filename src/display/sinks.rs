@@ -0,0 +1,143 @@
+//! Files that receive a copy of the session next to the terminal, each configured on its own,
+//! and the JSON event stream that stands in for the plain answer on stdout.
+use std::io::Write;
+use std::path::Path;
+
+/// Receives what the display shows. Each sink keeps what it cares about and ignores the rest.
+pub(super) trait Sink: Send {
+    fn prompt(&mut self, _text: &str) {}
+    fn answer_delta(&mut self, _delta: &str) {}
+    fn reasoning_delta(&mut self, _delta: &str) {}
+    /// The streamed phase ended; write out what it produced.
+    fn flush(&mut self) {}
+    fn tool_call(&mut self, _name: &str, _args_json: &str) {}
+    fn tool_result(&mut self, _name: &str, _rendered: &str, _result: &serde_json::Value) {}
+}
+
+/// Appends the bare answers to a file, one after another, without prompts or decoration.
+pub(super) struct AnswerFile {
+    file: std::fs::File,
+    answer: String,
+}
+
+impl AnswerFile {
+    pub(super) fn open(path: &Path) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self {
+            file,
+            answer: String::new(),
+        })
+    }
+}
+
+impl Sink for AnswerFile {
+    fn answer_delta(&mut self, delta: &str) {
+        self.answer.push_str(delta);
+    }
+
+    fn flush(&mut self) {
+        let answer = std::mem::take(&mut self.answer);
+        let answer = answer.trim();
+        if !answer.is_empty() {
+            let _ = writeln!(self.file, "{answer}");
+        }
+    }
+}
+
+/// Writes one JSON object per line: `delta` and `reasoning` pieces as they stream,
+/// `tool_call` and `tool_result` as tools run, and `answer_end` when an answer is done.
+pub(super) struct JsonEvents<W: Write + Send> {
+    out: W,
+    include_reasoning: bool,
+}
+
+impl<W: Write + Send> JsonEvents<W> {
+    pub(super) fn new(out: W, include_reasoning: bool) -> Self {
+        Self {
+            out,
+            include_reasoning,
+        }
+    }
+
+    fn emit(&mut self, event: serde_json::Value) {
+        let _ = writeln!(self.out, "{event}");
+        let _ = self.out.flush();
+    }
+}
+
+impl<W: Write + Send> Sink for JsonEvents<W> {
+    fn answer_delta(&mut self, delta: &str) {
+        self.emit(serde_json::json!({ "type": "delta", "text": delta }));
+    }
+
+    fn reasoning_delta(&mut self, delta: &str) {
+        if self.include_reasoning {
+            self.emit(serde_json::json!({ "type": "reasoning", "text": delta }));
+        }
+    }
+
+    fn flush(&mut self) {
+        self.emit(serde_json::json!({ "type": "answer_end" }));
+    }
+
+    fn tool_call(&mut self, name: &str, args_json: &str) {
+        let arguments = serde_json::from_str(args_json).unwrap_or_else(|_| args_json.into());
+        self.emit(serde_json::json!({ "type": "tool_call", "name": name, "arguments": arguments }));
+    }
+
+    fn tool_result(&mut self, name: &str, _rendered: &str, result: &serde_json::Value) {
+        self.emit(serde_json::json!({ "type": "tool_result", "name": name, "result": result }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answer_files_hold_only_the_answers() {
+        let path = std::env::temp_dir().join(format!("please-answers-{}.txt", std::process::id()));
+        let mut sink = AnswerFile::open(&path).unwrap();
+        sink.prompt("what is it?");
+        sink.reasoning_delta("thinking");
+        sink.answer_delta("It is ");
+        sink.answer_delta("fine.\n");
+        sink.flush();
+        sink.tool_call("read_file", "{}");
+        sink.flush();
+        sink.answer_delta("Done.");
+        sink.flush();
+        let written = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(written, "It is fine.\nDone.\n");
+    }
+
+    #[test]
+    fn json_events_are_one_parseable_object_per_line() {
+        let mut out = Vec::new();
+        let mut sink = JsonEvents::new(&mut out, false);
+        sink.reasoning_delta("hidden");
+        sink.tool_call("read_file", r#"{"path":"a \"b\".rs"}"#);
+        sink.tool_result("read_file", "ok", &serde_json::json!({ "ok": true }));
+        sink.answer_delta("line one\n");
+        sink.answer_delta("line two");
+        sink.flush();
+
+        let events = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        let types = events.iter().map(|e| e["type"].clone()).collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            ["tool_call", "tool_result", "delta", "delta", "answer_end"]
+        );
+        assert_eq!(events[0]["arguments"]["path"], "a \"b\".rs");
+        assert_eq!(events[1]["result"]["ok"], true);
+        assert_eq!(events[2]["text"], "line one\n");
+    }
+}
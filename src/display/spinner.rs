@@ -4,7 +4,7 @@ use crossterm::cursor;
 use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
 use crossterm::terminal::{Clear, ClearType};
 
-async fn display_spinner() {
+async fn display_spinner(color: Color) {
     use std::time::Duration;
     let frames = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
     let mut index: usize = 0;
@@ -15,7 +15,7 @@ async fn display_spinner() {
         let _ = crossterm::execute!(
             std::io::stderr(),
             Print("\r"),
-            SetForegroundColor(Color::DarkGrey),
+            SetForegroundColor(color),
             Print(frame),
             ResetColor
         );
@@ -39,9 +39,9 @@ impl Spinner {
     }
 
     /// Immediately start a task that will show a spinner until dropped.
-    pub(super) fn start() -> Self {
+    pub(super) fn start(color: Color) -> Self {
         Spinner {
-            task: Some(tokio::spawn(display_spinner())),
+            task: Some(tokio::spawn(display_spinner(color))),
         }
     }
 
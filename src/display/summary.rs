@@ -0,0 +1,101 @@
+//! Tally of what happened in a session, for the recap printed when it ends.
+use std::collections::BTreeSet;
+
+use crate::tools::{ToolKind, kind_of};
+
+#[derive(Default)]
+pub(super) struct SessionSummary {
+    turns: usize,
+    /// Paths that `apply_patch` wrote, added or deleted, and files `create_file` or `write_file` wrote.
+    files: BTreeSet<String>,
+    commands: usize,
+    /// Tool calls that came back with an error.
+    errors: usize,
+}
+
+impl SessionSummary {
+    pub(super) fn prompt(&mut self) {
+        self.turns += 1;
+    }
+
+    pub(super) fn tool_result(&mut self, name: &str, result: &serde_json::Value) {
+        let kind = kind_of(name);
+        if kind == ToolKind::RunCommand {
+            self.commands += 1;
+        }
+        let results = result
+            .get("results")
+            .and_then(|results| results.as_array())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let failed = result.get("error").is_some() || results.iter().any(|r| r["ok"] == false);
+        if failed {
+            self.errors += 1;
+        }
+        // The paths a dry run reports were left alone.
+        let dry_run = result.get("dry_run").is_some();
+        if !matches!(
+            kind,
+            ToolKind::ApplyPatch | ToolKind::CreateFile | ToolKind::WriteFile
+        ) || dry_run
+        {
+            return;
+        }
+        // Overwrite mode and the file tools report one path at the top; patch mode reports each file.
+        let written = std::iter::once(result)
+            .chain(results)
+            .filter(|r| r["ok"] == true)
+            .filter_map(|r| r.get("path").and_then(|path| path.as_str()));
+        self.files.extend(written.map(str::to_string));
+    }
+
+    /// The recap, or nothing when no prompt was answered.
+    pub(super) fn render(&self) -> Option<String> {
+        if self.turns == 0 {
+            return None;
+        }
+        let plural = |n: usize, what: &str| format!("{n} {what}{}", if n == 1 { "" } else { "s" });
+        let mut text = format!(
+            "session: {}, {}, {}, {}",
+            plural(self.turns, "turn"),
+            plural(self.files.len(), "file"),
+            plural(self.commands, "command"),
+            plural(self.errors, "tool error"),
+        );
+        for path in &self.files {
+            text.push_str("\n  modified ");
+            text.push_str(path);
+        }
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn patches_commands_and_errors_are_tallied() {
+        let mut summary = SessionSummary::default();
+        assert_eq!(summary.render(), None);
+
+        summary.prompt();
+        summary.tool_result(
+            "apply_patch",
+            &json!({ "ok": true, "mode": "patch", "results": [
+                { "path": "src/a.rs", "op": "update", "ok": true },
+                { "path": "src/b.rs", "op": "add", "ok": false, "error": "exists" },
+            ] }),
+        );
+        summary.tool_result(
+            "apply_patch",
+            &json!({ "ok": true, "mode": "overwrite", "path": "notes.md" }),
+        );
+        summary.tool_result("run_command", &json!({ "status": "finished" }));
+        assert_eq!(
+            summary.render().unwrap(),
+            "session: 1 turn, 2 files, 1 command, 1 tool error\n  modified notes.md\n  modified src/a.rs"
+        );
+    }
+}
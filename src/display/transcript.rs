@@ -0,0 +1,102 @@
+//! Markdown record of a session, written alongside the terminal output.
+use std::io::Write;
+use std::path::Path;
+
+use super::sinks::Sink;
+
+/// Appends timestamped sections to a markdown file.
+/// Streamed text is collected and written as one section once its phase ends.
+pub(super) struct Transcript {
+    file: std::fs::File,
+    include_reasoning: bool,
+    answer: String,
+    reasoning: String,
+}
+
+impl Transcript {
+    pub(super) fn open(path: &Path, include_reasoning: bool) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self {
+            file,
+            include_reasoning,
+            answer: String::new(),
+            reasoning: String::new(),
+        })
+    }
+
+    fn section(&mut self, title: &str, body: &str) {
+        let _ = write!(self.file, "## {title} ({})\n\n{body}\n\n", timestamp());
+    }
+}
+
+impl Sink for Transcript {
+    fn prompt(&mut self, text: &str) {
+        self.section("User", text.trim());
+    }
+
+    fn answer_delta(&mut self, delta: &str) {
+        self.answer.push_str(delta);
+    }
+
+    fn reasoning_delta(&mut self, delta: &str) {
+        if self.include_reasoning {
+            self.reasoning.push_str(delta);
+        }
+    }
+
+    /// Write out whatever was streamed since the last flush.
+    fn flush(&mut self) {
+        let reasoning = std::mem::take(&mut self.reasoning);
+        if !reasoning.trim().is_empty() {
+            self.section("Reasoning", &quote(reasoning.trim()));
+        }
+        let answer = std::mem::take(&mut self.answer);
+        if !answer.trim().is_empty() {
+            self.section("Answer", answer.trim());
+        }
+    }
+
+    fn tool_call(&mut self, name: &str, args_json: &str) {
+        self.flush();
+        self.section(&format!("Tool call `{name}`"), &fence("json", args_json));
+    }
+
+    fn tool_result(&mut self, name: &str, rendered: &str, _result: &serde_json::Value) {
+        self.section(&format!("Tool result `{name}`"), &fence("", rendered));
+    }
+}
+
+fn timestamp() -> String {
+    let now = time::OffsetDateTime::now_local().unwrap_or_else(|_| time::OffsetDateTime::now_utc());
+    now.format(&time::format_description::well_known::Rfc3339)
+        .unwrap_or_default()
+}
+
+/// Fence text so markdown in tool output cannot break the transcript layout.
+fn fence(lang: &str, text: &str) -> String {
+    // Use a fence longer than any backtick run inside the text.
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let ticks = "`".repeat(longest.max(2) + 1);
+    format!("{ticks}{lang}\n{}\n{ticks}", text.trim_end())
+}
+
+fn quote(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("> {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fences_outgrow_backticks_in_the_content() {
+        assert_eq!(fence("", "plain"), "```\nplain\n```");
+        assert_eq!(fence("md", "a ```b``` c"), "````md\na ```b``` c\n````");
+    }
+}
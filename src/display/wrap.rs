@@ -0,0 +1,111 @@
+//! Word-boundary soft wrapping for answers streamed to a terminal.
+
+/// Wraps streamed text at a fixed width without waiting for whole lines.
+/// Only the word being typed is held back, so output lags by at most one word.
+/// Fenced code blocks pass through unwrapped.
+pub(super) struct SoftWrap {
+    width: usize,
+    column: usize,
+    word: String,
+    in_fence: bool,
+}
+
+impl SoftWrap {
+    pub(super) fn new(width: usize) -> Self {
+        Self {
+            width: width.max(1),
+            column: 0,
+            word: String::new(),
+            in_fence: false,
+        }
+    }
+
+    /// Accept a streamed piece and return the text that is ready to print.
+    pub(super) fn push(&mut self, piece: &str) -> String {
+        let mut out = String::new();
+        for c in piece.chars() {
+            match c {
+                '\n' => {
+                    self.emit_word(&mut out);
+                    out.push('\n');
+                    self.column = 0;
+                }
+                ' ' => {
+                    self.emit_word(&mut out);
+                    if self.column >= self.width && !self.in_fence {
+                        // The space would land past the edge; break the line there instead.
+                        out.push('\n');
+                        self.column = 0;
+                    } else {
+                        out.push(' ');
+                        self.column += 1;
+                    }
+                }
+                _ => self.word.push(c),
+            }
+        }
+        out
+    }
+
+    /// Release the held-back word and start the next answer at the first column.
+    pub(super) fn finish(&mut self) -> String {
+        let mut out = String::new();
+        self.emit_word(&mut out);
+        self.column = 0;
+        self.in_fence = false;
+        out
+    }
+
+    fn emit_word(&mut self, out: &mut String) {
+        if self.word.is_empty() {
+            return;
+        }
+        let len = self.word.chars().count();
+        if self.column == 0 && self.word.starts_with("```") {
+            self.in_fence = !self.in_fence;
+        }
+        if !self.in_fence && self.column > 0 && self.column + len > self.width {
+            out.push('\n');
+            self.column = 0;
+        }
+        out.push_str(&self.word);
+        self.column += len;
+        self.word.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrap_pieces(width: usize, pieces: &[&str]) -> String {
+        let mut wrap = SoftWrap::new(width);
+        let mut out = pieces
+            .iter()
+            .map(|piece| wrap.push(piece))
+            .collect::<String>();
+        out.push_str(&wrap.finish());
+        out
+    }
+
+    #[test]
+    fn words_move_to_the_next_line_instead_of_splitting() {
+        let out = wrap_pieces(10, &["the quick br", "own fox jumps"]);
+        assert_eq!(out, "the quick \nbrown fox \njumps");
+    }
+
+    #[test]
+    fn explicit_newlines_reset_the_column() {
+        let out = wrap_pieces(8, &["short\nanother line"]);
+        assert_eq!(out, "short\nanother \nline");
+    }
+
+    #[test]
+    fn fenced_code_is_left_alone() {
+        let out = wrap_pieces(8, &["```\nlet value = compute();\n```\nplain words here"]);
+        assert_eq!(
+            out,
+            "```\nlet value = compute();\n```\nplain \nwords \nhere"
+        );
+    }
+}
@@ -2,6 +2,45 @@
 use crate::prompting::SYSTEM_PREAMBLE;
 use crate::protocol::Message;
 
+/// Reasoning effort asked for with `PLEASE_TRY`, e.g. `PLEASE_TRY=hard`; medium by default.
+pub fn reasoning_level(direct: bool) -> &'static str {
+    // Direct mode overrides the requested effort; thinking is what it exists to skip.
+    if direct {
+        return "low";
+    }
+    std::env::var("PLEASE_TRY")
+        .ok()
+        .map(|v| v.trim().to_lowercase())
+        .and_then(|v| match v.as_str() {
+            _ if v.starts_with("h") => Some("high"),
+            _ if v.starts_with("m") => Some("medium"),
+            _ if v.starts_with("l") => Some("low"),
+            _ if v.starts_with("e") => Some("low"),
+            _ => None,
+        })
+        .unwrap_or("medium")
+}
+
+/// State another reasoning effort in the system message, as a request may ask of the hub.
+/// Levels other than `low`, `medium` and `high` are ignored.
+pub fn set_reasoning(history: &mut [Message], level: &str) {
+    if !matches!(level, "low" | "medium" | "high") {
+        return;
+    }
+    let Some(Message::System(system)) = history.first_mut() else {
+        return;
+    };
+    *system = system
+        .lines()
+        .map(|line| match line.strip_prefix("Reasoning: ") {
+            Some(_) => format!("Reasoning: {level}"),
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + if system.ends_with('\n') { "\n" } else { "" };
+}
+
 /// Compose a full session history from the default preamble
 /// and optional stdin/extra contexts in the canonical order.
 pub fn make_history(
@@ -10,26 +49,28 @@ pub fn make_history(
 ) -> Vec<Message> {
     let now = time::OffsetDateTime::now_local().unwrap_or_else(|_| time::OffsetDateTime::now_utc());
     let now = now.date().to_string();
-    let reasoning = std::env::var("PLEASE_TRY")
-        .ok()
-        .map(|v| v.trim().to_lowercase())
-        .and_then(|v| match v.as_str() {
-            _ if v.starts_with("h") => Some("high".to_string()),
-            _ if v.starts_with("m") => Some("medium".to_string()),
-            _ if v.starts_with("l") => Some("low".to_string()),
-            _ if v.starts_with("e") => Some("low".to_string()),
-            _ => None,
-        })
-        .unwrap_or_else(|| "medium".to_string());
+    let direct = crate::config::get().direct;
     let mut history = vec![Message::System(
         SYSTEM_PREAMBLE
             .replace("¶cutoff", "2024-06")
             .replace("¶today", &now)
-            .replace("¶reasoning", &reasoning),
+            .replace("¶reasoning", reasoning_level(direct)),
     )];
-    let guidance = crate::prompting::TOOL_GUIDANCE.trim();
-    if !guidance.is_empty() {
-        history.push(Message::Developer(guidance.to_string()));
+    let config = crate::config::get();
+    if crate::tools::all_tools().is_empty() {
+        history.push(Message::Developer(
+            crate::prompting::NO_TOOLS_GUIDANCE.trim().to_string(),
+        ));
+    } else {
+        let guidance = crate::prompting::tool_guidance(|name| config.tool_enabled(name));
+        if !guidance.is_empty() {
+            history.push(Message::Developer(guidance));
+        }
+    }
+    if direct {
+        history.push(Message::Developer(
+            crate::prompting::DIRECT_ANSWER_GUIDANCE.trim().to_string(),
+        ));
     }
     if let Some(s) = stdin_content {
         let s = s.trim();
@@ -55,3 +96,23 @@ pub fn make_history(
     }
     history
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_restate_the_reasoning_effort() {
+        let mut history = vec![
+            Message::System("Hi\nReasoning: medium\n\nValid channels\n".to_string()),
+            Message::User("Reasoning: medium".to_string()),
+        ];
+        set_reasoning(&mut history, "high");
+        set_reasoning(&mut history, "extreme");
+        let [Message::System(system), Message::User(user)] = history.as_slice() else {
+            panic!("expected the system and user messages");
+        };
+        assert_eq!(system, "Hi\nReasoning: high\n\nValid channels\n");
+        assert_eq!(user, "Reasoning: medium");
+    }
+}
@@ -1,27 +1,155 @@
 //! The hub is a background process that hosts the inference engine and accepts requests from the CLI.
 use eyre::{Result, eyre};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use tokio::io::AsyncWriteExt;
 use tokio::net::{UnixListener, UnixStream};
 
 use crate::harmony::{HarmonyAdapter, HarmonyDelta};
 use crate::inference;
-use crate::protocol::Message;
-use crate::protocol::{Frame, read_frame_from_stream, write_frame_to_stream};
+use crate::protocol::{
+    Encoding, Frame, read_frame_as, sniff_encoding, write_frame_as, write_frame_to_stream,
+};
+use crate::protocol::{Message, RequestOptions};
+
+mod stops;
+use stops::StopMatcher;
 
 /// Loaded backend and model; shared across connections.
 struct Hub {
     backend: gg::llama_backend::LlamaBackend,
+    /// Swapped on reload, which first waits for the turns in flight to finish.
+    /// Empty only while a reload has released the old model and not yet loaded one.
+    loaded: std::sync::RwLock<Option<Arc<LoadedModel>>>,
+    /// Workers between turns, each still holding its context for whichever turn comes next.
+    idle_workers: std::sync::Mutex<Vec<TurnWorker>>,
+    /// One permit per worker allowed to exist, so the contexts in memory stay bounded
+    /// however many clients are connected.
+    worker_permits: Arc<tokio::sync::Semaphore>,
+    sessions: u32,
+}
+
+struct LoadedModel {
+    path: std::path::PathBuf,
     model: gg::model::LlamaModel,
 }
 
+impl Hub {
+    fn new(
+        backend: gg::llama_backend::LlamaBackend,
+        model: gg::model::LlamaModel,
+        path: std::path::PathBuf,
+    ) -> Self {
+        let sessions = u32::try_from(crate::config::get().sessions).unwrap_or(u32::MAX);
+        Self {
+            backend,
+            loaded: std::sync::RwLock::new(Some(Arc::new(LoadedModel { path, model }))),
+            idle_workers: std::sync::Mutex::new(Vec::new()),
+            worker_permits: Arc::new(tokio::sync::Semaphore::new(sessions as usize)),
+            sessions,
+        }
+    }
+
+    fn current(&self) -> Option<Arc<LoadedModel>> {
+        self.loaded.read().unwrap().clone()
+    }
+
+    /// Wait for a free worker, preferring the one that served this connection last, since its
+    /// context most likely still holds the start of the prompt.
+    async fn borrow_worker(self: &Arc<Self>, preferred: Option<u64>) -> Result<BorrowedWorker> {
+        let permit = self
+            .worker_permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| eyre!(e))?;
+        let idle = {
+            let mut idle = self.idle_workers.lock().unwrap();
+            let position = idle
+                .iter()
+                .position(|worker| Some(worker.id) == preferred)
+                .or_else(|| idle.len().checked_sub(1));
+            position.map(|position| idle.swap_remove(position))
+        };
+        let worker = match idle {
+            Some(worker) => worker,
+            None => {
+                let Some(model) = self.current() else {
+                    return Err(eyre!("hub: no model loaded; the last reload failed"));
+                };
+                TurnWorker::start(self.clone(), model)
+            }
+        };
+        Ok(BorrowedWorker {
+            hub: self.clone(),
+            worker: Some(worker),
+            _permit: permit,
+        })
+    }
+
+    /// Wait until no turn is running and end every worker, freeing their contexts.
+    /// The returned permits keep new turns out until they are dropped.
+    async fn drain_workers(&self) -> Result<tokio::sync::OwnedSemaphorePermit> {
+        let all = self
+            .worker_permits
+            .clone()
+            .acquire_many_owned(self.sessions)
+            .await
+            .map_err(|e| eyre!(e))?;
+        let idle = std::mem::take(&mut *self.idle_workers.lock().unwrap());
+        // Ending a worker waits for its thread, which may take a moment to free the context.
+        tokio::task::spawn_blocking(move || drop(idle))
+            .await
+            .map_err(|e| eyre!(e))?;
+        Ok(all)
+    }
+}
+
 /// Default UNIX socket location under `~/.please/socket`.
 pub fn socket_path() -> std::path::PathBuf {
     let home = std::env::var("HOME").unwrap_or_else(|_| String::from("."));
     std::path::Path::new(&home).join(".please").join("socket")
 }
 
+/// File under `~/.please` recording the model path the hub last loaded.
+fn current_model_record_path() -> std::path::PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| String::from("."));
+    std::path::Path::new(&home)
+        .join(".please")
+        .join("current_model")
+}
+
+/// Prefer the model loaded last time, if it is still present; otherwise run discovery.
+pub fn select_model_path() -> Option<std::path::PathBuf> {
+    let recorded = std::fs::read_to_string(current_model_record_path())
+        .ok()
+        .map(|s| std::path::PathBuf::from(s.trim()))
+        .filter(|path| path.is_file());
+    if let Some(path) = recorded {
+        tracing::info!(path=%path.display(), "hub: reusing previously loaded model");
+        return Some(path);
+    }
+    crate::cli::discovery::choose_best_model_path()
+}
+
+/// Remember the loaded model so a restarted hub picks the same one.
+fn record_model_path(model_path: &std::path::Path) {
+    let record = current_model_record_path();
+    let written = record
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&record, model_path.to_string_lossy().as_bytes()));
+    if let Err(error) = written {
+        tracing::warn!(%error, "hub: could not record the loaded model");
+    }
+}
+
+/// Forget the recorded model so the next hub start runs discovery again.
+pub fn forget_recorded_model() {
+    let _ = std::fs::remove_file(current_model_record_path());
+}
+
 /// Ensure the socket directory exists and is private (0700 on Unix).
 pub fn ensure_socket_dir(path: &std::path::Path) -> Result<()> {
     use std::fs;
@@ -65,53 +193,250 @@ pub fn cleanup_stale_socket(path: &std::path::Path) -> Result<()> {
     Ok(())
 }
 
-/// Run streaming inference and forward deltas to the sink.
-async fn serve_one_turn(
+async fn forward_delta(
     sink: &mut (impl AsyncWriteExt + Unpin),
+    encoding: Encoding,
+    delta: HarmonyDelta,
+) -> Result<()> {
+    let frame = match delta {
+        HarmonyDelta::Answer(text) => Frame::Answer(text),
+        HarmonyDelta::Thinking(text) => Frame::Thinking(text),
+        HarmonyDelta::Commentary(text) => Frame::Commentary(text),
+        HarmonyDelta::Preamble(text) => {
+            tracing::warn!(
+                "hub: model wrote text before its first header; passing it on as the answer"
+            );
+            Frame::Answer(text)
+        }
+    };
+    write_frame_as(sink, encoding, &frame).await?;
+    Ok(())
+}
+
+/// Raises the flag when dropped, so generation stops however the turn ends.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Runs turns on a thread of its own, which keeps the context and its KV cache alive between
+/// them. Dropping the worker ends the thread and frees the context.
+struct TurnWorker {
+    id: u64,
+    jobs: std::sync::mpsc::Sender<TurnJob>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+struct TurnJob {
+    history: Vec<Message>,
+    options: RequestOptions,
+    generated: inference::GenerationSender,
+    cancelled: Arc<AtomicBool>,
+    done: tokio::sync::oneshot::Sender<Result<()>>,
+}
+
+impl TurnWorker {
+    fn start(hub: Arc<Hub>, model: Arc<LoadedModel>) -> Self {
+        static NEXT_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+        let (jobs, queue) = std::sync::mpsc::channel::<TurnJob>();
+        let thread = std::thread::spawn(move || {
+            let prefix_cache = inference::PrefixCache::open(&model.path);
+            let mut slot = inference::SessionSlot::new(prefix_cache);
+            for job in queue {
+                let result = inference::generate_tokens_into_stream(
+                    &hub.backend,
+                    &model.model,
+                    &job.history,
+                    &job.options,
+                    job.generated,
+                    &job.cancelled,
+                    &mut slot,
+                );
+                let _ = job.done.send(result);
+            }
+        });
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            jobs,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for TurnWorker {
+    /// Close the queue and wait for the thread, so the context and the model it holds are
+    /// released by the time the worker is gone.
+    fn drop(&mut self) {
+        let (closed, _) = std::sync::mpsc::channel();
+        drop(std::mem::replace(&mut self.jobs, closed));
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A worker lent to one turn; it goes back to the pool however the turn ends,
+/// unless the turn found it stopped.
+struct BorrowedWorker {
     hub: Arc<Hub>,
+    worker: Option<TurnWorker>,
+    _permit: tokio::sync::OwnedSemaphorePermit,
+}
+
+impl Drop for BorrowedWorker {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            self.hub.idle_workers.lock().unwrap().push(worker);
+        }
+    }
+}
+
+/// Run streaming inference and forward deltas to the client.
+/// Generation stops as soon as the client hangs up; bytes it sends meanwhile are kept in `store`.
+async fn serve_one_turn(
+    stream: &mut UnixStream,
+    store: &mut Vec<u8>,
+    encoding: Encoding,
+    hub: Arc<Hub>,
+    last_worker: &mut Option<u64>,
     history: &[Message],
+    options: RequestOptions,
 ) -> Result<()> {
+    let mut history = history.to_owned();
+    if let Some(level) = &options.reasoning {
+        crate::history::set_reasoning(&mut history, level);
+    }
+    let history = history.as_slice();
+    let no_tools = options.no_tools.unwrap_or(false);
+    let timings = options.timings.unwrap_or(false);
     let harmony = HarmonyAdapter::gpt_oss()?;
     let mut parser = harmony.output_parser()?;
+    // Prose before the first header would otherwise vanish and leave the reply empty.
+    parser.keep_preamble();
+    // A continued answer is already in the prompt; only stream what comes after it.
+    parser.prime(&harmony.continuation_prefill(history))?;
     let (generated_tx, mut generated_rx) =
         tokio::sync::mpsc::unbounded_channel::<inference::Generated>();
 
-    let history = history.to_owned();
-    let also_hub = hub.clone();
-    let inference = tokio::task::spawn_blocking(move || {
-        inference::generate_tokens_into_stream(
-            &also_hub.backend,
-            &also_hub.model,
-            &history,
-            generated_tx,
-        )
-    });
+    let mut borrowed = hub.borrow_worker(*last_worker).await?;
+    let Some(worker) = borrowed.worker.as_ref() else {
+        return Err(eyre!("hub: inference worker stopped"));
+    };
+    *last_worker = Some(worker.id);
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let _cancel_on_exit = CancelOnDrop(cancelled.clone());
+    let mut stops = StopMatcher::new(&options.stop);
+    let mut stopped = false;
+    let (done, finished) = tokio::sync::oneshot::channel();
+    let job = TurnJob {
+        history: history.to_owned(),
+        options,
+        generated: generated_tx,
+        cancelled: cancelled.clone(),
+        done,
+    };
+    if worker.jobs.send(job).is_err() {
+        borrowed.worker = None;
+        return Err(eyre!("hub: inference worker stopped"));
+    }
 
-    while let Some(event) = generated_rx.recv().await {
+    // Clients send nothing until the answer is complete, so the socket turning readable
+    // mid-turn almost always means it hung up.
+    let mut watching = true;
+    loop {
+        let event = tokio::select! {
+            event = generated_rx.recv() => event,
+            _ = stream.readable(), if watching => {
+                let mut chunk = [0u8; 4096];
+                match stream.try_read(&mut chunk) {
+                    Ok(0) => cancelled.store(true, Ordering::Relaxed),
+                    Ok(n) => {
+                        store.extend_from_slice(&chunk[..n]);
+                        watching = false;
+                    }
+                    Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => {}
+                    Err(_) => cancelled.store(true, Ordering::Relaxed),
+                }
+                if cancelled.load(Ordering::Relaxed) {
+                    tracing::info!("hub: client hung up; stopping generation");
+                    break;
+                }
+                continue;
+            }
+        };
         match event {
-            inference::Generated::Token(token) => {
-                let Some(delta) = parser.push_token(token)? else {
-                    continue;
-                };
-                match delta {
-                    HarmonyDelta::Answer(text) => {
-                        write_frame_to_stream(sink, &Frame::Answer(text)).await?;
+            Some(inference::Generated::Token(token)) => match parser.push_token(token)? {
+                Some(HarmonyDelta::Answer(text)) => {
+                    let (ready, hit) = stops.push(&text);
+                    if !ready.is_empty() {
+                        forward_delta(stream, encoding, HarmonyDelta::Answer(ready)).await?;
                     }
-                    HarmonyDelta::Thinking(text) => {
-                        write_frame_to_stream(sink, &Frame::Thinking(text)).await?;
+                    if hit {
+                        tracing::debug!("hub: stop sequence reached");
+                        stopped = true;
+                        cancelled.store(true, Ordering::Relaxed);
+                        break;
                     }
                 }
+                Some(delta) => {
+                    let held = stops.flush();
+                    if !held.is_empty() {
+                        forward_delta(stream, encoding, HarmonyDelta::Answer(held)).await?;
+                    }
+                    forward_delta(stream, encoding, delta).await?;
+                }
+                None => {}
+            },
+            Some(inference::Generated::Timing(timing)) => {
+                tracing::info!("hub: {timing}");
+                if timings {
+                    write_frame_as(stream, encoding, &Frame::Log(timing.to_string())).await?;
+                }
             }
-            inference::Generated::Stop => break,
+            Some(inference::Generated::Stop) | None => break,
         }
     }
 
-    inference.await.map_err(|e| eyre!(e))??;
+    let Ok(generated) = finished.await else {
+        borrowed.worker = None;
+        return Err(eyre!("hub: inference worker stopped"));
+    };
+    generated?;
+    if stopped {
+        // The answer was cut short on purpose; there is nothing left to parse.
+        write_frame_as(stream, encoding, &Frame::Stop).await?;
+        return Ok(());
+    }
+    if cancelled.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let held = stops.flush();
+    if !held.is_empty() {
+        forward_delta(stream, encoding, HarmonyDelta::Answer(held)).await?;
+    }
+    if let Some(delta) = parser.end_preamble()? {
+        forward_delta(stream, encoding, delta).await?;
+    }
+    tracing::debug!(terminator = ?parser.terminator(), "hub: generation ended");
     match parser.finish() {
+        Ok(calls) if no_tools && !calls.is_empty() => {
+            write_frame_as(
+                stream,
+                encoding,
+                &Frame::ToolCallParseError(
+                    "tools are disabled for this request; answer without calling any".to_string(),
+                ),
+            )
+            .await?;
+        }
         Ok(calls) => {
             for call in calls {
-                write_frame_to_stream(
-                    sink,
+                write_frame_as(
+                    stream,
+                    encoding,
                     &Frame::ToolCall {
                         name: call.name,
                         arguments_json: serde_json::to_string(&call.arguments)?,
@@ -121,11 +446,102 @@ async fn serve_one_turn(
             }
         }
         Err(error) => {
-            write_frame_to_stream(sink, &Frame::ToolCallParseError(error.to_string())).await?;
+            write_frame_as(
+                stream,
+                encoding,
+                &Frame::ToolCallParseError(error.to_string()),
+            )
+            .await?;
         }
     }
-    write_frame_to_stream(sink, &Frame::Stop).await?;
+    write_frame_as(stream, encoding, &Frame::Stop).await?;
+
+    Ok(())
+}
+
+/// Re-run discovery and swap in the chosen model without restarting the hub.
+async fn reload_model(
+    sink: &mut (impl AsyncWriteExt + Unpin),
+    encoding: Encoding,
+    hub: Arc<Hub>,
+) -> Result<()> {
+    let Some(model_path) = crate::cli::discovery::choose_best_model_path() else {
+        write_frame_as(
+            sink,
+            encoding,
+            &Frame::Log("hub: no model found".to_string()),
+        )
+        .await?;
+        write_frame_as(sink, encoding, &Frame::Stop).await?;
+        return Ok(());
+    };
+    tracing::info!(model_path=%model_path.display(), "hub: reloading model");
+    // Workers hold contexts of the old model; end them so no turn keeps it alive.
+    let _no_turns = hub.drain_workers().await?;
+    // Release the old weights before loading the new ones, so a GPU that fits one model
+    // at a time has room for the next.
+    let previous = hub.loaded.write().unwrap().take();
+    let previous_path = previous.as_ref().map(|loaded| loaded.path.clone());
+    drop(previous);
 
+    let line = match load_on_hub(&hub, &model_path).await {
+        Ok(model) => {
+            install_model(&hub, &model_path, model);
+            record_model_path(&model_path);
+            format!("hub: reloaded {}", model_path.display())
+        }
+        Err(error) => match previous_path {
+            Some(previous_path) => match load_on_hub(&hub, &previous_path).await {
+                Ok(model) => {
+                    install_model(&hub, &previous_path, model);
+                    format!(
+                        "hub: reload failed, went back to {}: {error}",
+                        previous_path.display()
+                    )
+                }
+                Err(again) => format!(
+                    "hub: reload failed: {error}; loading {} again failed too: {again}",
+                    previous_path.display()
+                ),
+            },
+            None => format!("hub: reload failed: {error}"),
+        },
+    };
+    tracing::info!("{line}");
+    write_frame_as(sink, encoding, &Frame::Log(line)).await?;
+    write_frame_as(sink, encoding, &Frame::Stop).await?;
+    Ok(())
+}
+
+async fn load_on_hub(hub: &Arc<Hub>, path: &std::path::Path) -> Result<gg::model::LlamaModel> {
+    let also_hub = hub.clone();
+    let also_path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        crate::inference::load_model_with_backend(&also_hub.backend, &also_path)
+    })
+    .await
+    .map_err(|e| eyre!(e))?
+}
+
+fn install_model(hub: &Hub, path: &std::path::Path, model: gg::model::LlamaModel) {
+    *hub.loaded.write().unwrap() = Some(Arc::new(LoadedModel {
+        path: path.to_path_buf(),
+        model,
+    }));
+}
+
+/// Describe what the hub currently has loaded.
+async fn report_status(
+    sink: &mut (impl AsyncWriteExt + Unpin),
+    encoding: Encoding,
+    hub: &Hub,
+) -> Result<()> {
+    let line = match hub.current() {
+        Some(loaded) => format!("model: {}", loaded.path.display()),
+        None => "model: none; a reload is under way or failed".to_string(),
+    };
+    write_frame_as(sink, encoding, &Frame::Log(line)).await?;
+    write_frame_as(sink, encoding, &Frame::Stop).await?;
     Ok(())
 }
 
@@ -138,29 +554,66 @@ async fn accept_and_serve_request(stream: &mut UnixStream, hub: Arc<Hub>) -> Res
     tracing::info!("hub: connection accepted");
 
     let mut store = Vec::with_capacity(4096);
+    // Frontends other than the CLI may speak JSON lines; the first byte tells which.
+    let encoding = match sniff_encoding(stream, &mut store, total_timeout).await {
+        Err(crate::protocol::ProtocolError::Disconnect) => return Ok(()),
+        Err(e) => return Err(eyre!(e)),
+        Ok(encoding) => encoding,
+    };
+    tracing::debug!(?encoding, "hub: client encoding");
+    let mut last_worker = None;
 
     loop {
         // Wait for the next request; keep the connection alive between turns.
-        let req: std::result::Result<Frame, crate::protocol::ProtocolError> =
-            read_frame_from_stream(stream, &mut store, per_read_timeout, total_timeout).await;
+        let req: std::result::Result<Frame, crate::protocol::ProtocolError> = read_frame_as(
+            stream,
+            &mut store,
+            encoding,
+            per_read_timeout,
+            total_timeout,
+        )
+        .await;
 
         let req = match req {
             Err(crate::protocol::ProtocolError::Disconnect) => {
                 // Normal end of session
                 break;
             }
+            Err(crate::protocol::ProtocolError::Skipped { bytes }) => {
+                // Whatever the client asked for is lost; answer so it does not wait forever.
+                let line =
+                    format!("hub: dropped a corrupt request of {bytes} bytes; send it again");
+                write_frame_as(stream, encoding, &Frame::Error(line)).await?;
+                continue;
+            }
+            Err(e @ crate::protocol::ProtocolError::TooLarge(_)) => {
+                // There is no telling where the next frame starts, so the connection ends here.
+                let line = format!("hub: {e}");
+                write_frame_as(stream, encoding, &Frame::Error(line)).await?;
+                return Err(eyre!(e));
+            }
             Err(e) => return Err(eyre!(e)),
             Ok(frame) => frame,
         };
 
-        tracing::info!("hub: received inference request");
-
-        let history = match req {
-            Frame::Request { messages } => messages,
+        match req {
+            Frame::Request { messages, options } => {
+                tracing::info!(?options, "hub: received inference request");
+                serve_one_turn(
+                    stream,
+                    &mut store,
+                    encoding,
+                    hub.clone(),
+                    &mut last_worker,
+                    &messages,
+                    options,
+                )
+                .await?;
+            }
+            Frame::Reload => reload_model(stream, encoding, hub.clone()).await?,
+            Frame::Status => report_status(stream, encoding, &hub).await?,
             _ => return Err(eyre!("bad request: {req:?}")),
-        };
-
-        serve_one_turn(stream, hub.clone(), &history).await?;
+        }
 
         // Roll over to the next turn
     }
@@ -177,20 +630,34 @@ pub async fn run() -> Result<()> {
     tracing::info!("hub: listening at {}", socket_path.display());
 
     // Load model once and accept connections in a loop.
-    let Some(model_path) = crate::cli::discovery::choose_best_model_path() else {
+    let Some(model_path) = select_model_path() else {
         return Err(eyre!("hub: no model found"));
     };
-    let model_path = model_path.to_string_lossy().to_string();
-    tracing::info!(%model_path, "hub: selected model");
+    tracing::info!(model_path=%model_path.display(), "hub: selected model");
     let (backend, model) = crate::inference::load_model(&model_path)?;
-    let hub = Arc::new(Hub { backend, model });
+    record_model_path(&model_path);
+    let hub = Arc::new(Hub::new(backend, model, model_path));
 
     tracing::info!("hub: model loaded");
 
+    let max_connections = crate::config::get().max_connections;
+    let slots = Arc::new(tokio::sync::Semaphore::new(max_connections));
+
     loop {
         let (mut stream, _addr) = listener.accept().await?;
+        let Ok(slot) = slots.clone().try_acquire_owned() else {
+            tracing::warn!("hub: turning a connection away, {max_connections} already open");
+            tokio::spawn(async move {
+                let line =
+                    format!("hub: too many connections ({max_connections}); try again later");
+                let _ = write_frame_to_stream(&mut stream, &Frame::Error(line)).await;
+                let _ = stream.shutdown().await;
+            });
+            continue;
+        };
         let hub = hub.clone();
         tokio::spawn(async move {
+            let _slot = slot;
             let served = accept_and_serve_request(&mut stream, hub).await;
             if let Err(e) = served {
                 let _ = stream.shutdown().await;
@@ -203,13 +670,13 @@ pub async fn run() -> Result<()> {
 /// Convenience for in-process use: serve a single client over a UnixStream pair.
 pub async fn spawn() -> Result<UnixStream> {
     // Load model once and serve a single request over an in-process stream pair.
-    let Some(model_path) = crate::cli::discovery::choose_best_model_path() else {
+    let Some(model_path) = select_model_path() else {
         return Err(eyre!("hub: no model found"));
     };
     tracing::info!(model_path=%model_path.display(), "hub: selected model");
-    let model_path = model_path.to_string_lossy().to_string();
     let (backend, model) = crate::inference::load_model(&model_path)?;
-    let hub = Hub { backend, model };
+    record_model_path(&model_path);
+    let hub = Hub::new(backend, model, model_path);
 
     let (probe_end, mut hub_end) = UnixStream::pair()?;
     tokio::spawn(async move {
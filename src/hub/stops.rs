@@ -0,0 +1,76 @@
+//! Stop sequences: end the answer as soon as one of the client's sentinel strings appears in it.
+
+/// Watches answer text for stop strings. A stop can straddle deltas, so text that could be the
+/// start of one is held back until the next delta settles it.
+pub(super) struct StopMatcher {
+    stops: Vec<String>,
+    held: String,
+}
+
+impl StopMatcher {
+    pub(super) fn new(stops: &[String]) -> Self {
+        Self {
+            stops: stops.iter().filter(|s| !s.is_empty()).cloned().collect(),
+            held: String::new(),
+        }
+    }
+
+    /// Text that is safe to pass on, and whether a stop string was hit.
+    /// After a hit, the stop string and everything after it are dropped.
+    pub(super) fn push(&mut self, text: &str) -> (String, bool) {
+        self.held.push_str(text);
+        let hit = self
+            .stops
+            .iter()
+            .filter_map(|stop| self.held.find(stop.as_str()))
+            .min();
+        if let Some(at) = hit {
+            let before = self.held[..at].to_string();
+            self.held.clear();
+            return (before, true);
+        }
+        let keep = self.held.len() - self.longest_partial_stop();
+        let ready = self.held[..keep].to_string();
+        self.held.drain(..keep);
+        (ready, false)
+    }
+
+    /// Whatever is still held back; it turned out not to be a stop string.
+    pub(super) fn flush(&mut self) -> String {
+        std::mem::take(&mut self.held)
+    }
+
+    /// Length of the longest tail of the held text that begins some stop string.
+    fn longest_partial_stop(&self) -> usize {
+        self.held
+            .char_indices()
+            .map(|(at, _)| &self.held[at..])
+            .find(|tail| self.stops.iter().any(|stop| stop.starts_with(tail)))
+            .map_or(0, str::len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_that_straddle_deltas_are_caught_and_cut() {
+        let mut matcher = StopMatcher::new(&["```".to_string()]);
+        assert_eq!(
+            matcher.push("let x = 1;\n`"),
+            ("let x = 1;\n".to_string(), false)
+        );
+        assert_eq!(matcher.push("`"), (String::new(), false));
+        assert_eq!(matcher.push("`\nmore"), (String::new(), true));
+    }
+
+    #[test]
+    fn near_misses_are_released() {
+        let mut matcher = StopMatcher::new(&["END".to_string()]);
+        assert_eq!(matcher.push("the EN"), ("the ".to_string(), false));
+        assert_eq!(matcher.push("ough"), ("ENough".to_string(), false));
+        assert_eq!(matcher.push(" E"), (" ".to_string(), false));
+        assert_eq!(matcher.flush(), "E");
+    }
+}
@@ -12,96 +12,254 @@ use gg::model::LlamaModel;
 use gg::model::params::LlamaModelParams;
 use gg::sampling::LlamaSampler;
 use gg::token::LlamaToken;
-use std::time::{SystemTime, UNIX_EPOCH};
+use gg::{DecodeError, LlamaContextLoadError};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use crate::config::{SamplerConfig, WindowSlack};
 use crate::harmony::HarmonyAdapter;
-use crate::protocol::Message;
+use crate::protocol::{Message, RequestOptions};
 
 mod intuition;
-use intuition::{pick_n_ctx_by_vram, vram_free_bytes};
-
-const USE_MIROSTAT: bool = true;
+mod prefix_cache;
+pub use intuition::vram_free_bytes;
+use intuition::{pick_n_ctx_by_vram, pick_n_gpu_layers};
+pub use prefix_cache::{PrefixCache, cache_dir, content_key, model_identity};
+
+/// Constrain function-call arguments to valid JSON while the model writes them.
+const USE_JSON_GRAMMAR: bool = true;
+
+/// JSON object grammar after llama.cpp's `grammars/json.gbnf`.
+const JSON_OBJECT_GRAMMAR: &str = r#"
+root   ::= object
+value  ::= object | array | string | number | ("true" | "false" | "null") ws
+object ::= "{" ws ( string ":" ws value ("," ws string ":" ws value)* )? "}" ws
+array  ::= "[" ws ( value ("," ws value)* )? "]" ws
+string ::= "\"" ( [^"\\\x7F\x00-\x1F] | "\\" (["\\/bfnrt] | "u" [0-9a-fA-F]{4}) )* "\"" ws
+number ::= ("-"? ([0-9] | [1-9] [0-9]{0,15})) ("." [0-9]+)? ([eE] [-+]? [0-9] [1-9]{0,15})? ws
+ws     ::= | " " | "\n" [ \t]{0,20}
+"#;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Generated {
     Token(u32),
+    /// How long the turn took; sent once, right before `Stop`.
+    Timing(TurnTiming),
     Stop,
 }
 
+/// Where the time of a turn went, for benchmarking models.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnTiming {
+    /// Prompt tokens decoded this turn, not counting those kept from the previous one.
+    pub prompt_tokens: usize,
+    /// Prompt tokens reused from the previous turn's KV cache or loaded from the prefix cache.
+    pub cached_tokens: usize,
+    pub prefill: Duration,
+    pub generated_tokens: u32,
+    pub decode: Duration,
+}
+
+/// One `key=value` line, so benchmark scripts can pick fields out of the log.
+impl std::fmt::Display for TurnTiming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let per_second = |tokens: f64, time: Duration| {
+            let secs = time.as_secs_f64();
+            if secs > 0.0 { tokens / secs } else { 0.0 }
+        };
+        write!(
+            f,
+            "timing prompt_tok={} cached_tok={} prefill_s={:.3} prefill_tok_s={:.1} \
+             eval_tok={} eval_s={:.3} eval_tok_s={:.1}",
+            self.prompt_tokens,
+            self.cached_tokens,
+            self.prefill.as_secs_f64(),
+            per_second(self.prompt_tokens as f64, self.prefill),
+            self.generated_tokens,
+            self.decode.as_secs_f64(),
+            per_second(f64::from(self.generated_tokens), self.decode),
+        )
+    }
+}
+
 pub type GenerationSender = tokio::sync::mpsc::UnboundedSender<Generated>;
 
 /// Load the model into memory through llama.cpp. Metal layers are enabled on macOS by the
 /// dependency feature rather than through please-owned kernels.
-pub fn load_model(model_path: &str) -> Result<(LlamaBackend, LlamaModel)> {
+pub fn load_model(model_path: &Path) -> Result<(LlamaBackend, LlamaModel)> {
     let backend = LlamaBackend::init()?;
-    let model_params = LlamaModelParams::default().with_n_gpu_layers(u32::MAX);
-    let model = LlamaModel::load_from_file(&backend, model_path, &model_params)?;
+    let model = load_model_with_backend(&backend, model_path)?;
     Ok((backend, model))
 }
 
-pub fn generate_tokens_into_stream(
+/// Load another model on an already initialized backend, e.g. when the hub reloads.
+pub fn load_model_with_backend(backend: &LlamaBackend, model_path: &Path) -> Result<LlamaModel> {
+    let model_path = utf8_model_path(model_path)?;
+    let n_gpu_layers = pick_n_gpu_layers(crate::config::get().gpu_layers, vram_free_bytes());
+    let model_params = LlamaModelParams::default().with_n_gpu_layers(n_gpu_layers);
+    LlamaModel::load_from_file(backend, model_path, &model_params)
+        .map_err(|error| explain_load_failure(Path::new(model_path), error.into()))
+}
+
+/// Smallest plausible size of complete weights by size label; the mxfp4 files are about
+/// 12 GB for 20b and 63 GB for 120b.
+const MIN_PLAUSIBLE_BYTES: &[(&str, u64)] = &[("120b", 60_000_000_000), ("20b", 11_000_000_000)];
+
+/// llama.cpp fails the same way on a missing, cut-off or foreign file; tell those apart,
+/// since all but the last are fixed by loading the model again.
+fn explain_load_failure(model_path: &Path, error: eyre::Report) -> eyre::Report {
+    use std::io::Read;
+
+    let shown = model_path.display();
+    let Ok(meta) = std::fs::metadata(model_path) else {
+        return eyre!("model file not found: {shown}; run `please load`");
+    };
+    let mut magic = [0u8; 4];
+    let is_gguf = std::fs::File::open(model_path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok_and(|()| &magic == b"GGUF");
+    if !is_gguf {
+        return eyre!(
+            "{shown} is not a GGUF file ({} bytes); re-run `please load`",
+            meta.len()
+        );
+    }
+    // `current.gguf` is a link; the size label is in the name of the file it points at.
+    let real_path = std::fs::canonicalize(model_path).unwrap_or_else(|_| model_path.into());
+    let file_name = real_path.file_name().unwrap_or_default().to_string_lossy();
+    if let Some(min) = expected_min_bytes(&file_name)
+        && meta.len() < min
+    {
+        return eyre!(
+            "model file appears truncated ({} bytes, expected at least {min}): {shown}; re-run `please load`",
+            meta.len()
+        );
+    }
+    eyre!("could not load {shown}: {error}; the file is corrupt or not supported by this build")
+}
+
+/// Lower bound on the size of a complete model file, when its name tells which model it is.
+fn expected_min_bytes(file_name: &str) -> Option<u64> {
+    let name = file_name.to_ascii_lowercase();
+    // A shard holds only part of the weights.
+    if name.contains("-of-") {
+        return None;
+    }
+    MIN_PLAUSIBLE_BYTES
+        .iter()
+        .find(|(label, _)| name.contains(&format!("-{label}")))
+        .map(|&(_, min)| min)
+}
+
+/// llama.cpp opens the weights by a UTF-8 C string, so a path with other bytes cannot reach it intact.
+/// Refuse it by name instead of passing a lossy copy that then fails as "file not found".
+fn utf8_model_path(model_path: &Path) -> Result<&str> {
+    model_path.to_str().ok_or_else(|| {
+        eyre!(
+            "model path is not valid UTF-8: {}; rename the file or the directories above it",
+            model_path.display()
+        )
+    })
+}
+
+/// A context kept between turns of one session, so a prompt that extends the previous one
+/// only needs its new tokens decoded.
+pub struct KvSession<'model> {
+    ctx: LlamaContext<'model>,
+    batch: LlamaBatch,
+    /// Tokens whose keys and values are in the cache, by position.
+    tokens: Vec<LlamaToken>,
+}
+
+/// What one connection keeps between its turns: the live context, and where a fresh context
+/// can load the preamble from instead of prefilling it.
+pub struct SessionSlot<'model> {
+    session: Option<KvSession<'model>>,
+    prefix_cache: Option<PrefixCache>,
+}
+
+impl SessionSlot<'_> {
+    pub fn new(prefix_cache: Option<PrefixCache>) -> Self {
+        Self {
+            session: None,
+            prefix_cache,
+        }
+    }
+}
+
+/// Generate a reply to `history`. A session left in `slot` by the previous turn is reused when
+/// the new prompt starts with what it holds; the context is left in `slot` for the next turn.
+pub fn generate_tokens_into_stream<'model>(
     backend: &LlamaBackend,
-    model: &LlamaModel,
+    model: &'model LlamaModel,
     history: &[Message],
+    options: &RequestOptions,
     generated: GenerationSender,
+    cancelled: &AtomicBool,
+    slot: &mut SessionSlot<'model>,
 ) -> Result<()> {
     let harmony = HarmonyAdapter::gpt_oss()?;
     let prompt_token_ids = harmony.render_protocol_tokens(history)?;
 
-    let num_threads = std::thread::available_parallelism()
-        .ok()
-        .map(|n| n.get())
-        .unwrap_or(1);
-
-    let batch_size = 512;
-    let n_ctx = vram_free_bytes()
-        .map(|free| pick_n_ctx_by_vram(model, free))
-        .unwrap_or_else(|| std::num::NonZeroU32::new(8_192.min(model.n_ctx_train())).unwrap());
-    let ctx_params = LlamaContextParams::default()
-        .with_n_ctx(Some(n_ctx))
-        .with_n_threads(num_threads as i32)
-        .with_n_threads_batch(num_threads as i32)
-        .with_n_batch(batch_size as u32)
-        .with_n_ubatch(batch_size as u32);
-    let mut ctx = model.new_context(backend, ctx_params)?;
+    let prefill_started = Instant::now();
+    let reused = match slot.session.take() {
+        Some(kept) => reuse_session(kept, &harmony, history, &prompt_token_ids)?,
+        None => None,
+    };
+    let Prefilled {
+        mut ctx,
+        mut batch,
+        prompt_tokens,
+        preamble_len,
+        mut logits_idx,
+        cached_tokens,
+    } = match reused {
+        Some(prefilled) => prefilled,
+        None => fresh_prefill(
+            backend,
+            model,
+            &harmony,
+            history,
+            &prompt_token_ids,
+            slot.prefix_cache.as_ref(),
+        )?,
+    };
+    let prefill = prefill_started.elapsed();
     let ctx_cap = ctx.n_ctx() as usize;
 
-    let preamble_len = compute_preamble_len(&harmony, history, ctx_cap)?;
-    let prompt_tokens = clip_to_ctx(prompt_token_ids, preamble_len, ctx_cap)
-        .into_iter()
-        .map(token_to_llama)
-        .collect::<Result<Vec<_>>>()?;
-
-    let mut batch = LlamaBatch::new(batch_size as usize, 1);
-    ctx.clear_kv_cache();
-    let mut logits_idx =
-        prefill_returning_logits_idx(&mut ctx, &mut batch, &prompt_tokens, batch_size as usize)?;
-
-    let seed = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.subsec_nanos())
-        .unwrap_or(31337);
-    let mut sampler = if USE_MIROSTAT {
-        LlamaSampler::chain_simple([
-            LlamaSampler::penalties(64, 1.0, 0.0, 0.0),
-            LlamaSampler::temp(1.0),
-            LlamaSampler::mirostat_v2(seed, 5.0, 0.1),
-        ])
+    let sampler_config = crate::config::get().sampler.with_overrides(options);
+    let seed = sampling_seed(&sampler_config);
+    let greedy = options.greedy.unwrap_or(crate::config::get().greedy);
+    let max_tokens = options.max_tokens.or(crate::config::get().max_tokens);
+    let mut sampler = SamplerChain::choose(greedy, &sampler_config)
+        .build(seed)
+        .with_tokens(prompt_tokens.iter().copied());
+
+    // Follows the Harmony stream so arguments of function calls can be sampled under the grammar.
+    let mut call_tracker = if USE_JSON_GRAMMAR {
+        let mut tracker = harmony.output_parser()?;
+        tracker.prime(&harmony.continuation_prefill(history))?;
+        Some(tracker)
     } else {
-        LlamaSampler::chain_simple([
-            LlamaSampler::penalties(64, 1.1, 0.0, 0.0),
-            LlamaSampler::top_k(40),
-            LlamaSampler::top_p(0.9, 1),
-            LlamaSampler::temp(0.8),
-            LlamaSampler::dist(seed),
-        ])
-    }
-    .with_tokens(prompt_tokens.iter().copied());
+        None
+    };
+    let mut call_sampler: Option<LlamaSampler> = None;
 
     let mut rolling_tokens = prompt_tokens.clone();
     let mut pos = rolling_tokens.len();
+    let mut generated_count = 0u32;
+    let decode_started = Instant::now();
 
     loop {
+        if max_tokens.is_some_and(|max_tokens| generated_count >= max_tokens) {
+            break;
+        }
+        // Checked before every decode so an abandoned request frees the GPU within a token.
+        if cancelled.load(Ordering::Relaxed) {
+            tracing::debug!(generated_count, "generation cancelled");
+            break;
+        }
         if pos >= ctx_cap {
             let (compact, new_pos, new_logits_idx) = rebuild_kv_with_sliding_window(
                 &mut ctx,
@@ -109,14 +267,19 @@ pub fn generate_tokens_into_stream(
                 &rolling_tokens,
                 preamble_len,
                 ctx_cap,
-                batch_size as usize,
+                BATCH_SIZE,
             )?;
             rolling_tokens = compact;
             pos = new_pos;
             logits_idx = new_logits_idx;
         }
 
-        let token = sampler.sample(&ctx, logits_idx);
+        // The grammar sampler picks the token itself and keeps its own state; the main chain
+        // still accepts it below so its penalties see the whole output.
+        let token = match call_sampler.as_mut() {
+            Some(call_sampler) => call_sampler.sample(&ctx, logits_idx),
+            None => sampler.sample(&ctx, logits_idx),
+        };
         let token_id = token_to_u32(token)?;
         let is_harmony_stop = harmony.is_stop_token(token_id);
         let is_model_eog = ctx.model.is_eog_token(token);
@@ -132,6 +295,25 @@ pub fn generate_tokens_into_stream(
         }
 
         sampler.accept(token);
+        generated_count += 1;
+        if let Some(tracker) = call_tracker.as_mut() {
+            match tracker.push_token(token_id) {
+                Ok(_) if !tracker.in_tool_call() => call_sampler = None,
+                Ok(_) if call_sampler.is_none() => match json_sampler(model) {
+                    Ok(json) => call_sampler = Some(json),
+                    Err(error) => {
+                        tracing::warn!(%error, "sampling tool calls without a grammar");
+                        call_tracker = None;
+                    }
+                },
+                Ok(_) => {}
+                // A stream the parser rejects is reported by the hub; just stop constraining it.
+                Err(_) => {
+                    call_tracker = None;
+                    call_sampler = None;
+                }
+            }
+        }
 
         batch.clear();
         batch.add(token, pos as i32, &[0], true)?;
@@ -142,10 +324,281 @@ pub fn generate_tokens_into_stream(
         rolling_tokens.push(token);
     }
 
+    let _ = generated.send(Generated::Timing(TurnTiming {
+        prompt_tokens: prompt_tokens.len() - cached_tokens,
+        cached_tokens,
+        prefill,
+        generated_tokens: generated_count,
+        decode: decode_started.elapsed(),
+    }));
     let _ = generated.send(Generated::Stop);
+    slot.session = Some(KvSession {
+        ctx,
+        batch,
+        tokens: rolling_tokens,
+    });
     Ok(())
 }
 
+/// Create a context sized for the free VRAM and prefill the whole prompt.
+fn fresh_prefill<'model>(
+    backend: &LlamaBackend,
+    model: &'model LlamaModel,
+    harmony: &HarmonyAdapter,
+    history: &[Message],
+    prompt_token_ids: &[u32],
+    prefix_cache: Option<&PrefixCache>,
+) -> Result<Prefilled<'model>> {
+    let n_ctx = vram_free_bytes()
+        .map(|free| pick_n_ctx_by_vram(model, free))
+        .unwrap_or_else(|| std::num::NonZeroU32::new(8_192.min(model.n_ctx_train())).unwrap());
+    let prefill = |n_ctx| {
+        prefill_context(
+            backend,
+            model,
+            harmony,
+            history,
+            prompt_token_ids,
+            n_ctx,
+            prefix_cache,
+        )
+    };
+    // The VRAM estimate can be optimistic; a smaller context is better than no answer.
+    match prefill(n_ctx) {
+        Ok(prefilled) => Ok(prefilled),
+        Err(error) if looks_like_oom(&error) && n_ctx.get() / 2 >= MIN_RETRY_N_CTX => {
+            let smaller = std::num::NonZeroU32::new(n_ctx.get() / 2).unwrap();
+            tracing::warn!(
+                %error,
+                from = n_ctx.get(),
+                to = smaller.get(),
+                "retrying with a smaller context"
+            );
+            prefill(smaller)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Keep the cached prefix the prompt shares with the previous turn and prefill only the rest.
+/// Returns nothing when there is no usable prefix, e.g. because an earlier message was edited
+/// or the prompt no longer fits without clipping; the caller then starts afresh.
+fn reuse_session<'model>(
+    session: KvSession<'model>,
+    harmony: &HarmonyAdapter,
+    history: &[Message],
+    prompt_token_ids: &[u32],
+) -> Result<Option<Prefilled<'model>>> {
+    let KvSession {
+        mut ctx,
+        mut batch,
+        tokens: cached,
+    } = session;
+    let ctx_cap = ctx.n_ctx() as usize;
+    if prompt_token_ids.len() > ctx_cap.saturating_sub(1) {
+        return Ok(None);
+    }
+    let prompt_tokens = prompt_token_ids
+        .iter()
+        .copied()
+        .map(token_to_llama)
+        .collect::<Result<Vec<_>>>()?;
+    let shared = shared_prefix_len(&cached, &prompt_tokens);
+    // Decode at least the last prompt token again so there are logits to sample from.
+    let kept = shared.min(prompt_tokens.len().saturating_sub(1));
+    if kept == 0 {
+        return Ok(None);
+    }
+    if !ctx
+        .clear_kv_cache_seq(Some(0), Some(kept as u32), None)
+        .unwrap_or(false)
+    {
+        return Ok(None);
+    }
+    tracing::debug!(kept, prompt = prompt_tokens.len(), "reusing kv cache");
+    let logits_idx =
+        prefill_returning_logits_idx(&mut ctx, &mut batch, &prompt_tokens, kept, BATCH_SIZE)?;
+    Ok(Some(Prefilled {
+        ctx,
+        batch,
+        prompt_tokens,
+        preamble_len: compute_preamble_len(harmony, history, ctx_cap)?,
+        logits_idx,
+        cached_tokens: kept,
+    }))
+}
+
+fn shared_prefix_len<T: PartialEq>(cached: &[T], prompt: &[T]) -> usize {
+    cached
+        .iter()
+        .zip(prompt)
+        .take_while(|(cached, prompt)| cached == prompt)
+        .count()
+}
+
+/// Which sampler chain to build, with every parameter resolved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SamplerChain {
+    Greedy,
+    Mirostat {
+        temperature: f32,
+        tau: f32,
+        eta: f32,
+    },
+    TopKTopP {
+        top_k: i32,
+        top_p: f32,
+        temperature: f32,
+    },
+}
+
+impl SamplerChain {
+    fn choose(greedy: bool, config: &SamplerConfig) -> Self {
+        if greedy {
+            Self::Greedy
+        } else if config.mirostat {
+            Self::Mirostat {
+                temperature: config.temperature.unwrap_or(1.0),
+                tau: 5.0,
+                eta: 0.1,
+            }
+        } else {
+            Self::TopKTopP {
+                top_k: config.top_k,
+                top_p: config.top_p,
+                temperature: config.temperature.unwrap_or(0.8),
+            }
+        }
+    }
+
+    fn build(self, seed: u32) -> LlamaSampler {
+        match self {
+            Self::Greedy => LlamaSampler::chain_simple([
+                LlamaSampler::penalties(64, 1.1, 0.0, 0.0),
+                LlamaSampler::greedy(),
+            ]),
+            Self::Mirostat {
+                temperature,
+                tau,
+                eta,
+            } => LlamaSampler::chain_simple([
+                LlamaSampler::penalties(64, 1.0, 0.0, 0.0),
+                LlamaSampler::temp(temperature),
+                LlamaSampler::mirostat_v2(seed, tau, eta),
+            ]),
+            Self::TopKTopP {
+                top_k,
+                top_p,
+                temperature,
+            } => LlamaSampler::chain_simple([
+                LlamaSampler::penalties(64, 1.1, 0.0, 0.0),
+                LlamaSampler::top_k(top_k),
+                LlamaSampler::top_p(top_p, 1),
+                LlamaSampler::temp(temperature),
+                LlamaSampler::dist(seed),
+            ]),
+        }
+    }
+}
+
+/// `PLEASE_SEED` when set, else a seed from the clock.
+fn sampling_seed(config: &SamplerConfig) -> u32 {
+    config.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(31337)
+    })
+}
+
+/// Tokens per decode batch, for both prefill and the KV rebuilds.
+const BATCH_SIZE: usize = 512;
+
+/// Below this, halving the context would clip too much history to be worth a retry.
+const MIN_RETRY_N_CTX: u32 = 2_048;
+
+/// A context holding the prompt, ready to sample the first token.
+struct Prefilled<'model> {
+    ctx: LlamaContext<'model>,
+    batch: LlamaBatch,
+    prompt_tokens: Vec<LlamaToken>,
+    preamble_len: usize,
+    logits_idx: i32,
+    /// Leading prompt tokens that were already in the context and not decoded again.
+    cached_tokens: usize,
+}
+
+/// Create a context of `n_ctx` tokens and decode the prompt into it, clipped to fit.
+/// The preamble comes from `prefix_cache` when it was saved before.
+fn prefill_context<'model>(
+    backend: &LlamaBackend,
+    model: &'model LlamaModel,
+    harmony: &HarmonyAdapter,
+    history: &[Message],
+    prompt_token_ids: &[u32],
+    n_ctx: std::num::NonZeroU32,
+    prefix_cache: Option<&PrefixCache>,
+) -> Result<Prefilled<'model>> {
+    let num_threads = std::thread::available_parallelism()
+        .ok()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let ctx_params = LlamaContextParams::default()
+        .with_n_ctx(Some(n_ctx))
+        .with_n_threads(num_threads as i32)
+        .with_n_threads_batch(num_threads as i32)
+        .with_n_batch(BATCH_SIZE as u32)
+        .with_n_ubatch(BATCH_SIZE as u32);
+    let mut ctx = model.new_context(backend, ctx_params)?;
+    let ctx_cap = ctx.n_ctx() as usize;
+
+    let preamble_len = compute_preamble_len(harmony, history, ctx_cap)?;
+    let prompt_tokens = clip_to_ctx(prompt_token_ids.to_vec(), preamble_len, ctx_cap)
+        .into_iter()
+        .map(token_to_llama)
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut batch = LlamaBatch::new(BATCH_SIZE, 1);
+    ctx.clear_kv_cache();
+    // The last prompt token is always decoded here, since sampling needs its logits.
+    let start = match prefix_cache {
+        Some(cache) if preamble_len > 0 && preamble_len < prompt_tokens.len() => {
+            cache.prime(&mut ctx, &mut batch, &prompt_tokens[..preamble_len])?
+        }
+        _ => 0,
+    };
+    let logits_idx =
+        prefill_returning_logits_idx(&mut ctx, &mut batch, &prompt_tokens, start, BATCH_SIZE)?;
+    Ok(Prefilled {
+        ctx,
+        batch,
+        prompt_tokens,
+        preamble_len,
+        logits_idx,
+        cached_tokens: start,
+    })
+}
+
+/// llama.cpp reports running out of memory as a context it could not create or a batch
+/// it could not place, without saying why.
+fn looks_like_oom(error: &eyre::Report) -> bool {
+    error.downcast_ref::<LlamaContextLoadError>().is_some()
+        || matches!(
+            error.downcast_ref::<DecodeError>(),
+            Some(DecodeError::NoKvCacheSlot | DecodeError::Unknown(_))
+        )
+}
+
+/// A fresh sampler that only lets through a JSON object, then an end-of-message token.
+fn json_sampler(model: &LlamaModel) -> Result<LlamaSampler> {
+    let grammar = LlamaSampler::grammar(model, JSON_OBJECT_GRAMMAR, "root")
+        .map_err(|error| eyre!("invalid JSON grammar: {error:?}"))?;
+    Ok(LlamaSampler::chain_simple([
+        grammar,
+        LlamaSampler::greedy(),
+    ]))
+}
+
 fn token_to_llama(token: u32) -> Result<LlamaToken> {
     let token = i32::try_from(token)?;
     Ok(LlamaToken::new(token))
@@ -185,15 +638,17 @@ fn clip_to_ctx(mut tokens: Vec<u32>, preamble_len: usize, ctx_cap: usize) -> Vec
     tokens
 }
 
+/// Decode `toks` from position `start` on; earlier positions must already be in the cache.
 fn prefill_returning_logits_idx(
     ctx: &mut LlamaContext,
     batch: &mut LlamaBatch,
     toks: &[LlamaToken],
+    start: usize,
     batch_size: usize,
 ) -> Result<i32> {
-    let mut pos = 0usize;
+    let mut pos = start;
     let mut logits_idx = 0;
-    for chunk in toks.chunks(batch_size) {
+    for chunk in toks[start..].chunks(batch_size) {
         batch.clear();
         for (i, &token) in chunk.iter().enumerate() {
             let want_logits = (pos + i + 1) == toks.len();
@@ -216,41 +671,189 @@ fn rebuild_kv_with_sliding_window(
     ctx_cap: usize,
     batch_size: usize,
 ) -> Result<(Vec<LlamaToken>, usize, i32)> {
+    let slack = crate::config::get().window_slack;
+    let compact = slide_window(rolling_tokens, preamble_len, ctx_cap, slack);
+
+    ctx.clear_kv_cache();
+    let logits_idx = prefill_returning_logits_idx(ctx, batch, &compact, 0, batch_size)?;
+    let new_pos = compact.len();
+    Ok((compact, new_pos, logits_idx))
+}
+
+/// Keep the preamble and as much of the tail as fits, leaving `slack` of room for new tokens.
+fn slide_window<T: Copy>(
+    rolling_tokens: &[T],
+    preamble_len: usize,
+    ctx_cap: usize,
+    slack: WindowSlack,
+) -> Vec<T> {
     let keep = rolling_tokens.len().min(preamble_len);
     let available_tail_room = ctx_cap.saturating_sub(1 + keep);
-    let slack = ((ctx_cap + 31).saturating_div(32))
-        .max(128)
+    let slack_tokens = ((ctx_cap as f64 * slack.fraction).ceil() as usize)
+        .max(slack.min)
         .min(available_tail_room);
-    let tail_room = available_tail_room.saturating_sub(slack);
+    let tail_room = available_tail_room.saturating_sub(slack_tokens);
     let tail_start = rolling_tokens.len().saturating_sub(tail_room);
 
     tracing::trace!(
         ?ctx_cap,
         ?preamble_len,
-        ?slack,
+        ?slack_tokens,
         "rebuilding kv with sliding window"
     );
 
     let mut compact = Vec::with_capacity(keep + (rolling_tokens.len() - tail_start));
     compact.extend_from_slice(&rolling_tokens[..keep]);
     compact.extend_from_slice(&rolling_tokens[tail_start..]);
+    compact
+}
 
-    ctx.clear_kv_cache();
-
-    let mut new_pos = 0usize;
-    let mut logits_idx = 0;
-    for chunk in compact.chunks(batch_size) {
-        batch.clear();
-        for (i, &token) in chunk.iter().enumerate() {
-            let want_logits = (new_pos + i + 1) == compact.len();
-            if want_logits {
-                logits_idx = i as i32;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampler_chains_follow_the_config() {
+        let defaults = SamplerConfig::default();
+        assert_eq!(SamplerChain::choose(true, &defaults), SamplerChain::Greedy);
+        assert_eq!(
+            SamplerChain::choose(false, &defaults),
+            SamplerChain::Mirostat {
+                temperature: 1.0,
+                tau: 5.0,
+                eta: 0.1
             }
-            batch.add(token, (new_pos + i) as i32, &[0], want_logits)?;
-        }
-        ctx.decode(batch)?;
-        new_pos += chunk.len();
+        );
+        let tuned = SamplerConfig {
+            mirostat: false,
+            temperature: Some(0.6),
+            top_k: 20,
+            ..defaults
+        };
+        let chain = SamplerChain::choose(false, &tuned);
+        assert_eq!(
+            chain,
+            SamplerChain::TopKTopP {
+                top_k: 20,
+                top_p: 0.9,
+                temperature: 0.6
+            }
+        );
+        let _ = chain.build(7);
     }
 
-    Ok((compact, new_pos, logits_idx))
+    #[test]
+    fn edited_history_shares_only_the_prefix_before_the_edit() {
+        assert_eq!(shared_prefix_len(&[1, 2, 3], &[1, 2, 3, 4, 5]), 3);
+        assert_eq!(shared_prefix_len(&[1, 2, 3, 4], &[1, 9, 3, 4, 5]), 1);
+        assert_eq!(shared_prefix_len::<u32>(&[], &[1]), 0);
+    }
+
+    #[test]
+    fn turn_timing_is_one_line_of_key_value_pairs() {
+        let timing = TurnTiming {
+            prompt_tokens: 512,
+            cached_tokens: 100,
+            prefill: Duration::from_millis(1_280),
+            generated_tokens: 310,
+            decode: Duration::from_millis(6_200),
+        };
+        assert_eq!(
+            timing.to_string(),
+            "timing prompt_tok=512 cached_tok=100 prefill_s=1.280 prefill_tok_s=400.0 \
+             eval_tok=310 eval_s=6.200 eval_tok_s=50.0"
+        );
+    }
+
+    #[test]
+    fn compaction_keeps_the_preamble_and_leaves_the_configured_slack() {
+        let tokens = (0..1_000).collect::<Vec<u32>>();
+
+        let compact = slide_window(&tokens, 10, 1_000, WindowSlack::default());
+        assert_eq!(compact.len(), 10 + 861);
+        assert_eq!(&compact[..11], &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 139]);
+        assert_eq!(compact.last(), Some(&999));
+
+        let roomy = WindowSlack {
+            fraction: 0.5,
+            min: 0,
+        };
+        let compact = slide_window(&tokens, 10, 1_000, roomy);
+        assert_eq!(compact.len(), 10 + 489);
+        assert_eq!(compact[10], 511);
+
+        // The slack never eats into the preamble.
+        let compact = slide_window(&tokens, 990, 1_000, roomy);
+        assert_eq!(compact.len(), 990);
+    }
+
+    #[test]
+    fn a_pinned_seed_is_used_as_is() {
+        let pinned = SamplerConfig {
+            seed: Some(42),
+            ..SamplerConfig::default()
+        };
+        assert_eq!(sampling_seed(&pinned), 42);
+        assert_eq!(sampling_seed(&pinned), sampling_seed(&pinned));
+    }
+
+    #[test]
+    fn only_allocation_failures_count_as_oom() {
+        assert!(looks_like_oom(&eyre::Report::new(
+            DecodeError::NoKvCacheSlot
+        )));
+        assert!(looks_like_oom(&eyre::Report::new(
+            LlamaContextLoadError::NullReturn
+        )));
+        assert!(!looks_like_oom(&eyre::Report::new(
+            DecodeError::NTokensZero
+        )));
+        assert!(!looks_like_oom(&eyre!("tokenizer failed")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_loads_say_whether_the_file_is_missing_cut_off_or_foreign() {
+        let dir = std::env::temp_dir().join(format!("please-weights-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cut_off = dir.join("gpt-oss-20b-mxfp4.gguf");
+        std::fs::write(&cut_off, b"GGUF\x03\0\0\0").unwrap();
+        let foreign = dir.join("gpt-oss-20b-page.gguf");
+        std::fs::write(&foreign, b"<html>").unwrap();
+        let unlabeled = dir.join("model.gguf");
+        std::fs::write(&unlabeled, b"GGUF").unwrap();
+
+        let explain = |path: &Path| explain_load_failure(path, eyre!("boom")).to_string();
+        let missing = explain(&dir.join("absent.gguf"));
+        let cut_off = explain(&cut_off);
+        let foreign = explain(&foreign);
+        let unlabeled = explain(&unlabeled);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(missing.starts_with("model file not found"));
+        assert!(cut_off.starts_with("model file appears truncated (8 bytes"));
+        assert!(foreign.contains("is not a GGUF file (6 bytes)"));
+        assert!(unlabeled.contains("boom"));
+        assert_eq!(
+            expected_min_bytes("gpt-oss-120b-mxfp4-00001-of-00003.gguf"),
+            None
+        );
+        assert_eq!(
+            expected_min_bytes("gpt-oss-120b-mxfp4.gguf"),
+            Some(60_000_000_000)
+        );
+    }
+
+    #[test]
+    fn non_utf8_model_paths_are_refused_by_name() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"/weights/m\xffodel.gguf"));
+        let error = utf8_model_path(path).unwrap_err().to_string();
+        assert!(error.contains("not valid UTF-8"), "{error}");
+        assert_eq!(
+            utf8_model_path(Path::new("/weights/model.gguf")).unwrap(),
+            "/weights/model.gguf"
+        );
+    }
 }
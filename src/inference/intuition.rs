@@ -57,6 +57,26 @@ pub fn pick_n_ctx_by_vram(model: &LlamaModel, vram_free_bytes: u64) -> NonZeroU3
     NonZeroU32::new(8_192).unwrap()
 }
 
+/// Below this much free video memory, offloading costs more than it saves.
+const MIN_OFFLOAD_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Layers to offload to the GPU: an explicit count wins, otherwise all of them, which llama.cpp
+/// clamps to what its backend can take. Only a probe that finds too little free memory keeps
+/// the model on the CPU; an unknown amount (no nvidia-smi, a container, Vulkan) is not that.
+pub fn pick_n_gpu_layers(requested: Option<u32>, vram_free_bytes: Option<u64>) -> u32 {
+    match (requested, vram_free_bytes) {
+        (Some(layers), _) => layers,
+        (None, Some(free)) if free < MIN_OFFLOAD_BYTES => {
+            tracing::info!(
+                free,
+                "model: too little free GPU memory; running on the CPU"
+            );
+            0
+        }
+        (None, _) => u32::MAX,
+    }
+}
+
 /// Returns free VRAM bytes if known (best-effort).
 pub fn vram_free_bytes() -> Option<u64> {
     #[cfg(not(target_os = "macos"))]
@@ -173,3 +193,17 @@ fn metal_free_bytes() -> Option<u64> {
     let current = dev.current_allocated_size();
     Some(recommended.saturating_sub(current))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_stay_on_the_cpu_only_when_gpu_memory_is_short() {
+        assert_eq!(pick_n_gpu_layers(None, None), u32::MAX);
+        assert_eq!(pick_n_gpu_layers(None, Some(8 << 30)), u32::MAX);
+        assert_eq!(pick_n_gpu_layers(None, Some(100 << 20)), 0);
+        assert_eq!(pick_n_gpu_layers(Some(12), None), 12);
+        assert_eq!(pick_n_gpu_layers(Some(0), Some(8 << 30)), 0);
+    }
+}
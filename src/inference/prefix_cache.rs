@@ -0,0 +1,116 @@
+//! KV state of the prompt preamble saved to disk, so a restarted hub skips prefilling it.
+use eyre::Result;
+use gg::context::LlamaContext;
+use gg::llama_batch::LlamaBatch;
+use gg::token::LlamaToken;
+use std::path::{Path, PathBuf};
+
+use super::{BATCH_SIZE, prefill_returning_logits_idx};
+
+/// Saved preambles of one model under `~/.please/cache`, one `<hash>.kv` file per preamble.
+pub struct PrefixCache {
+    dir: PathBuf,
+    /// Identifies the weights; a different path or a rewritten file misses the cache.
+    model: String,
+}
+
+impl PrefixCache {
+    /// Open the cache for the model at `model_path` when enabled in the config.
+    pub fn open(model_path: &Path) -> Option<Self> {
+        if !crate::config::get().prefix_cache {
+            return None;
+        }
+        Some(Self {
+            dir: cache_dir()?,
+            model: model_identity(model_path)?,
+        })
+    }
+
+    /// Bring `preamble` into the empty context, loading it from disk when saved before and
+    /// saving it otherwise. Returns how many leading tokens are now in the context.
+    pub(super) fn prime(
+        &self,
+        ctx: &mut LlamaContext,
+        batch: &mut LlamaBatch,
+        preamble: &[LlamaToken],
+    ) -> Result<usize> {
+        let file = self.dir.join(format!("{}.kv", self.key(preamble)));
+        if file.is_file() {
+            match ctx.load_session_file(&file, preamble.len()) {
+                Ok(tokens) if tokens == preamble => {
+                    tracing::debug!(tokens = preamble.len(), "prefix cache: loaded preamble");
+                    return Ok(preamble.len());
+                }
+                Ok(_) => tracing::debug!("prefix cache: saved tokens differ; prefilling"),
+                Err(error) => tracing::warn!(%error, "prefix cache: could not load preamble"),
+            }
+            ctx.clear_kv_cache();
+        }
+
+        prefill_returning_logits_idx(ctx, batch, preamble, 0, BATCH_SIZE)?;
+        // Write aside and rename so a hub starting meanwhile never loads a partial file.
+        let staging = file.with_extension(format!("{}.tmp", std::process::id()));
+        let saved = std::fs::create_dir_all(&self.dir)
+            .map_err(eyre::Report::from)
+            .and_then(|()| Ok(ctx.save_session_file(&staging, preamble)?))
+            .and_then(|()| Ok(std::fs::rename(&staging, &file)?));
+        if let Err(error) = saved {
+            tracing::warn!(%error, "prefix cache: could not save preamble");
+            let _ = std::fs::remove_file(&staging);
+        }
+        Ok(preamble.len())
+    }
+
+    fn key(&self, preamble: &[LlamaToken]) -> String {
+        preamble_key(&self.model, preamble.iter().map(|token| token.0))
+    }
+}
+
+/// Hash of the model identity and the preamble tokens; either changing picks another file.
+fn preamble_key(model: &str, preamble: impl Iterator<Item = i32>) -> String {
+    let bytes = preamble.flat_map(i32::to_le_bytes).collect::<Vec<_>>();
+    content_key(model, &bytes)
+}
+
+/// `~/.please/cache`, shared by the saved preambles and the CLI's response cache.
+pub fn cache_dir() -> Option<PathBuf> {
+    let home = std::env::var("HOME").ok()?;
+    Some(Path::new(&home).join(".please").join("cache"))
+}
+
+/// Path, size and modification time of the weights; a different file or a rewritten one differs.
+pub fn model_identity(model_path: &Path) -> Option<String> {
+    let meta = std::fs::metadata(model_path).ok()?;
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_secs());
+    Some(format!("{}:{}:{mtime}", model_path.display(), meta.len()))
+}
+
+/// File name for cached content: a SHA-256 of the model identity and the content, shortened.
+pub fn content_key(model: &str, content: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update(model.as_bytes());
+    hasher.update([0]);
+    hasher.update(content);
+    let digest = format!("{:x}", hasher.finalize());
+    digest[..32].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_change_with_the_model_and_the_preamble() {
+        let key = preamble_key("a.gguf:10:0", [1, 2, 3].into_iter());
+        assert_eq!(key, preamble_key("a.gguf:10:0", [1, 2, 3].into_iter()));
+        assert_ne!(key, preamble_key("a.gguf:11:0", [1, 2, 3].into_iter()));
+        assert_ne!(key, preamble_key("a.gguf:10:0", [1, 2, 4].into_iter()));
+        assert_eq!(key.len(), 32);
+    }
+}
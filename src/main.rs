@@ -2,6 +2,7 @@
 use eyre::Result;
 
 pub mod cli;
+pub mod config;
 pub mod display;
 pub mod harmony;
 pub mod history;
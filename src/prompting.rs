@@ -13,6 +13,18 @@ Tool calls must be sent in the commentary channel with a recipient: `to=function
 In commentary, output only JSON for the tool arguments with no extra text. Keep final answers concise and actionable.
 "#;
 
+/// What we tell the model instead of the tool guidance when tools are disabled.
+pub const NO_TOOLS_GUIDANCE: &str = r#"# No tools
+No tools are available in this session and any tool call will be rejected.
+Answer directly in `final` from the conversation alone.
+"#;
+
+/// What we tell the model in direct-answer mode, where latency matters more than deliberation.
+pub const DIRECT_ANSWER_GUIDANCE: &str = r#"# Direct answers
+Skip the `analysis` channel: do not think before answering.
+Start your reply in `final`, or in `commentary` when a tool call is needed, and keep it brief.
+"#;
+
 /// What we let the model know about the tools it can call.
 pub const TOOL_GUIDANCE: &str = r#"# Tool calling instructions
 Call tools in the `commentary` channel with a recipient: `to=functions.<name>` and pure JSON args only.
@@ -27,31 +39,50 @@ After tool output, continue reasoning, then write your response in `final`.
 # Tools available
 ```
 namespace functions {
-  // List files under a path recursively with optional depth.
-  // Defaults: path=".", max_depth=0
+  // List files under a path recursively with optional depth, leaving out what .gitignore ignores. detailed=true adds kind, size and modification time (RFC 3339) to each entry.
+  // Defaults: path=".", max_depth=0, respect_gitignore=true
   type list_files = (_: {
     path?: string,
     max_depth?: number,
-  }) => string[] | { error: string };
+    respect_gitignore?: boolean,
+    detailed?: boolean,
+  }) => string[] | { path: string, kind: "file" | "dir" | "symlink", size_bytes: number, modified: string | null }[] | { error: string };
+
+  // Search files for lines containing literal text, or matching pattern as a regex with regex=true; context_lines adds surrounding lines like `grep -C`.
+  // Prefer this over running grep. What .gitignore ignores is left out. Lines longer than 300 characters are cut with "…"; max_matches is at most 1000.
+  // Defaults: path=".", regex=false, context_lines=0, max_matches=100
+  type search_files = (_: {
+    pattern: string,
+    regex?: boolean,
+    path?: string,
+    context_lines?: number,
+    max_matches?: number,
+  }) => { matches: { path: string, line: number, text: string, before?: string[], after?: string[] }[], truncated: boolean } | { error: string };
 
-  // Read a file's content with a byte limit.
+  // Read a file's content with a byte limit. With start_line or end_line (1-based, inclusive), only those lines come back, after a header like "lines 40-80 of 1200". Binary files are refused unless force is true.
   // Defaults: max_bytes=524288
   type read_file = (_: {
     path: string,
     max_bytes?: number,
-  }) => string | { error: string };
+    start_line?: number,
+    end_line?: number,
+    force?: boolean,
+  }) => string | { error: "binary file", size_bytes: number } | { error: string };
 
-  // Start a command by argv. Output is capped. Commands still running after waitSeconds, default 40, return status="running" with a pid. When that happens, always call control_command next; do not answer final while a command is still running.
-  type run_command = (_: { argv: string[], waitSeconds?: number }) => {
+  // Start a command by argv. Each stream is capped at maxOutputBytes, default 65536; keep="tail" returns the end of longer output instead of the start. env adds environment variables; stdin is fed to the command, whose stdin is empty otherwise. Commands still running after waitSeconds, default 40, return status="running" with a pid. When that happens, always call control_command next; do not answer final while a command is still running. Commands still running after timeoutSeconds are stopped and return error="timed out" with the output so far.
+  type run_command = (_: { argv: string[], waitSeconds?: number, timeoutSeconds?: number, maxOutputBytes?: number, keep?: "head" | "tail", env?: { [name: string]: string }, stdin?: string }) => {
     ok: boolean,
-    status: "finished" | "running",
+    status: "finished" | "running" | "killed",
     runningFor: string,
     stdout: string,
+    stdoutTruncated: boolean,
     stdoutBytesOmitted: number,
     stderr: string,
+    stderrTruncated: boolean,
     stderrBytesOmitted: number,
     pid?: number,
     exitCode?: number | null,
+    error?: "timed out",
     next?: string,
   } | { error: string };
 
@@ -61,8 +92,10 @@ namespace functions {
     status: "finished" | "running" | "killed",
     runningFor: string,
     stdout: string,
+    stdoutTruncated: boolean,
     stdoutBytesOmitted: number,
     stderr: string,
+    stderrTruncated: boolean,
     stderrBytesOmitted: number,
     pid?: number,
     exitCode?: number | null,
@@ -71,10 +104,31 @@ namespace functions {
   } | { error: string };
 
   // Write file content
+  // With partial=true, hunks that match are written even if others fail; results list the failed hunks to fix.
+  // With verify, the command runs right after a clean apply and its output is returned as `verify`.
+  // A hunk that is not found says where the closest lines are; matching="relaxed" lets context lines differ in indentation and blank lines.
+  // With include_diff=true, each updated file's result has a `diff` of what changed, numbered as in the file.
+  // With dry_run=true, nothing is written: the same results come back with dry_run: true, so failing hunks can be fixed first.
+  // With backup=true, each file is copied to `<path>.please.bak.<unix time>` before it changes, and the copy is reported as `backup`.
   type apply_patch = (_: {
     path?: string,
     patch: string,
-  }) => { ok: true, mode: "overwrite", path: string } | { ok: true, mode: "patch", results: any[] } | { error: string };
+    partial?: boolean,
+    matching?: "strict" | "relaxed",
+    include_diff?: boolean,
+    verify?: string[],
+    dry_run?: boolean,
+    backup?: boolean,
+  }) => { ok: true, mode: "overwrite", path: string, backup?: string, verify?: any, dry_run?: true } | { ok: true, mode: "patch", results: any[], verify?: any, dry_run?: true } | { error: string };
+
+  // Create a new file with exactly this content. Fails if the file already exists; never overwrites.
+  type create_file = (_: { path: string, content: string }) => { ok: true, path: string } | { error: string };
+
+  // Replace a file's whole content, or create it. Prefer apply_patch for edits to part of a file.
+  type write_file = (_: { path: string, content: string, create_dirs?: boolean }) => { ok: true, path: string, bytes_written: number } | { error: string };
+
+  // Ask the user a question and wait for the reply, which arrives as the next user message. Use it only when a wrong guess would waste real work.
+  type ask_user = (_: { question: string }) => { ok: true, reply: string } | { error: string };
 } // namespace functions
 ```
 
@@ -110,6 +164,8 @@ namespace functions {
     - Body is the full file content (optionally fenced in triple backticks).
   - Delete a file:
     - `*** Delete File: path/to/file`
+  - Move or rename a file, keeping its content:
+    - `*** Move File: old/path -> new/path`
 - Trailing newline control:
   - To produce a file with no trailing newline, end the add body or final update hunk with the exact marker line: `\ No newline at end of file`.
   - In update hunks, that marker must immediately follow the final `+` or context line that remains in the resulting file.
@@ -139,3 +195,55 @@ This is the exact patch text to pass as the `patch` string:
     ```
     *** End Patch
 "#;
+
+/// `TOOL_GUIDANCE` describing only the tools for which `enabled` holds.
+pub fn tool_guidance(enabled: impl Fn(&str) -> bool) -> String {
+    let (tools, patching) = TOOL_GUIDANCE
+        .split_once("\n# Using `apply_patch` tool")
+        .unwrap_or((TOOL_GUIDANCE, ""));
+    let (head, rest) = tools
+        .split_once("namespace functions {\n")
+        .unwrap_or(("", tools));
+    let (namespace, tail) = rest
+        .split_once("} // namespace functions")
+        .unwrap_or((rest, ""));
+    let mut out = head.to_string();
+    out.push_str("namespace functions {\n");
+    // Each tool in the namespace is a run of lines, comments first, set apart by blank lines.
+    let mut blocks = Vec::new();
+    for block in namespace.split("\n\n") {
+        let name = block
+            .lines()
+            .find_map(|line| line.trim_start().strip_prefix("type "))
+            .and_then(|rest| rest.split_whitespace().next());
+        if name.is_none_or(&enabled) {
+            blocks.push(block.trim_end_matches('\n'));
+        }
+    }
+    out.push_str(&blocks.join("\n\n"));
+    out.push_str("\n} // namespace functions");
+    out.push_str(tail);
+    if !patching.is_empty() && enabled("apply_patch") {
+        out.push_str("\n# Using `apply_patch` tool");
+        out.push_str(patching);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guidance_leaves_out_disabled_tools() {
+        let guidance = tool_guidance(|name| name == "read_file" || name == "list_files");
+        assert!(guidance.contains("type read_file ="));
+        assert!(guidance.contains("type list_files ="));
+        assert!(!guidance.contains("type run_command ="));
+        assert!(!guidance.contains("Start a command by argv"));
+        assert!(!guidance.contains("# Using `apply_patch` tool"));
+        assert!(guidance.contains("} // namespace functions"));
+
+        assert_eq!(tool_guidance(|_| true), TOOL_GUIDANCE);
+    }
+}
@@ -1,4 +1,5 @@
 //! Minimal postcard-framed protocol between CLI and hub.
+//! Each postcard frame is preceded by its length as a big-endian `u32`, so a reader can step over a corrupt one.
 use eyre::{Result, eyre};
 use serde::{Deserialize, Serialize};
 
@@ -10,12 +11,42 @@ pub enum Message {
     Reasoning(String),
     Tool(String),
     Assistant(String),
+    /// User-visible assistant notes sent on `commentary` without a recipient.
+    Commentary(String),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Per-request overrides of hub-side generation settings; `None` keeps the hub's default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RequestOptions {
+    /// Always pick the most likely token.
+    pub greedy: Option<bool>,
+    /// Stop after generating this many tokens.
+    pub max_tokens: Option<u32>,
+    /// End the answer where any of these strings first appears, leaving the string out.
+    #[serde(default)]
+    pub stop: Vec<String>,
+    /// Steer with Mirostat v2 rather than top-k and top-p.
+    pub mirostat: Option<bool>,
+    pub temperature: Option<f32>,
+    pub top_k: Option<i32>,
+    pub top_p: Option<f32>,
+    /// Seed for the random samplers; without it the hub picks a fresh one.
+    pub seed: Option<u32>,
+    /// Reasoning effort for the system message: `low`, `medium` or `high`.
+    pub reasoning: Option<String>,
+    /// Turn down any tool call, reporting it back as an error.
+    pub no_tools: Option<bool>,
+    /// Send prefill and decode timings back as log lines; off unless asked for.
+    pub timings: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Frame {
     Request {
         messages: Vec<Message>,
+        /// Optional for JSON clients; postcard always carries it.
+        #[serde(default)]
+        options: RequestOptions,
     },
     Log(String),
     Answer(String),
@@ -27,6 +58,14 @@ pub enum Frame {
     },
     ToolCallParseError(String),
     Stop,
+    /// Delta of a `commentary` message without a recipient: a note, not the final answer.
+    Commentary(String),
+    /// Ask the hub to re-run discovery and swap in the chosen model.
+    Reload,
+    /// Ask the hub to describe its loaded state.
+    Status,
+    /// The hub turned the request down; the client reports this and gives up on the turn.
+    Error(String),
 }
 
 #[derive(Debug)]
@@ -35,6 +74,40 @@ pub enum ProtocolError {
     Timeout,
     Io(std::io::Error),
     Decode(postcard::Error),
+    /// A frame that could not be decoded was stepped over; the stream is still usable.
+    Skipped {
+        bytes: usize,
+    },
+    /// A length prefix beyond [`MAX_FRAME_BYTES`]; the stream cannot be trusted past it.
+    TooLarge(usize),
+}
+
+/// Frames above this size are refused. It also keeps the first byte of a length prefix zero,
+/// so a postcard stream is never sniffed as JSON.
+pub const MAX_FRAME_BYTES: usize = 16 * 1024 * 1024 - 1;
+
+/// Size of the length prefix ahead of each postcard frame.
+const LENGTH_PREFIX_BYTES: usize = 4;
+
+/// How frames are encoded on a connection.
+/// The CLI speaks postcard; other frontends may speak newline-delimited JSON instead,
+/// with each line holding one frame in serde's externally tagged form, e.g. `{"Answer":"hi"}`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Postcard,
+    JsonLines,
+}
+
+impl Encoding {
+    /// Tell the encoding from the first byte a client sends.
+    /// JSON opens with `{` or `"`, which never begin a postcard frame as its length prefix opens with zero.
+    pub fn sniff(first: u8) -> Self {
+        match first {
+            b'{' | b'"' => Encoding::JsonLines,
+            _ => Encoding::Postcard,
+        }
+    }
 }
 
 impl std::fmt::Display for ProtocolError {
@@ -46,6 +119,15 @@ impl std::fmt::Display for ProtocolError {
             ProtocolError::Io(e) => write!(f, "io error: {e}"),
             ProtocolError::Timeout => write!(f, "timed out while reading request"),
             ProtocolError::Decode(e) => write!(f, "decode error: {e}"),
+            ProtocolError::Skipped { bytes } => {
+                write!(f, "skipped a corrupt frame of {bytes} bytes")
+            }
+            ProtocolError::TooLarge(len) => {
+                write!(
+                    f,
+                    "frame of {len} bytes exceeds the {MAX_FRAME_BYTES} byte limit"
+                )
+            }
         }
     }
 }
@@ -57,17 +139,147 @@ pub async fn write_frame_to_stream<W: tokio::io::AsyncWriteExt + Unpin, T: serde
     sink: &mut W,
     frame: &T,
 ) -> Result<()> {
-    let bytes = postcard::to_allocvec(frame).map_err(|e| eyre!(e))?;
+    write_frame_as(sink, Encoding::Postcard, frame).await
+}
+
+/// Serialize a frame in the given encoding and write it to the sink.
+pub async fn write_frame_as<W: tokio::io::AsyncWriteExt + Unpin, T: serde::Serialize>(
+    sink: &mut W,
+    encoding: Encoding,
+    frame: &T,
+) -> Result<()> {
+    let bytes = encode_frame(encoding, frame)?;
     sink.write_all(&bytes).await?;
     Ok(())
 }
 
+/// The exact bytes a frame goes over the wire as, delimiter or length prefix included.
+pub fn encode_frame<T: serde::Serialize>(encoding: Encoding, frame: &T) -> Result<Vec<u8>> {
+    match encoding {
+        Encoding::Postcard => {
+            let body = postcard::to_allocvec(frame).map_err(|e| eyre!(e))?;
+            if body.len() > MAX_FRAME_BYTES {
+                return Err(eyre!(ProtocolError::TooLarge(body.len())));
+            }
+            let mut bytes = Vec::with_capacity(LENGTH_PREFIX_BYTES + body.len());
+            bytes.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&body);
+            Ok(bytes)
+        }
+        Encoding::JsonLines => {
+            let mut line = serde_json::to_vec(frame)?;
+            line.push(b'\n');
+            Ok(line)
+        }
+    }
+}
+
 /// Read a single postcard frame from the stream, buffering as needed.
+/// Corrupt frames are logged and stepped over.
 pub async fn read_frame_from_stream<T: serde::de::DeserializeOwned>(
     stream: &mut tokio::net::UnixStream,
     store: &mut Vec<u8>,
     per_read_timeout: Option<std::time::Duration>,
     total_timeout: Option<std::time::Duration>,
+) -> std::result::Result<T, ProtocolError> {
+    loop {
+        match read_frame_as(
+            stream,
+            store,
+            Encoding::Postcard,
+            per_read_timeout,
+            total_timeout,
+        )
+        .await
+        {
+            Err(ProtocolError::Skipped { .. }) => continue,
+            result => return result,
+        }
+    }
+}
+
+/// Wait for the first bytes of a connection and tell which encoding the client speaks.
+/// The bytes stay in `store` for the first read.
+pub async fn sniff_encoding(
+    stream: &mut tokio::net::UnixStream,
+    store: &mut Vec<u8>,
+    total_timeout: Option<std::time::Duration>,
+) -> std::result::Result<Encoding, ProtocolError> {
+    use tokio::io::AsyncReadExt;
+
+    let total_timeout = total_timeout.unwrap_or(std::time::Duration::MAX);
+    let mut chunk = [0u8; 4096];
+    while store.is_empty() {
+        match tokio::time::timeout(total_timeout, AsyncReadExt::read(stream, &mut chunk)).await {
+            Err(_) => return Err(ProtocolError::Timeout),
+            Ok(Err(e)) => return Err(ProtocolError::Io(e)),
+            Ok(Ok(0)) => return Err(ProtocolError::Disconnect),
+            Ok(Ok(n)) => store.extend_from_slice(&chunk[..n]),
+        }
+    }
+    Ok(Encoding::sniff(store[0]))
+}
+
+/// Take one complete frame off the front of `store`, or `None` when more bytes are needed.
+/// A frame that fails to decode is dropped from `store` and reported as [`ProtocolError::Skipped`],
+/// leaving the next one ready to read.
+fn take_frame<T: serde::de::DeserializeOwned>(
+    store: &mut Vec<u8>,
+    encoding: Encoding,
+) -> std::result::Result<Option<T>, ProtocolError> {
+    match encoding {
+        Encoding::Postcard => {
+            let Some(prefix) = store.first_chunk::<LENGTH_PREFIX_BYTES>() else {
+                return Ok(None);
+            };
+            let len = u32::from_be_bytes(*prefix) as usize;
+            if len > MAX_FRAME_BYTES {
+                return Err(ProtocolError::TooLarge(len));
+            }
+            if store.len() < LENGTH_PREFIX_BYTES + len {
+                return Ok(None);
+            }
+            let frame = store
+                .drain(..LENGTH_PREFIX_BYTES + len)
+                .skip(LENGTH_PREFIX_BYTES)
+                .collect::<Vec<_>>();
+            // The prefix marks where the next frame starts, so a corrupt one is skipped rather than fatal.
+            match postcard::from_bytes(&frame) {
+                Ok(frame) => Ok(Some(frame)),
+                Err(error) => {
+                    tracing::warn!(%error, bytes = len, "protocol: skipping a corrupt frame");
+                    Err(ProtocolError::Skipped { bytes: len })
+                }
+            }
+        }
+        Encoding::JsonLines => loop {
+            let Some(end) = store.iter().position(|&byte| byte == b'\n') else {
+                return Ok(None);
+            };
+            let line = store.drain(..=end).collect::<Vec<_>>();
+            // Blank lines between frames are tolerated.
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            // The newline marks where the next frame starts, so a corrupt one is skipped rather than fatal.
+            return match serde_json::from_slice(&line) {
+                Ok(frame) => Ok(Some(frame)),
+                Err(error) => {
+                    tracing::warn!(%error, bytes = line.len(), "protocol: skipping a corrupt frame");
+                    Err(ProtocolError::Skipped { bytes: line.len() })
+                }
+            };
+        },
+    }
+}
+
+/// Read a single frame in the given encoding from the stream, buffering as needed.
+pub async fn read_frame_as<T: serde::de::DeserializeOwned>(
+    stream: &mut tokio::net::UnixStream,
+    store: &mut Vec<u8>,
+    encoding: Encoding,
+    per_read_timeout: Option<std::time::Duration>,
+    total_timeout: Option<std::time::Duration>,
 ) -> std::result::Result<T, ProtocolError> {
     use std::time::Instant;
     use tokio::io::AsyncReadExt;
@@ -79,21 +291,10 @@ pub async fn read_frame_from_stream<T: serde::de::DeserializeOwned>(
 
     loop {
         if !store.is_empty() {
-            match postcard::take_from_bytes::<T>(&store[..]) {
-                Err(postcard::Error::DeserializeUnexpectedEnd) => {
-                    // Need more bytes; fall through to the read path below.
-                }
-                Err(e) => {
-                    // Broken transmission; abort
-                    return Err(ProtocolError::Decode(e));
-                }
-                Ok((msg, rest)) => {
-                    // Chop off the consumed prefix, keep remainder for next call
-                    let consumed = store.len() - rest.len();
-                    let _ = store.drain(0..consumed);
-                    return Ok(msg);
-                }
+            if let Some(msg) = take_frame(store, encoding)? {
+                return Ok(msg);
             }
+            // Need more bytes; fall through to the read path below.
         }
 
         if start.elapsed() > total_timeout {
@@ -110,3 +311,74 @@ pub async fn read_frame_from_stream<T: serde::de::DeserializeOwned>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_lines_frames_are_read_one_line_at_a_time() {
+        let mut store =
+            b"{\"Request\":{\"messages\":[{\"User\":\"hi\"}]}}\n\n\"Status\"\n{\"Ans".to_vec();
+        assert_eq!(Encoding::sniff(store[0]), Encoding::JsonLines);
+
+        let frame = take_frame::<Frame>(&mut store, Encoding::JsonLines).unwrap();
+        assert!(matches!(
+            frame,
+            Some(Frame::Request { messages, options })
+                if matches!(&messages[..], [Message::User(text)] if text == "hi")
+                    && options == RequestOptions::default()
+        ));
+        let frame = take_frame::<Frame>(&mut store, Encoding::JsonLines).unwrap();
+        assert!(matches!(frame, Some(Frame::Status)));
+        let frame = take_frame::<Frame>(&mut store, Encoding::JsonLines).unwrap();
+        assert!(frame.is_none());
+        assert_eq!(store, b"{\"Ans");
+    }
+
+    #[test]
+    fn corrupt_json_lines_are_skipped() {
+        let mut store = b"{\"Answer\":\n\"Stop\"\n".to_vec();
+        let skipped = take_frame::<Frame>(&mut store, Encoding::JsonLines);
+        assert!(matches!(skipped, Err(ProtocolError::Skipped { bytes: 11 })));
+        let frame = take_frame::<Frame>(&mut store, Encoding::JsonLines).unwrap();
+        assert!(matches!(frame, Some(Frame::Stop)));
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn corrupt_postcard_frames_are_skipped_by_their_length() {
+        let mut store = 3u32.to_be_bytes().to_vec();
+        store.extend_from_slice(&[0xff, 0xff, 0xff]);
+        store.extend_from_slice(&encode_frame(Encoding::Postcard, &Frame::Stop).unwrap());
+        let partial = encode_frame(Encoding::Postcard, &Frame::Answer("hi".into())).unwrap();
+        store.extend_from_slice(&partial[..5]);
+
+        let skipped = take_frame::<Frame>(&mut store, Encoding::Postcard);
+        assert!(matches!(skipped, Err(ProtocolError::Skipped { bytes: 3 })));
+        let frame = take_frame::<Frame>(&mut store, Encoding::Postcard).unwrap();
+        assert!(matches!(frame, Some(Frame::Stop)));
+        let frame = take_frame::<Frame>(&mut store, Encoding::Postcard).unwrap();
+        assert!(frame.is_none());
+        assert_eq!(store, partial[..5]);
+    }
+
+    #[test]
+    fn oversized_postcard_frames_are_refused() {
+        let mut store = u32::MAX.to_be_bytes().to_vec();
+        let error = take_frame::<Frame>(&mut store, Encoding::Postcard);
+        assert!(matches!(error, Err(ProtocolError::TooLarge(_))));
+    }
+
+    #[test]
+    fn postcard_frames_are_never_mistaken_for_json() {
+        let request = Frame::Request {
+            messages: vec![],
+            options: RequestOptions::default(),
+        };
+        for frame in [request, Frame::Reload, Frame::Status] {
+            let bytes = encode_frame(Encoding::Postcard, &frame).unwrap();
+            assert_eq!(Encoding::sniff(bytes[0]), Encoding::Postcard);
+        }
+    }
+}
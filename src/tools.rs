@@ -1,16 +1,28 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
 pub mod common;
 use self::common::{AsyncFn, Param, with_args};
 
 mod apply_patch;
+mod ask_user;
 mod control_command;
+mod create_file;
+mod gitignore;
 mod list_files;
 mod read_file;
 mod run_command;
+mod search_files;
+mod sensitive;
+mod write_file;
 
 pub use self::common::Stride;
-pub use apply_patch::summarize_patch_for_preview;
+pub use apply_patch::{
+    count_patch_ops, summarize_new_file_for_preview, summarize_overwrite_for_preview,
+    summarize_patch_failures, summarize_patch_for_preview,
+};
+pub use run_command::{run_for_context, summarize_command_for_preview, write_warnings};
+pub use sensitive::{DEFAULT_SENSITIVE_PATTERNS, is_sensitive_path};
 
 /// Exposed tools are represented as a map keyed by function name.
 pub type ExposedTools = HashMap<&'static str, (&'static str, AsyncFn, Vec<Param>)>;
@@ -20,6 +32,14 @@ pub enum ToolKind {
     RunCommand,
     ControlCommand,
     ApplyPatch,
+    /// Writes a file that must not exist yet.
+    CreateFile,
+    /// Replaces a whole file, or creates it.
+    WriteFile,
+    /// A question for the user, answered by the CLI rather than run as a tool.
+    AskUser,
+    /// Tools that show file content to the model.
+    ReadFiles,
     Other,
 }
 
@@ -33,11 +53,30 @@ impl ToolKind {
     }
 
     pub fn starts_command(self, args: &serde_json::Value) -> bool {
-        matches!(self, Self::RunCommand)
-            && args
-                .get("argv")
-                .and_then(|value| value.as_array())
-                .is_some_and(|argv| !argv.is_empty())
+        let argv_key = match self {
+            Self::RunCommand => "argv",
+            // A patch may carry a command that verifies it.
+            Self::ApplyPatch => "verify",
+            Self::ControlCommand
+            | Self::CreateFile
+            | Self::WriteFile
+            | Self::AskUser
+            | Self::ReadFiles
+            | Self::Other => {
+                return false;
+            }
+        };
+        args.get(argv_key)
+            .and_then(|value| value.as_array())
+            .is_some_and(|argv| !argv.is_empty())
+    }
+
+    /// Whether the call lets a command run, so its output can be shown as it arrives.
+    pub fn runs_command(self, args: &serde_json::Value) -> bool {
+        match self {
+            Self::ControlCommand => args.get("action").and_then(|v| v.as_str()) == Some("wait"),
+            _ => self.starts_command(args),
+        }
     }
 }
 
@@ -51,12 +90,36 @@ pub fn kind_of(name: &str) -> ToolKind {
     if name == apply_patch::NAME {
         return ToolKind::ApplyPatch;
     }
+    if name == ask_user::NAME {
+        return ToolKind::AskUser;
+    }
+    if name == create_file::NAME {
+        return ToolKind::CreateFile;
+    }
+    if name == write_file::NAME {
+        return ToolKind::WriteFile;
+    }
+    if name == read_file::NAME || name == search_files::NAME {
+        return ToolKind::ReadFiles;
+    }
     ToolKind::Other
 }
 
 pub const CONTROL_COMMAND_NAME: &str = control_command::NAME;
 
+/// Every tool this process may use: none with `PLEASE_NO_TOOLS`, and only those that
+/// `PLEASE_TOOLS` allows and `PLEASE_DISABLE` does not name.
 pub fn all_tools() -> ExposedTools {
+    enabled_tools(registry(), crate::config::get())
+}
+
+fn enabled_tools(mut tools: ExposedTools, config: &crate::config::Config) -> ExposedTools {
+    tools.retain(|name, _| config.tool_enabled(name));
+    tools
+}
+
+/// Every registered tool, enabled or not.
+fn registry() -> ExposedTools {
     macro_rules! collect_tools {
       ($($module:ident),+ $(,)?) => {{
         let mut map: ExposedTools = HashMap::new();
@@ -72,9 +135,13 @@ pub fn all_tools() -> ExposedTools {
     collect_tools![
         list_files,
         read_file,
+        search_files,
         run_command,
         control_command,
-        apply_patch
+        apply_patch,
+        create_file,
+        write_file,
+        ask_user
     ]
 }
 
@@ -86,7 +153,149 @@ pub async fn invoke(
     args: serde_json::Value,
 ) -> Result<serde_json::Value, String> {
     let Some((_, work, _)) = tools.get(name) else {
-        return Err("No such function".to_string());
+        if registry().contains_key(name) {
+            return Err(format!(
+                "tool disabled: `{name}` is not available in this session"
+            ));
+        }
+        return Err(unknown_tool_error(name));
     };
-    Ok(work(args, stride).await)
+    let limit = time_limit(crate::config::get().tool_timeout, &args);
+    Ok(guarded(work(args, stride), limit).await)
+}
+
+/// The configured limit, stretched by the `waitSeconds` a command tool was asked to wait.
+fn time_limit(base: Duration, args: &serde_json::Value) -> Duration {
+    let wait = args
+        .get("waitSeconds")
+        .and_then(|v| v.as_f64())
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .unwrap_or_default();
+    base.saturating_add(wait)
+}
+
+/// Run a tool call on its own task, so that a panic or a call outliving `limit` comes back
+/// as an error result instead of taking the turn down with it.
+async fn guarded(work: common::ToolFuture, limit: Duration) -> serde_json::Value {
+    /// Dropping the call, as Ctrl-C does, stops the tool too.
+    struct AbortOnDrop(tokio::task::JoinHandle<serde_json::Value>);
+    impl Drop for AbortOnDrop {
+        fn drop(&mut self) {
+            self.0.abort();
+        }
+    }
+
+    let mut task = AbortOnDrop(tokio::spawn(work));
+    match tokio::time::timeout(limit, &mut task.0).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(error)) if error.is_panic() => {
+            let panic = error.into_panic();
+            let detail = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            serde_json::json!({ "error": "tool panicked", "detail": detail })
+        }
+        Ok(Err(_)) => serde_json::json!({ "error": "tool was cancelled" }),
+        Err(_) => serde_json::json!({
+            "error": "tool timed out",
+            "limit_seconds": limit.as_secs(),
+        }),
+    }
+}
+
+/// gpt-oss was trained with `python` and `browser.*` built-ins and may call them unprompted.
+/// None are registered here, so point the model at what it can use instead.
+fn unknown_tool_error(name: &str) -> String {
+    if name == "python" {
+        return format!(
+            "`python` is not available here; run scripts with {}, e.g. argv [\"python3\", \"-c\", ...]",
+            run_command::NAME
+        );
+    }
+    if name.starts_with("browser.") {
+        return format!("`{name}` is not available here; there is no web access");
+    }
+    "No such function".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtins_the_model_expects_get_a_pointed_error() {
+        assert!(unknown_tool_error("python").contains(run_command::NAME));
+        assert!(unknown_tool_error("browser.search").contains("no web access"));
+        assert_eq!(unknown_tool_error("frobnicate"), "No such function");
+    }
+
+    #[tokio::test]
+    async fn panicking_and_hanging_tools_become_error_results() {
+        let mut tools = ExposedTools::new();
+        let panics = with_args::<serde_json::Value, _, _>(|_, _| async {
+            serde_json::from_str::<serde_json::Value>("not json").expect("stub tool broke")
+        });
+        tools.insert("panics", ("Always panics.", panics, Vec::new()));
+        let result = invoke(&tools, Stride::default(), "panics", serde_json::json!({}))
+            .await
+            .unwrap();
+        assert_eq!(result["error"], "tool panicked");
+        assert!(
+            result["detail"]
+                .as_str()
+                .unwrap()
+                .contains("stub tool broke")
+        );
+
+        let hangs: common::ToolFuture = Box::pin(std::future::pending());
+        let result = guarded(hangs, Duration::from_millis(20)).await;
+        assert_eq!(result["error"], "tool timed out");
+    }
+
+    #[test]
+    fn waits_a_tool_asks_for_stretch_its_time_limit() {
+        let base = Duration::from_secs(600);
+        let args = serde_json::json!({ "waitSeconds": 900 });
+        assert_eq!(time_limit(base, &args), Duration::from_secs(1500));
+        assert_eq!(time_limit(base, &serde_json::json!({})), base);
+    }
+
+    fn enabled_names(config: &crate::config::Config) -> Vec<&'static str> {
+        let mut names = enabled_tools(registry(), config)
+            .into_keys()
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn allow_lists_keep_only_the_named_tools() {
+        let config = crate::config::Config {
+            tools: Some(vec!["read_file".to_string(), "list_files".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(enabled_names(&config), ["list_files", "read_file"]);
+    }
+
+    #[test]
+    fn deny_lists_drop_the_named_tools_and_win_over_allow_lists() {
+        let config = crate::config::Config {
+            disabled_tools: vec!["run_command".to_string(), "apply_patch".to_string()],
+            ..Default::default()
+        };
+        let names = enabled_names(&config);
+        assert!(!names.contains(&"run_command"));
+        assert!(!names.contains(&"apply_patch"));
+        assert!(names.contains(&"read_file"));
+        assert_eq!(names.len(), registry().len() - 2);
+
+        let config = crate::config::Config {
+            tools: Some(vec!["read_file".to_string(), "run_command".to_string()]),
+            disabled_tools: vec!["run_command".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(enabled_names(&config), ["read_file"]);
+    }
 }
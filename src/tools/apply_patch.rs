@@ -9,8 +9,19 @@ use super::common::{Param, ParamType, Stride};
 use serde::Deserialize;
 use serde_json::json;
 
+pub(super) use filesystem::write_verbatim_within_cwd;
 pub use parsing::parse_patch_ops;
-pub use preview::summarize_patch_for_preview;
+pub use preview::{
+    summarize_new_file_for_preview, summarize_overwrite_for_preview, summarize_patch_for_preview,
+};
+
+/// Number of file operations in a patch, or `None` for overwrite mode and unparsable patches.
+pub fn count_patch_ops(raw: &str) -> Option<usize> {
+    if !parsing::contains_patch_syntax(raw) {
+        return None;
+    }
+    parse_patch_ops(raw).ok().map(|ops| ops.len())
+}
 
 pub const NAME: &str = "apply_patch";
 
@@ -21,9 +32,58 @@ pub struct Args {
     path: Option<String>,
     /// Raw content to overwrite with, or an OpenAI-style patch to apply
     patch: Option<String>,
+    /// Keep the hunks that matched even when others in the same file did not
+    #[serde(default)]
+    partial: bool,
+    /// Whether hunks may match despite different indentation and blank lines
+    #[serde(default)]
+    matching: model::Matching,
+    /// Return a unified diff of each updated file
+    #[serde(default)]
+    include_diff: bool,
+    /// Command to run once the patch applied cleanly, e.g. `["cargo", "check"]`
+    #[serde(default)]
+    verify: Vec<String>,
+    /// Report what the patch would do without changing any file
+    #[serde(default)]
+    dry_run: bool,
+    /// Copy changed files aside first; defaults to `PLEASE_PATCH_BACKUPS`
+    #[serde(default)]
+    backup: Option<bool>,
 }
 
-pub async fn call(args: Args, _stride: Stride) -> serde_json::Value {
+pub async fn call(mut args: Args, stride: Stride) -> serde_json::Value {
+    let verify = std::mem::take(&mut args.verify);
+    let mut result = apply(args);
+    // A dry run leaves nothing on disk to verify.
+    if verify.is_empty() || result.get("dry_run").is_some() {
+        return result;
+    }
+    let verification = if applied_cleanly(&result) {
+        super::run_command::verify(verify, stride).await
+    } else {
+        json!({ "skipped": "the patch did not apply cleanly" })
+    };
+    if let Some(fields) = result.as_object_mut() {
+        fields.insert("verify".to_string(), verification);
+    }
+    result
+}
+
+/// True when there is no top-level error and every file operation succeeded.
+fn applied_cleanly(result: &serde_json::Value) -> bool {
+    result.get("error").is_none()
+        && result
+            .get("results")
+            .and_then(|results| results.as_array())
+            .is_none_or(|results| results.iter().all(|r| r["ok"] == true))
+}
+
+fn apply(args: Args) -> serde_json::Value {
+    if args.dry_run {
+        return dry_run(&args);
+    }
+    let backups = args.backup.unwrap_or(crate::config::get().patch_backups);
     let content = match args.patch {
         Some(s) => s,
         None => return json!({ "error": "apply_patch requires parameter `patch`" }),
@@ -35,23 +95,108 @@ pub async fn call(args: Args, _stride: Stride) -> serde_json::Value {
             return json!({ "error": "overwrite mode requires `path`" });
         };
 
-        return match filesystem::write_verbatim_within_cwd(path, &content) {
-            Ok(()) => json!({ "ok": true, "mode": "overwrite", "path": path }),
+        let written = match backups {
+            true => filesystem::back_up_within_cwd(path),
+            false => Ok(None),
+        }
+        .and_then(|backup| filesystem::write_verbatim_within_cwd(path, &content).map(|()| backup));
+        return match written {
+            Ok(None) => json!({ "ok": true, "mode": "overwrite", "path": path }),
+            Ok(Some(backup)) => {
+                json!({ "ok": true, "mode": "overwrite", "path": path, "backup": backup })
+            }
             Err(e) => json!({ "error": e.to_string() }),
         };
     }
 
     // Patch mode: parse -> execute; tolerate per-op errors, keep going.
     match parse_patch_ops(&content) {
-        Ok(ops) => filesystem::execute_patch_ops(ops),
+        Ok(ops) => {
+            let mut workspace = filesystem::Workspace { backups };
+            filesystem::execute_patch_ops(
+                &mut workspace,
+                ops,
+                args.partial,
+                args.matching,
+                args.include_diff,
+            )
+        }
         Err(e) => json!({ "error": e }),
     }
 }
 
+/// The results applying the patch would give, with every change kept in memory.
+fn dry_run(args: &Args) -> serde_json::Value {
+    let Some(content) = args.patch.as_deref() else {
+        return json!({ "error": "apply_patch requires parameter `patch`" });
+    };
+    if !parsing::contains_patch_syntax(content) {
+        let Some(path) = args.path.as_deref() else {
+            return json!({ "error": "overwrite mode requires `path`" });
+        };
+        return match super::common::resolve_path_within_cwd(path) {
+            Ok(_) => json!({ "ok": true, "mode": "overwrite", "path": path, "dry_run": true }),
+            Err(e) => json!({ "error": e.to_string() }),
+        };
+    }
+    let mut result = match parse_patch_ops(content) {
+        Ok(ops) => filesystem::execute_patch_ops(
+            &mut filesystem::DryRun::default(),
+            ops,
+            args.partial,
+            args.matching,
+            args.include_diff,
+        ),
+        Err(e) => json!({ "error": e }),
+    };
+    if let Some(fields) = result.as_object_mut() {
+        fields.insert("dry_run".to_string(), json!(true));
+    }
+    result
+}
+
+/// Lines naming each operation of `patch` that would fail, for the approval prompt.
+pub fn summarize_patch_failures(patch: &str) -> Vec<String> {
+    let result = dry_run(&Args {
+        path: None,
+        patch: Some(patch.to_string()),
+        partial: false,
+        matching: model::Matching::Strict,
+        include_diff: false,
+        verify: Vec::new(),
+        dry_run: true,
+        backup: None,
+    });
+    let results = result["results"].as_array().cloned().unwrap_or_default();
+    results
+        .iter()
+        .filter(|r| r["ok"] != true)
+        .map(|r| {
+            let path = r["path"].as_str().unwrap_or("?");
+            let op = r["op"].as_str().unwrap_or("edit");
+            let Some(errors) = r["errors"].as_array() else {
+                return format!("{op} {path}: {}", r["error"].as_str().unwrap_or("fails"));
+            };
+            let hunks = errors
+                .iter()
+                .filter_map(|e| e["hunk"].as_u64())
+                .map(|hunk| (hunk + 1).to_string())
+                .collect::<Vec<_>>();
+            match hunks.as_slice() {
+                [hunk] => format!("{op} {path}: hunk {hunk} does not match the file"),
+                _ => format!(
+                    "{op} {path}: hunks {} do not match the file",
+                    hunks.join(", ")
+                ),
+            }
+        })
+        .collect()
+}
+
 pub fn spec() -> (&'static str, &'static str, Vec<Param>) {
     (
         NAME,
-        "Apply edits via OpenAI-style patch markers or overwrite without markers. Patch format: wrap ops between '*** Begin Patch' and '*** End Patch'; each op starts with '*** Update File:', '*** Add File:' or '*** Delete File:'. Update bodies use + / - / space prefixes and optional @@ separators; add bodies are raw file content. Append a 'No newline at end of file' comment line to suppress trailing newline. Without markers, requires `path` and overwrites verbatim.",
+        "Apply edits via OpenAI-style patch markers or overwrite without markers. Patch format: wrap ops between '*** Begin Patch' and '*** End Patch'; each op starts with '*** Update File:', '*** Add File:', '*** Delete File:' or '*** Move File: old -> new'. Update bodies use + / - / space prefixes and optional @@ separators; add bodies are raw file content. Append a 'No newline at end of file' comment line to suppress trailing newline. Without markers, requires `path` and overwrites verbatim.",
         vec![
             Param {
                 name: "path",
@@ -65,6 +210,42 @@ pub fn spec() -> (&'static str, &'static str, Vec<Param>) {
                 param_type: ParamType::String,
                 required: true,
             },
+            Param {
+                name: "partial",
+                desc: "Write the hunks that matched and report only the failed ones; default false",
+                param_type: ParamType::Boolean,
+                required: false,
+            },
+            Param {
+                name: "matching",
+                desc: "strict (default) compares lines up to trailing whitespace; relaxed also ignores indentation and blank lines and keeps the file's indentation",
+                param_type: ParamType::Choice(&["strict", "relaxed"]),
+                required: false,
+            },
+            Param {
+                name: "include_diff",
+                desc: "Add a unified diff with line numbers to each updated file's result; default false",
+                param_type: ParamType::Boolean,
+                required: false,
+            },
+            Param {
+                name: "dry_run",
+                desc: "Report the per-file results without changing any file; default false",
+                param_type: ParamType::Boolean,
+                required: false,
+            },
+            Param {
+                name: "backup",
+                desc: "Copy each changed file to <path>.please.bak.<unix time> first; default from PLEASE_PATCH_BACKUPS",
+                param_type: ParamType::Boolean,
+                required: false,
+            },
+            Param {
+                name: "verify",
+                desc: "Argument vector of a command to run after the patch applies cleanly, e.g. [\"cargo\", \"check\"]",
+                param_type: ParamType::Array,
+                required: false,
+            },
         ],
     )
 }
@@ -1,23 +1,27 @@
-use super::model::Hunk;
-use super::text::{find_lines_window, preview};
+use super::model::{Hunk, Matching};
+use super::text::{
+    closest_window, find_lines_window, find_lines_window_ignoring_indent, indent_of, preview,
+};
 
-pub fn apply_all_hunks(before: &str, hunks: &[Hunk]) -> Result<String, Vec<(usize, String)>> {
+/// Apply every hunk that matches, skipping the rest.
+/// Returns the text with the matching hunks applied and the index and error of each skipped hunk.
+pub fn apply_all_hunks(
+    before: &str,
+    hunks: &[Hunk],
+    matching: Matching,
+) -> (String, Vec<(usize, String)>) {
     let mut text = before.to_string();
     let mut errors: Vec<(usize, String)> = Vec::new();
     for (idx, h) in hunks.iter().enumerate() {
-        match apply_hunk(&text, h) {
+        match apply_hunk(&text, h, matching) {
             Ok(next) => text = next,
             Err(e) => errors.push((idx, e)),
         }
     }
-    if errors.is_empty() {
-        Ok(text)
-    } else {
-        Err(errors)
-    }
+    (text, errors)
 }
 
-pub fn apply_hunk(before: &str, h: &Hunk) -> Result<String, String> {
+pub fn apply_hunk(before: &str, h: &Hunk, matching: Matching) -> Result<String, String> {
     if h.old_lines.is_empty() {
         let mut out = String::from(before);
         if !out.is_empty() && !out.ends_with('\n') {
@@ -52,5 +56,53 @@ pub fn apply_hunk(before: &str, h: &Hunk) -> Result<String, String> {
         return Ok(out);
     }
 
-    Err(format!("hunk old text not found: {}", preview(&old_seg)))
+    if matching == Matching::Relaxed
+        && let Some((s, e)) = find_lines_window_ignoring_indent(&before_lines, &old_lines)
+    {
+        let new_lines = reindent(&h.new_lines, &old_lines, &before_lines[s..e]);
+        let mut owned: Vec<String> = before_lines.iter().map(|s| (*s).to_string()).collect();
+        owned.splice(s..e, new_lines);
+        let mut out = owned.join("\n");
+        if ends_with_nl && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        return Ok(out);
+    }
+
+    let mut error = format!("hunk old text not found: {}", preview(&old_seg));
+    if matching == Matching::Strict
+        && find_lines_window_ignoring_indent(&before_lines, &old_lines).is_some()
+    {
+        error.push_str(
+            "; it matches if indentation and blank lines are ignored (matching: \"relaxed\")",
+        );
+    } else if let Some((start, agree)) = closest_window(&before_lines, &old_lines) {
+        let wanted = old_lines.iter().filter(|l| !l.trim().is_empty()).count();
+        error.push_str(&format!(
+            "; closest is at line {}, where {agree} of {wanted} lines agree",
+            start + 1
+        ));
+    }
+    Err(error)
+}
+
+/// Shift the new lines by however the file's indentation differs from the patch's, judged by
+/// the first line that is not blank, so a relaxed match keeps the file's indentation.
+fn reindent(new_lines: &[String], old: &[&str], matched: &[&str]) -> Vec<String> {
+    let first = |lines: &[&str]| {
+        lines
+            .iter()
+            .find(|l| !l.trim().is_empty())
+            .map(|l| indent_of(l).to_string())
+    };
+    let (Some(theirs), Some(ours)) = (first(old), first(matched)) else {
+        return new_lines.to_vec();
+    };
+    new_lines
+        .iter()
+        .map(|line| match line.strip_prefix(theirs.as_str()) {
+            Some(rest) if !line.trim().is_empty() => format!("{ours}{rest}"),
+            _ => line.clone(),
+        })
+        .collect()
 }
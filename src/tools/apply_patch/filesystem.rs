@@ -1,10 +1,13 @@
 use serde_json::json;
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
 
 use super::applying::apply_all_hunks;
-use super::model::PatchOp;
-use super::text::set_trailing_newline;
+use super::model::{Matching, PatchOp};
+use super::preview::unified_diff;
+use super::text::{resolve_trailing_newline, set_trailing_newline};
 use crate::tools::common::resolve_path_within_cwd;
 
 fn write_text_creating_dirs(
@@ -32,6 +35,60 @@ pub fn write_verbatim_within_cwd(path: &str, content: &str) -> std::io::Result<(
     fs::write(rel, content)
 }
 
+/// Copy an existing file at `path` aside before it changes, returning where the copy went.
+/// Files that do not exist yet have nothing to keep.
+pub fn back_up_within_cwd(path: &str) -> std::io::Result<Option<String>> {
+    let rel = resolve_path_within_cwd(path)?; // sanitized relative path
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    Ok(back_up_file(&rel, stamp)?.map(|backup| backup.display().to_string()))
+}
+
+/// Copy `path` to `<path>.please.bak.<stamp>`, numbering the copy when that name is taken.
+pub(super) fn back_up_file(path: &Path, stamp: u64) -> std::io::Result<Option<PathBuf>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let name = |suffix: String| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".please.bak.{stamp}{suffix}"));
+        PathBuf::from(name)
+    };
+    let mut backup = name(String::new());
+    let mut copy = 1;
+    while fs::symlink_metadata(&backup).is_ok() {
+        copy += 1;
+        backup = name(format!("-{copy}"));
+    }
+    fs::copy(path, &backup)?;
+    Ok(Some(backup))
+}
+
+/// Move a file within the workspace without overwriting anything at the destination.
+fn rename_within_cwd(from: &str, to: &str) -> std::io::Result<()> {
+    let from = resolve_path_within_cwd(from)?; // sanitized relative path
+    let to = resolve_path_within_cwd(to)?;
+    if !from.is_file() {
+        return Err(std::io::Error::new(
+            ErrorKind::NotFound,
+            "no such file to move",
+        ));
+    }
+    if fs::symlink_metadata(&to).is_ok() {
+        return Err(std::io::Error::new(
+            ErrorKind::AlreadyExists,
+            "destination already exists",
+        ));
+    }
+    if let Some(parent) = to.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(from, to)
+}
+
 fn remove_file_if_exists(path: &str) -> std::io::Result<()> {
     let rel = resolve_path_within_cwd(path)?; // sanitized relative path
     match fs::remove_file(rel) {
@@ -41,7 +98,209 @@ fn remove_file_if_exists(path: &str) -> std::io::Result<()> {
     }
 }
 
-pub fn execute_patch_ops(ops: Vec<PatchOp>) -> serde_json::Value {
+/// Find an existing path under `root` that matches `rel` when compared component by component
+/// ignoring case. Exact components are preferred; returns `None` when nothing matches.
+pub(super) fn find_path_ignoring_case(root: &Path, rel: &Path) -> Option<PathBuf> {
+    let mut found = PathBuf::new();
+    for component in rel.components() {
+        let Component::Normal(part) = component else {
+            continue;
+        };
+        let exact = found.join(part);
+        if fs::symlink_metadata(root.join(&exact)).is_ok() {
+            found = exact;
+            continue;
+        }
+        let wanted = part.to_string_lossy().to_lowercase();
+        let entry = fs::read_dir(root.join(&found))
+            .ok()?
+            .flatten()
+            .find(|entry| entry.file_name().to_string_lossy().to_lowercase() == wanted)?;
+        found.push(entry.file_name());
+    }
+    Some(found)
+}
+
+/// When enabled, swap a missing path for an existing one differing only in case.
+/// Returns the path to use and, if it was corrected, a note for the result.
+fn correct_case_if_enabled(path: String) -> (String, Option<String>) {
+    if !crate::config::get().patch_case_insensitive {
+        return (path, None);
+    }
+    let Ok(rel) = resolve_path_within_cwd(&path) else {
+        return (path, None);
+    };
+    if fs::symlink_metadata(&rel).is_ok() {
+        return (path, None);
+    }
+    match find_path_ignoring_case(Path::new("."), &rel) {
+        Some(found) => {
+            let found = found.to_string_lossy().to_string();
+            let note = format!("`{path}` not found; used existing `{found}` instead");
+            (found, Some(note))
+        }
+        None => (path, None),
+    }
+}
+
+fn with_note(mut result: serde_json::Value, note: &Option<String>) -> serde_json::Value {
+    if let (Some(note), Some(fields)) = (note, result.as_object_mut()) {
+        fields.insert("note".to_string(), json!(note));
+    }
+    result
+}
+
+fn with_backup(result: serde_json::Value, backup: Option<String>) -> serde_json::Value {
+    with_field(result, "backup", backup)
+}
+
+fn with_field(
+    mut result: serde_json::Value,
+    key: &str,
+    value: Option<String>,
+) -> serde_json::Value {
+    if let (Some(value), Some(fields)) = (value, result.as_object_mut()) {
+        fields.insert(key.to_string(), json!(value));
+    }
+    result
+}
+
+/// Where patch operations find files and leave their changes.
+pub trait PatchFiles {
+    /// The spelling of `path` to use, with a note when it differs from the patch's.
+    fn correct_case(&self, path: String) -> (String, Option<String>) {
+        (path, None)
+    }
+    /// Keep a copy of `path` before it changes, if this store keeps backups at all.
+    fn back_up(&mut self, _path: &str) -> std::io::Result<Option<String>> {
+        Ok(None)
+    }
+    fn read(&self, path: &str) -> std::io::Result<String>;
+    fn write(&mut self, path: &str, content: &str, trailing_newline: bool) -> std::io::Result<()>;
+    /// Removing a file that is not there succeeds.
+    fn remove(&mut self, path: &str) -> std::io::Result<()>;
+    /// Fails when `from` is missing or `to` is already taken.
+    fn rename(&mut self, from: &str, to: &str) -> std::io::Result<()>;
+}
+
+/// The workspace on disk.
+pub struct Workspace {
+    /// Copy files aside before changing them.
+    pub backups: bool,
+}
+
+impl PatchFiles for Workspace {
+    fn correct_case(&self, path: String) -> (String, Option<String>) {
+        correct_case_if_enabled(path)
+    }
+
+    fn back_up(&mut self, path: &str) -> std::io::Result<Option<String>> {
+        if !self.backups {
+            return Ok(None);
+        }
+        back_up_within_cwd(path)
+    }
+
+    fn read(&self, path: &str) -> std::io::Result<String> {
+        resolve_path_within_cwd(path).and_then(fs::read_to_string)
+    }
+
+    fn write(&mut self, path: &str, content: &str, trailing_newline: bool) -> std::io::Result<()> {
+        write_text_creating_dirs(path, content, trailing_newline)
+    }
+
+    fn remove(&mut self, path: &str) -> std::io::Result<()> {
+        remove_file_if_exists(path)
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> std::io::Result<()> {
+        rename_within_cwd(from, to)
+    }
+}
+
+/// Reads the workspace but keeps every change in memory; `None` marks a deleted file.
+#[derive(Default)]
+pub struct DryRun {
+    changes: BTreeMap<String, Option<String>>,
+}
+
+impl PatchFiles for DryRun {
+    fn correct_case(&self, path: String) -> (String, Option<String>) {
+        correct_case_if_enabled(path)
+    }
+
+    fn read(&self, path: &str) -> std::io::Result<String> {
+        match self.changes.get(path) {
+            Some(Some(text)) => Ok(text.clone()),
+            Some(None) => Err(ErrorKind::NotFound.into()),
+            None => resolve_path_within_cwd(path).and_then(fs::read_to_string),
+        }
+    }
+
+    fn write(&mut self, path: &str, content: &str, trailing_newline: bool) -> std::io::Result<()> {
+        resolve_path_within_cwd(path)?;
+        let content = set_trailing_newline(content, trailing_newline);
+        self.changes.insert(path.to_string(), Some(content));
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &str) -> std::io::Result<()> {
+        resolve_path_within_cwd(path)?;
+        self.changes.insert(path.to_string(), None);
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> std::io::Result<()> {
+        if self.read(to).is_ok() {
+            return Err(ErrorKind::AlreadyExists.into());
+        }
+        resolve_path_within_cwd(to)?;
+        let text = self.read(from)?;
+        self.changes.insert(from.to_string(), None);
+        self.changes.insert(to.to_string(), Some(text));
+        Ok(())
+    }
+}
+
+/// Files held entirely in memory, keyed by path.
+impl PatchFiles for BTreeMap<String, String> {
+    fn read(&self, path: &str) -> std::io::Result<String> {
+        self.get(path)
+            .cloned()
+            .ok_or_else(|| ErrorKind::NotFound.into())
+    }
+
+    fn write(&mut self, path: &str, content: &str, trailing_newline: bool) -> std::io::Result<()> {
+        let content = set_trailing_newline(content, trailing_newline);
+        self.insert(path.to_string(), content);
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &str) -> std::io::Result<()> {
+        BTreeMap::remove(self, path);
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> std::io::Result<()> {
+        if self.contains_key(to) {
+            return Err(ErrorKind::AlreadyExists.into());
+        }
+        let text = BTreeMap::remove(self, from).ok_or(ErrorKind::NotFound)?;
+        self.insert(to.to_string(), text);
+        Ok(())
+    }
+}
+
+/// Write updates whose hunks only partly matched, reporting the failed hunks, when `partial` is set.
+/// Otherwise an update with any failed hunk leaves the file untouched.
+/// With `include_diff`, written updates carry a unified diff of the file before and after.
+pub fn execute_patch_ops(
+    files: &mut impl PatchFiles,
+    ops: Vec<PatchOp>,
+    partial: bool,
+    matching: Matching,
+    include_diff: bool,
+) -> serde_json::Value {
     let mut results = Vec::new();
     for op in ops {
         match op {
@@ -50,53 +309,97 @@ pub fn execute_patch_ops(ops: Vec<PatchOp>) -> serde_json::Value {
                 content,
                 no_newline,
             } => {
-                let res = write_text_creating_dirs(&path, &content, !no_newline);
+                let res = files
+                    .back_up(&path)
+                    .and_then(|backup| files.write(&path, &content, !no_newline).map(|()| backup));
                 match res {
-                    Ok(_) => results.push(json!({ "path": path, "op": "add", "ok": true })),
+                    Ok(backup) => results.push(with_backup(
+                        json!({ "path": path, "op": "add", "ok": true }),
+                        backup,
+                    )),
                     Err(e) => results.push(
                         json!({ "path": path, "op": "add", "ok": false, "error": e.to_string() }),
                     ),
                 }
             }
             PatchOp::Delete { path } => {
-                let res = remove_file_if_exists(&path);
-                match res {
-                    Ok(_) => results.push(json!({ "path": path, "op": "delete", "ok": true })),
-                    Err(e) => results.push(
-                        json!({ "path": path, "op": "delete", "ok": false, "error": e.to_string() }),
-                    ),
-                }
+                let (path, note) = files.correct_case(path);
+                let res = files
+                    .back_up(&path)
+                    .and_then(|backup| files.remove(&path).map(|()| backup));
+                let result = match res {
+                    Ok(backup) => {
+                        with_backup(json!({ "path": path, "op": "delete", "ok": true }), backup)
+                    }
+                    Err(e) => {
+                        json!({ "path": path, "op": "delete", "ok": false, "error": e.to_string() })
+                    }
+                };
+                results.push(with_note(result, &note));
+            }
+            PatchOp::Move { from, to } => {
+                let (from, note) = files.correct_case(from);
+                let result = match files.rename(&from, &to) {
+                    Ok(()) => json!({ "path": to, "from": from, "op": "move", "ok": true }),
+                    Err(e) => {
+                        json!({ "path": to, "from": from, "op": "move", "ok": false, "error": e.to_string() })
+                    }
+                };
+                results.push(with_note(result, &note));
             }
             PatchOp::Update {
                 path,
                 hunks,
-                no_newline,
+                trailing_newline,
             } => {
-                let text0 = match resolve_path_within_cwd(&path).and_then(fs::read_to_string) {
+                let (path, note) = files.correct_case(path);
+                let text0 = match files.read(&path) {
                     Ok(s) => s,
                     Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
                     Err(e) => {
-                        results.push(json!({ "path": path, "op": "update", "ok": false, "error": format!("read: {}", e) }));
+                        results.push(with_note(json!({ "path": path, "op": "update", "ok": false, "error": format!("read: {}", e) }), &note));
                         continue;
                     }
                 };
 
-                match apply_all_hunks(&text0, &hunks) {
-                    Ok(text) => {
-                        match write_text_creating_dirs(&path, &text, !no_newline) {
-                            Ok(_) => results.push(json!({ "path": path, "op": "update", "ok": true })),
-                            Err(e) => results.push(json!({ "path": path, "op": "update", "ok": false, "error": format!("write: {}", e) })),
+                let (text, errs) = apply_all_hunks(&text0, &hunks, matching);
+                let errors = errs
+                    .iter()
+                    .map(|(i, e)| json!({ "hunk": i, "error": e }))
+                    .collect::<Vec<_>>();
+                let applied = (0..hunks.len())
+                    .filter(|i| !errs.iter().any(|(failed, _)| failed == i))
+                    .collect::<Vec<_>>();
+                let result = if errs.is_empty() || (partial && !applied.is_empty()) {
+                    let want_newline = resolve_trailing_newline(trailing_newline, &text0);
+                    let written = files.back_up(&path).and_then(|backup| {
+                        files.write(&path, &text, want_newline).map(|()| backup)
+                    });
+                    match written {
+                        Ok(backup) => {
+                            let result = if errs.is_empty() {
+                                json!({ "path": path, "op": "update", "ok": true })
+                            } else {
+                                json!({
+                                    "path": path,
+                                    "op": "update",
+                                    "ok": false,
+                                    "partial": true,
+                                    "applied": applied,
+                                    "errors": errors,
+                                })
+                            };
+                            let diff = include_diff.then(|| unified_diff(&text0, &text));
+                            with_field(with_backup(result, backup), "diff", diff)
+                        }
+                        Err(e) => {
+                            json!({ "path": path, "op": "update", "ok": false, "error": format!("write: {}", e) })
                         }
                     }
-                    Err(errs) => {
-                        results.push(json!({
-                            "path": path,
-                            "op": "update",
-                            "ok": false,
-                            "errors": errs.iter().map(|(i, e)| json!({ "hunk": i, "error": e })).collect::<Vec<_>>()
-                        }));
-                    }
-                }
+                } else {
+                    json!({ "path": path, "op": "update", "ok": false, "errors": errors })
+                };
+                results.push(with_note(result, &note));
             }
         }
     }
@@ -1,9 +1,12 @@
+use serde::Deserialize;
+
 #[derive(Debug)]
 pub enum PatchOp {
     Update {
         path: String,
         hunks: Vec<Hunk>,
-        no_newline: bool,
+        /// `Some` only when the patch marks the end of file; otherwise the file keeps its state.
+        trailing_newline: Option<bool>,
     },
     Add {
         path: String,
@@ -13,6 +16,21 @@ pub enum PatchOp {
     Delete {
         path: String,
     },
+    Move {
+        from: String,
+        to: String,
+    },
+}
+
+/// How closely a hunk's old lines must agree with the file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Matching {
+    /// Lines agree up to trailing whitespace.
+    #[default]
+    Strict,
+    /// Leading whitespace and blank lines do not count either.
+    Relaxed,
 }
 
 #[derive(Debug, Default)]
@@ -12,6 +12,8 @@ enum Header {
     Update,
     Add,
     Delete,
+    Move,
+    Rename,
 }
 
 pub fn parse_patch_ops(raw: &str) -> Result<Vec<PatchOp>, String> {
@@ -37,11 +39,11 @@ pub fn parse_patch_ops(raw: &str) -> Result<Vec<PatchOp>, String> {
 
         if let Some(path) = parse_header_path(line, Header::Update) {
             i += 1;
-            let (hunks, no_newline) = parse_update_hunks(&lines, &mut i, end)?;
+            let (hunks, trailing_newline) = parse_update_hunks(&lines, &mut i, end)?;
             ops.push(PatchOp::Update {
                 path,
                 hunks,
-                no_newline,
+                trailing_newline,
             });
             continue;
         }
@@ -60,6 +62,11 @@ pub fn parse_patch_ops(raw: &str) -> Result<Vec<PatchOp>, String> {
             ops.push(PatchOp::Delete { path });
             continue;
         }
+        if let Some((from, to)) = parse_move_header(line) {
+            i += 1;
+            ops.push(PatchOp::Move { from, to });
+            continue;
+        }
 
         i += 1;
     }
@@ -77,7 +84,8 @@ pub(crate) fn contains_patch_syntax(s: &str) -> bool {
             line.starts_with("***")
                 && (parse_header_path(line, Header::Update).is_some()
                     || parse_header_path(line, Header::Add).is_some()
-                    || parse_header_path(line, Header::Delete).is_some())
+                    || parse_header_path(line, Header::Delete).is_some()
+                    || parse_move_header(line).is_some())
         })
 }
 
@@ -106,6 +114,8 @@ fn parse_header_path(line: &str, h: Header) -> Option<String> {
         Header::Update => "update file",
         Header::Add => "add file",
         Header::Delete => "delete file",
+        Header::Move => "move file",
+        Header::Rename => "rename file",
     };
     let l_lower = l.to_ascii_lowercase();
     let kw_nospace = kw.replace(' ', "");
@@ -126,11 +136,24 @@ fn parse_header_path(line: &str, h: Header) -> Option<String> {
     }
 }
 
+/// `*** Move File: old -> new`, also spelled `Rename File`.
+fn parse_move_header(line: &str) -> Option<(String, String)> {
+    let paths = parse_header_path(line, Header::Move)
+        .or_else(|| parse_header_path(line, Header::Rename))?;
+    let (from, to) = paths.split_once("->")?;
+    let from = from.trim().trim_matches('"');
+    let to = to.trim().trim_matches('"');
+    if from.is_empty() || to.is_empty() {
+        return None;
+    }
+    Some((from.to_string(), to.to_string()))
+}
+
 fn parse_update_hunks(
     lines: &[&str],
     i: &mut usize,
     end: usize,
-) -> Result<(Vec<Hunk>, bool), String> {
+) -> Result<(Vec<Hunk>, Option<bool>), String> {
     let mut hunks: Vec<Hunk> = Vec::new();
     if *i < end && lines[*i].trim_start().starts_with("```") {
         *i += 1;
@@ -139,6 +162,7 @@ fn parse_update_hunks(
     let mut cur = Hunk::default();
     let mut have_any = false;
     let mut no_newline = false;
+    let mut removed_no_newline = false;
     let mut last_line_survives = false;
 
     while *i < end {
@@ -187,6 +211,8 @@ fn parse_update_hunks(
         } else if is_no_newline_comment_line(raw) {
             if last_line_survives {
                 no_newline = true;
+            } else {
+                removed_no_newline = true;
             }
             last_line_survives = false;
         } else {
@@ -205,7 +231,16 @@ fn parse_update_hunks(
     if *i < end && lines[*i].trim_start().starts_with("```") {
         *i += 1;
     }
-    Ok((hunks, no_newline))
+    // A marker on the new side drops the newline; one only on the old side means the patch
+    // replaced an unterminated last line with a terminated one.
+    let trailing_newline = if no_newline {
+        Some(false)
+    } else if removed_no_newline {
+        Some(true)
+    } else {
+        None
+    };
+    Ok((hunks, trailing_newline))
 }
 
 fn parse_add_block(lines: &[&str], i: &mut usize, end: usize) -> (String, bool) {
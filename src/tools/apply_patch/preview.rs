@@ -2,13 +2,28 @@ use super::model;
 use super::parse_patch_ops;
 use super::parsing;
 
-/// Produce a full diff-like preview for a proposed patch (no truncation).
+/// Produce a full diff-like preview for a proposed patch.
 /// For overwrite mode, returns the full content. For patch mode, returns a
 /// unified diff-style representation across all ops.
+/// Lines longer than `PLEASE_PREVIEW_LINE_CHARS` are cut short so minified files stay reviewable.
 pub fn summarize_patch_for_preview(raw: &str) -> Option<String> {
+    render_preview(raw, crate::config::get().preview_line_chars)
+}
+
+/// Render the preview, keeping at most `line_chars` characters of each line; 0 keeps everything.
+pub(super) fn render_preview(raw: &str, line_chars: usize) -> Option<String> {
+    let push_line = |out: &mut String, marker: Option<char>, line: &str| {
+        out.extend(marker);
+        out.push_str(&clip_line(line, line_chars));
+        out.push('\n');
+    };
     if !parsing::contains_patch_syntax(raw) {
-        // Overwrite mode: show full content as-is
-        return Some(raw.to_string());
+        // Overwrite mode: show full content
+        let mut out = String::new();
+        for l in raw.lines() {
+            push_line(&mut out, None, l);
+        }
+        return Some(out);
     }
 
     let ops = match parse_patch_ops(raw) {
@@ -24,9 +39,7 @@ pub fn summarize_patch_for_preview(raw: &str) -> Option<String> {
                 out.push_str(&format!("+++ {path}\n"));
                 out.push_str("@@\n");
                 for l in content.lines() {
-                    out.push('+');
-                    out.push_str(l);
-                    out.push('\n');
+                    push_line(&mut out, Some('+'), l);
                 }
                 out.push('\n');
             }
@@ -35,6 +48,11 @@ pub fn summarize_patch_for_preview(raw: &str) -> Option<String> {
                 out.push_str("+++ /dev/null\n");
                 out.push_str("@@\n\n");
             }
+            model::PatchOp::Move { from, to } => {
+                out.push_str(&format!("--- {from}\n"));
+                out.push_str(&format!("+++ {to}\n"));
+                out.push_str("(renamed, content unchanged)\n\n");
+            }
             model::PatchOp::Update { path, hunks, .. } => {
                 out.push_str(&format!("--- {path}\n"));
                 out.push_str(&format!("+++ {path}\n"));
@@ -45,27 +63,17 @@ pub fn summarize_patch_for_preview(raw: &str) -> Option<String> {
                         let old = &h.old_lines[i];
                         let newl = &h.new_lines[i];
                         if old == newl {
-                            out.push(' ');
-                            out.push_str(old);
-                            out.push('\n');
+                            push_line(&mut out, Some(' '), old);
                         } else {
-                            out.push('-');
-                            out.push_str(old);
-                            out.push('\n');
-                            out.push('+');
-                            out.push_str(newl);
-                            out.push('\n');
+                            push_line(&mut out, Some('-'), old);
+                            push_line(&mut out, Some('+'), newl);
                         }
                     }
                     for i in n..h.old_lines.len() {
-                        out.push('-');
-                        out.push_str(&h.old_lines[i]);
-                        out.push('\n');
+                        push_line(&mut out, Some('-'), &h.old_lines[i]);
                     }
                     for i in n..h.new_lines.len() {
-                        out.push('+');
-                        out.push_str(&h.new_lines[i]);
-                        out.push('\n');
+                        push_line(&mut out, Some('+'), &h.new_lines[i]);
                     }
                     out.push('\n');
                 }
@@ -74,3 +82,152 @@ pub fn summarize_patch_for_preview(raw: &str) -> Option<String> {
     }
     Some(out)
 }
+
+/// Preview a file about to be created, in the same form as an added file in a patch.
+pub fn summarize_new_file_for_preview(path: &str, content: &str) -> String {
+    let line_chars = crate::config::get().preview_line_chars;
+    let mut out = format!("--- /dev/null\n+++ {path}\n@@\n");
+    for l in content.lines() {
+        out.push('+');
+        out.push_str(&clip_line(l, line_chars));
+        out.push('\n');
+    }
+    out
+}
+
+/// Preview replacing the file at `path` with `content`: a diff against what it holds now,
+/// or the whole content when it does not exist yet.
+pub fn summarize_overwrite_for_preview(path: &str, content: &str) -> String {
+    let current =
+        crate::tools::common::resolve_path_within_cwd(path).and_then(std::fs::read_to_string);
+    let Ok(current) = current else {
+        return summarize_new_file_for_preview(path, content);
+    };
+    let line_chars = crate::config::get().preview_line_chars;
+    let mut out = format!("--- {path}\n+++ {path}\n");
+    for l in unified_diff(&current, content).lines() {
+        out.push_str(&clip_line(l, line_chars));
+        out.push('\n');
+    }
+    out
+}
+
+/// Lines of context around each change in `unified_diff`.
+const DIFF_CONTEXT: usize = 3;
+
+/// Above this many line pairs the changed middle of a file is shown as removed then added
+/// rather than diffed line by line.
+const DIFF_CELLS: usize = 1_000_000;
+
+/// A unified diff of two versions of a file, with `@@ -start,count +start,count @@` headers
+/// numbering lines as they are in each version. Empty when the lines are the same.
+pub(super) fn unified_diff(before: &str, after: &str) -> String {
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+    let script = edit_script(&old, &new);
+
+    // Line numbers in each version just before each step of the script.
+    let mut at = Vec::with_capacity(script.len() + 1);
+    let (mut o, mut n) = (0, 0);
+    for (marker, _) in &script {
+        at.push((o, n));
+        match marker {
+            ' ' => (o, n) = (o + 1, n + 1),
+            '-' => o += 1,
+            _ => n += 1,
+        }
+    }
+    at.push((o, n));
+
+    let changed: Vec<usize> = (0..script.len()).filter(|&i| script[i].0 != ' ').collect();
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &i in &changed {
+        let (from, to) = (
+            i.saturating_sub(DIFF_CONTEXT),
+            (i + 1 + DIFF_CONTEXT).min(script.len()),
+        );
+        match groups.last_mut() {
+            Some(last) if from <= last.1 => last.1 = to,
+            _ => groups.push((from, to)),
+        }
+    }
+
+    let mut out = String::new();
+    for (from, to) in groups {
+        let ((o0, n0), (o1, n1)) = (at[from], at[to]);
+        let start = |line: usize, count: usize| if count == 0 { line } else { line + 1 };
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            start(o0, o1 - o0),
+            o1 - o0,
+            start(n0, n1 - n0),
+            n1 - n0
+        ));
+        for (marker, line) in &script[from..to] {
+            out.push(*marker);
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Each line of either version marked ` `, `-` or `+`, from a longest common subsequence
+/// of the part between the lines both versions start and end with.
+fn edit_script<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(char, &'a str)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut script: Vec<(char, &str)> = old[..prefix].iter().map(|l| (' ', *l)).collect();
+    if a.len() * b.len() > DIFF_CELLS {
+        script.extend(a.iter().map(|l| ('-', *l)));
+        script.extend(b.iter().map(|l| ('+', *l)));
+    } else {
+        // common[i][j]: length of the longest common subsequence of a[i..] and b[j..].
+        let mut common = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                common[i][j] = if a[i] == b[j] {
+                    common[i + 1][j + 1] + 1
+                } else {
+                    common[i + 1][j].max(common[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                script.push((' ', a[i]));
+                (i, j) = (i + 1, j + 1);
+            } else if j == b.len() || (i < a.len() && common[i + 1][j] >= common[i][j + 1]) {
+                script.push(('-', a[i]));
+                i += 1;
+            } else {
+                script.push(('+', b[j]));
+                j += 1;
+            }
+        }
+    }
+    script.extend(old[old.len() - suffix..].iter().map(|l| (' ', *l)));
+    script
+}
+
+/// Keep the first `limit` characters of a line and say how long it really was.
+fn clip_line(line: &str, limit: usize) -> std::borrow::Cow<'_, str> {
+    match line.char_indices().nth(limit) {
+        Some((cut, _)) if limit > 0 => {
+            let total = line.chars().count();
+            format!("{}… (line truncated, {total} chars)", &line[..cut]).into()
+        }
+        _ => line.into(),
+    }
+}
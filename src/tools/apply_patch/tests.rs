@@ -1,60 +1,20 @@
 #![cfg(test)]
 
-use serde_json::json;
 use std::collections::BTreeMap;
 
 use super::applying::{apply_all_hunks, apply_hunk};
-use super::model::{Hunk, PatchOp};
+use super::filesystem::{DryRun, back_up_file, execute_patch_ops, find_path_ignoring_case};
+use super::model::{Hunk, Matching, PatchOp};
 use super::parsing::{contains_patch_syntax, parse_patch_ops};
-use super::text::set_trailing_newline;
+use super::preview::{render_preview, unified_diff};
 
+/// The per-op results of applying `ops` to files held in memory.
 fn execute_patch_ops_in_memory(
     files: &mut BTreeMap<String, String>,
     ops: Vec<PatchOp>,
 ) -> Vec<serde_json::Value> {
-    let mut results = Vec::new();
-
-    for op in ops {
-        match op {
-            PatchOp::Add {
-                path,
-                content,
-                no_newline,
-            } => {
-                let text = set_trailing_newline(&content, !no_newline);
-                files.insert(path.clone(), text);
-                results.push(json!({ "path": path, "op": "add", "ok": true }));
-            }
-            PatchOp::Delete { path } => {
-                files.remove(&path);
-                results.push(json!({ "path": path, "op": "delete", "ok": true }));
-            }
-            PatchOp::Update {
-                path,
-                hunks,
-                no_newline,
-            } => {
-                let before = files.get(&path).cloned().unwrap_or_default();
-                match apply_all_hunks(&before, &hunks) {
-                    Ok(mut text) => {
-                        text = set_trailing_newline(&text, !no_newline);
-                        files.insert(path.clone(), text);
-                        results.push(json!({ "path": path, "op": "update", "ok": true }));
-                    }
-                    Err(errs) => {
-                        results.push(json!({
-                          "path": path,
-                          "op": "update",
-                          "ok": false,
-                          "errors": errs.iter().map(|(i, e)| json!({ "hunk": i, "error": e })).collect::<Vec<_>>()
-                      }));
-                    }
-                }
-            }
-        }
-    }
-
-    results
+    let result = execute_patch_ops(files, ops, false, Matching::Strict, false);
+    result["results"].as_array().cloned().unwrap_or_default()
 }
 
 #[test]
@@ -79,11 +39,12 @@ fn pure_parse_and_apply_update() {
         PatchOp::Update {
             path,
             hunks,
-            no_newline,
+            trailing_newline,
         } => {
             assert_eq!(path, "text.text");
-            assert!(!no_newline);
-            let out = apply_all_hunks("a\n", &hunks).unwrap();
+            assert_eq!(*trailing_newline, None);
+            let (out, errors) = apply_all_hunks("a\n", &hunks, Matching::Strict);
+            assert!(errors.is_empty());
             assert_eq!(out, "b\n");
         }
         _ => panic!("expected update"),
@@ -332,11 +293,10 @@ world
             .any(|r| r["op"] == "delete" && r["ok"] == true)
     );
 
-    // Validate content and trailing newline policy (no newline requested initially,
-    // but update introduced a newline since we didn't carry the no_newline marker
-    // on the final hunk).
+    // Validate content and trailing newline policy: the file was added without a newline,
+    // and the update did not touch the end of file, so it stays without one.
     let text = mem.get("some.text").unwrap();
-    assert!(text.ends_with('\n'));
+    assert!(!text.ends_with('\n'));
     assert!(text.contains("hello, friend"));
 }
 
@@ -367,6 +327,234 @@ fn relaxed_trailing_whitespace_matching() {
         old_lines: vec!["line 1".into(), "line 2".into()],
         new_lines: vec!["line 1x".into(), "line 2y".into()],
     };
-    let out = apply_hunk(before, &h).expect("apply");
+    let out = apply_hunk(before, &h, Matching::Strict).expect("apply");
     assert_eq!(out, "line 1x\nline 2y\n");
 }
+
+#[test]
+fn case_insensitive_lookup_finds_existing_path() {
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let root = std::env::temp_dir().join(format!("please-case-{}-{stamp}", std::process::id()));
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(root.join("src").join("main.rs"), "fn main() {}\n").unwrap();
+
+    let found = find_path_ignoring_case(&root, std::path::Path::new("SRC/Main.rs"));
+    let missing = find_path_ignoring_case(&root, std::path::Path::new("src/lib.rs"));
+    let _ = std::fs::remove_dir_all(&root);
+
+    assert_eq!(found, Some(std::path::Path::new("src").join("main.rs")));
+    assert_eq!(missing, None);
+}
+
+#[test]
+fn partial_application_keeps_matching_hunks_and_reports_the_rest() {
+    let hunks = vec![
+        Hunk {
+            old_lines: vec!["a".into()],
+            new_lines: vec!["A".into()],
+        },
+        Hunk {
+            old_lines: vec!["missing".into()],
+            new_lines: vec!["M".into()],
+        },
+        Hunk {
+            old_lines: vec!["c".into()],
+            new_lines: vec!["C".into()],
+        },
+    ];
+    let (text, errors) = apply_all_hunks("a\nb\nc\n", &hunks, Matching::Strict);
+    assert_eq!(text, "A\nb\nC\n");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, 1);
+}
+
+#[test]
+fn update_keeps_the_existing_trailing_newline_state() {
+    let patch = "*** Begin Patch\n*** Update File: text.text\n@@\n-a\n+b\n*** End Patch\n";
+    for before in ["x\na\n", "x\na"] {
+        let ops = parse_patch_ops(patch).unwrap();
+        let mut files = BTreeMap::from([("text.text".to_string(), before.to_string())]);
+        execute_patch_ops_in_memory(&mut files, ops);
+        let after = files.get("text.text").unwrap();
+        assert_eq!(
+            after.ends_with('\n'),
+            before.ends_with('\n'),
+            "{before:?} -> {after:?}"
+        );
+    }
+}
+
+#[test]
+fn update_drops_the_trailing_newline_only_when_marked() {
+    let patch = "*** Begin Patch\n*** Update File: text.text\n@@\n-a\n+b\n\\ No newline at end of file\n*** End Patch\n";
+    let ops = parse_patch_ops(patch).unwrap();
+    let mut files = BTreeMap::from([("text.text".to_string(), "a\n".to_string())]);
+    execute_patch_ops_in_memory(&mut files, ops);
+    assert_eq!(files.get("text.text").unwrap(), "b");
+}
+
+#[test]
+fn preview_clips_long_lines_on_char_boundaries() {
+    let minified = "é".repeat(5000);
+    let patch = format!(
+        "*** Begin Patch\n*** Update File: app.min.js\n@@\n-{minified}\n+short\n*** End Patch\n"
+    );
+    let preview = render_preview(&patch, 10).unwrap();
+    assert!(preview.contains("-éééééééééé… (line truncated, 5000 chars)\n"));
+    assert!(preview.contains("+short\n"));
+
+    let preview = render_preview(&patch, 0).unwrap();
+    assert!(preview.contains(&format!("-{minified}\n")));
+}
+
+#[test]
+fn dry_runs_see_their_own_changes_but_leave_the_disk_alone() {
+    let dir = format!("please-dry-run-{}", std::process::id());
+    let patch = format!(
+        "*** Begin Patch\n*** Add File: {dir}/new.txt\none\n\
+         *** Update File: {dir}/new.txt\n@@\n-one\n+two\n\
+         *** Update File: {dir}/new.txt\n@@\n-one\n+three\n*** End Patch\n"
+    );
+    let ops = parse_patch_ops(&patch).expect("parse");
+    let result = execute_patch_ops(&mut DryRun::default(), ops, false, Matching::Strict, false);
+    let results = result["results"].as_array().unwrap();
+
+    assert_eq!(results[0]["ok"], true);
+    assert_eq!(results[1]["ok"], true);
+    assert_eq!(results[2]["ok"], false);
+    assert_eq!(results[2]["errors"][0]["hunk"], 0);
+    assert!(!std::path::Path::new(&dir).exists());
+}
+
+#[test]
+fn backups_hold_the_file_as_it_was_before_the_edit() {
+    let dir = std::env::temp_dir().join(format!("please-backup-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("notes.txt");
+    std::fs::write(&path, "before\n").unwrap();
+
+    let first = back_up_file(&path, 42).unwrap();
+    std::fs::write(&path, "after\n").unwrap();
+    let second = back_up_file(&path, 42).unwrap();
+    let missing = back_up_file(&dir.join("new.txt"), 42).unwrap();
+    let first_content = first.as_ref().map(|p| std::fs::read_to_string(p).unwrap());
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert_eq!(first, Some(dir.join("notes.txt.please.bak.42")));
+    assert_eq!(first_content.as_deref(), Some("before\n"));
+    assert_eq!(second, Some(dir.join("notes.txt.please.bak.42-2")));
+    assert_eq!(missing, None);
+}
+
+#[test]
+fn move_headers_parse_with_tolerant_casing_and_spacing() {
+    let patch = "*** Begin Patch\n*** Move File: src/old.rs -> src/new.rs\n***  rename file:  \"a b.txt\"->c.txt\n*** MoveFile: x -> \n*** End Patch\n";
+    let ops = parse_patch_ops(patch).unwrap();
+    let moves = ops
+        .iter()
+        .map(|op| match op {
+            PatchOp::Move { from, to } => (from.as_str(), to.as_str()),
+            other => panic!("unexpected op {other:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(moves, [("src/old.rs", "src/new.rs"), ("a b.txt", "c.txt")]);
+    assert!(contains_patch_syntax("*** Rename File: a -> b"));
+}
+
+#[test]
+fn move_keeps_content_and_refuses_to_overwrite() {
+    let patch = "*** Begin Patch\n*** Move File: old.text -> dir/new.text\n*** Move File: other.text -> dir/new.text\n*** Update File: dir/new.text\n@@\n-kept\n+edited\n*** End Patch\n";
+    let ops = parse_patch_ops(patch).unwrap();
+    let mut files = BTreeMap::from([
+        ("old.text".to_string(), "kept\n".to_string()),
+        ("other.text".to_string(), "other\n".to_string()),
+    ]);
+    let results = execute_patch_ops_in_memory(&mut files, ops);
+    assert_eq!(results[0]["op"], "move");
+    assert_eq!(results[0]["ok"], true);
+    assert_eq!(results[0]["from"], "old.text");
+    assert_eq!(results[1]["ok"], false);
+    assert_eq!(results[2]["ok"], true);
+    assert_eq!(
+        files,
+        BTreeMap::from([
+            ("dir/new.text".to_string(), "edited\n".to_string()),
+            ("other.text".to_string(), "other\n".to_string()),
+        ])
+    );
+}
+
+#[test]
+fn move_preview_names_both_paths() {
+    let preview = render_preview(
+        "*** Begin Patch\n*** Move File: a.rs -> b.rs\n*** End Patch\n",
+        0,
+    )
+    .unwrap();
+    assert!(preview.starts_with("--- a.rs\n+++ b.rs\n"));
+}
+
+#[test]
+fn indentation_drift_needs_relaxed_matching() {
+    let before = "fn main() {\n    if ready {\n\n        go();\n    }\n}\n";
+    let h = Hunk {
+        old_lines: vec!["if ready {".into(), "    go();".into(), "}".into()],
+        new_lines: vec![
+            "if ready {".into(),
+            "    go();".into(),
+            "    done();".into(),
+            "}".into(),
+        ],
+    };
+    let strict = apply_hunk(before, &h, Matching::Strict).unwrap_err();
+    assert!(strict.contains("relaxed"), "{strict}");
+
+    let out = apply_hunk(before, &h, Matching::Relaxed).expect("apply");
+    assert_eq!(
+        out,
+        "fn main() {\n    if ready {\n        go();\n        done();\n    }\n}\n"
+    );
+}
+
+#[test]
+fn missing_hunks_point_at_the_closest_lines() {
+    let before = "a\nb\nc\nd\n";
+    let h = Hunk {
+        old_lines: vec!["c".into(), "x".into()],
+        new_lines: vec!["C".into()],
+    };
+    let error = apply_hunk(before, &h, Matching::Relaxed).unwrap_err();
+    assert!(
+        error.ends_with("closest is at line 3, where 1 of 2 lines agree"),
+        "{error}"
+    );
+}
+
+#[test]
+fn unified_diff_numbers_lines_as_in_the_file() {
+    let before = (1..=20).map(|n| format!("line {n}\n")).collect::<String>();
+    let after = before
+        .replace("line 10\n", "line ten\nline ten and a half\n")
+        .replace("line 18\n", "");
+    assert_eq!(
+        unified_diff(&before, &after),
+        "@@ -7,7 +7,8 @@\n line 7\n line 8\n line 9\n-line 10\n+line ten\n+line ten and a half\n line 11\n line 12\n line 13\n\
+         @@ -15,6 +16,5 @@\n line 15\n line 16\n line 17\n-line 18\n line 19\n line 20\n"
+    );
+    assert_eq!(unified_diff(&before, &before), "");
+}
+
+#[test]
+fn update_results_carry_a_diff_of_the_matched_location() {
+    let patch = "*** Begin Patch\n*** Update File: list.text\n@@\n c\n-d\n+D\n*** End Patch\n";
+    let ops = parse_patch_ops(patch).unwrap();
+    let mut files = BTreeMap::from([("list.text".to_string(), "a\nb\nc\nd\ne\n".to_string())]);
+    let result = execute_patch_ops(&mut files, ops, false, Matching::Strict, true);
+    assert_eq!(
+        result["results"][0]["diff"],
+        "@@ -1,5 +1,5 @@\n a\n b\n c\n-d\n+D\n e\n"
+    );
+}
@@ -10,6 +10,12 @@ pub fn set_trailing_newline(s: &str, want_newline: bool) -> String {
     t
 }
 
+/// Keep the file's own trailing-newline state unless the patch asked for one explicitly.
+/// Empty or missing files get a newline, like added files do.
+pub fn resolve_trailing_newline(requested: Option<bool>, before: &str) -> bool {
+    requested.unwrap_or(before.is_empty() || before.ends_with('\n'))
+}
+
 pub fn find_lines_window(before: &[&str], old: &[&str]) -> Option<(usize, usize)> {
     if old.is_empty() || before.len() < old.len() {
         return None;
@@ -29,6 +35,71 @@ fn eq_line_relaxed(a: &str, b: &str) -> bool {
     a.trim_end() == b.trim_end()
 }
 
+/// Like `find_lines_window`, but lines agree up to all surrounding whitespace and blank lines
+/// on either side are skipped. Blank lines at the ends of `old` take in blank lines around the match.
+pub fn find_lines_window_ignoring_indent(before: &[&str], old: &[&str]) -> Option<(usize, usize)> {
+    let wanted: Vec<&str> = old
+        .iter()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .collect();
+    let first = *wanted.first()?;
+    'outer: for start in 0..before.len() {
+        if before[start].trim() != first {
+            continue;
+        }
+        let mut at = start;
+        for line in &wanted {
+            while at < before.len() && before[at].trim().is_empty() {
+                at += 1;
+            }
+            if at == before.len() || before[at].trim() != *line {
+                continue 'outer;
+            }
+            at += 1;
+        }
+        let leading = old.iter().take_while(|l| l.trim().is_empty()).count();
+        let trailing = old.iter().rev().take_while(|l| l.trim().is_empty()).count();
+        let start = start
+            - before[..start]
+                .iter()
+                .rev()
+                .take(leading)
+                .take_while(|l| l.trim().is_empty())
+                .count();
+        let end = at
+            + before[at..]
+                .iter()
+                .take(trailing)
+                .take_while(|l| l.trim().is_empty())
+                .count();
+        return Some((start, end));
+    }
+    None
+}
+
+/// Where in `before` the lines most like `old` begin, and how many of them agree
+/// up to surrounding whitespace. `None` when no line agrees at all.
+pub fn closest_window(before: &[&str], old: &[&str]) -> Option<(usize, usize)> {
+    let span = old.len().min(before.len());
+    (0..=before.len() - span)
+        .map(|start| {
+            let agree = old
+                .iter()
+                .zip(&before[start..start + span])
+                .filter(|(a, b)| !a.trim().is_empty() && a.trim() == b.trim())
+                .count();
+            (start, agree)
+        })
+        .filter(|&(_, agree)| agree > 0)
+        .max_by_key(|&(start, agree)| (agree, std::cmp::Reverse(start)))
+}
+
+/// The whitespace a line starts with.
+pub fn indent_of(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
 pub fn preview(s: &str) -> String {
     let s = s.replace('\n', "\\n");
     if s.len() > 160 {
@@ -0,0 +1,29 @@
+use super::common::{Param, ParamType, Stride};
+use serde::Deserialize;
+
+pub const NAME: &str = "ask_user";
+
+#[derive(Deserialize)]
+pub struct Args {
+    question: String,
+}
+
+/// The CLI answers this call itself by prompting the user; reaching here means nobody can.
+pub async fn call(args: Args, _stride: Stride) -> serde_json::Value {
+    serde_json::json!({
+        "error": format!("nobody can answer {:?} here; proceed on your best assumption", args.question)
+    })
+}
+
+pub fn spec() -> (&'static str, &'static str, Vec<Param>) {
+    (
+        NAME,
+        "Ask the user a question and wait for the reply before going on. Use it only when a wrong guess would waste real work.",
+        vec![Param {
+            name: "question",
+            desc: "One short, specific question",
+            param_type: ParamType::String,
+            required: true,
+        }],
+    )
+}
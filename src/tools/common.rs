@@ -57,6 +57,10 @@ pub enum ParamType {
     Number,
     #[allow(dead_code)]
     Boolean,
+    /// A list of strings, such as an argument vector.
+    Array,
+    /// An object of names to string values, such as environment variables.
+    Object,
 }
 
 #[derive(Clone)]
@@ -68,11 +72,10 @@ pub struct Param {
 }
 
 /// Anything that can be called with a `serde_json::Value` payload and a stride.
-pub type AsyncFn = Box<
-    dyn Fn(serde_json::Value, Stride) -> Pin<Box<dyn Future<Output = serde_json::Value> + Send>>
-        + Send
-        + Sync,
->;
+pub type AsyncFn = Box<dyn Fn(serde_json::Value, Stride) -> ToolFuture + Send + Sync>;
+
+/// A tool call in progress.
+pub type ToolFuture = Pin<Box<dyn Future<Output = serde_json::Value> + Send>>;
 
 /// Adapt a typed async handler to an LLM/tool-call friendly `Fn(Value, Stride) -> Future<Value>`.
 /// The stride carries shared turn state and optional live output for this call.
@@ -121,6 +124,12 @@ where
     })
 }
 
+/// Build output and dependency directories, skipped by directory walks where no `.gitignore` says
+/// what to leave out. Names like `lib` or `build` are left alone, as they often hold sources.
+pub fn is_excluded_dir(name: &str) -> bool {
+    matches!(name, "target" | "node_modules" | "dist")
+}
+
 /// Resolve a user-supplied path to a relative path confined to the current working
 /// directory ("workspace").
 ///
@@ -0,0 +1,87 @@
+use super::common::{Param, ParamType, Stride, resolve_path_within_cwd};
+use serde::Deserialize;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+pub const NAME: &str = "create_file";
+
+#[derive(Deserialize)]
+pub struct Args {
+    path: String,
+    content: String,
+}
+
+pub async fn call(args: Args, _stride: Stride) -> serde_json::Value {
+    match create_new(&args.path, &args.content) {
+        Ok(()) => serde_json::json!({ "ok": true, "path": args.path }),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => serde_json::json!({
+            "error": format!("{} already exists; edit it with apply_patch instead", args.path)
+        }),
+        Err(e) => serde_json::json!({ "error": e.to_string() }),
+    }
+}
+
+fn create_new(path: &str, content: &str) -> std::io::Result<()> {
+    let rel = resolve_path_within_cwd(path)?; // sanitized relative path
+    write_new_file(&rel, content)
+}
+
+/// Write `content` to a file that must not exist yet, creating parent directories as needed.
+fn write_new_file(path: &Path, content: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    file.write_all(content.as_bytes())
+}
+
+pub fn spec() -> (&'static str, &'static str, Vec<Param>) {
+    (
+        NAME,
+        "Create a new file with the given content; fails if the file already exists",
+        vec![
+            Param {
+                name: "path",
+                desc: "Relative path of the file to create",
+                param_type: ParamType::String,
+                required: true,
+            },
+            Param {
+                name: "content",
+                desc: "Full content of the new file, written verbatim",
+                param_type: ParamType::String,
+                required: true,
+            },
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn existing_files_are_never_overwritten() {
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root =
+            std::env::temp_dir().join(format!("please-create-{}-{stamp}", std::process::id()));
+        let path = root.join("src").join("new.txt");
+
+        write_new_file(&path, "first").unwrap();
+        let again = write_new_file(&path, "second").unwrap_err();
+        let content = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(again.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(content, "first");
+    }
+}
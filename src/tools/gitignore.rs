@@ -0,0 +1,229 @@
+//! Enough of `.gitignore` to keep listings to what the repository tracks: globs with `*`, `?`,
+//! `**` and `[...]`, rules anchored by a `/`, directory-only rules and `!` negation.
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+struct Rule {
+    glob: String,
+    negated: bool,
+    dir_only: bool,
+    /// Matched against the whole path below the ignore file rather than just the name.
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let glob = line.trim_start_matches('/');
+        if glob.is_empty() {
+            return None;
+        }
+        Some(Self {
+            glob: glob.to_string(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// `rel` is the path below the ignore file's directory, separated by `/`.
+    fn matches(&self, rel: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let subject = if self.anchored {
+            rel
+        } else {
+            rel.rsplit('/').next().unwrap_or(rel)
+        };
+        glob(self.glob.as_bytes(), subject.as_bytes())
+    }
+}
+
+/// `*` and `?` stay within one path component; `**` spans any number of them.
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            if let Some(after) = rest.strip_prefix(b"/")
+                && glob(after, text)
+            {
+                return true;
+            }
+            (0..=text.len()).any(|at| glob(rest, &text[at..]))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&at| at == 0 || text[at - 1] != b'/')
+            .any(|at| glob(rest, &text[at..])),
+        [b'?', rest @ ..] => text.first().is_some_and(|&c| c != b'/') && glob(rest, &text[1..]),
+        [b'[', class @ ..] if class.contains(&b']') => {
+            let end = class.iter().position(|&c| c == b']').unwrap_or_default();
+            let Some(&c) = text.first() else {
+                return false;
+            };
+            in_class(&class[..end], c) && glob(&class[end + 1..], &text[1..])
+        }
+        [c, rest @ ..] => text.first() == Some(c) && glob(rest, &text[1..]),
+    }
+}
+
+/// Whether `c` is in a bracket expression like `cod`, `a-z` or `!0-9`.
+fn in_class(class: &[u8], c: u8) -> bool {
+    let (negated, class) = match class {
+        [b'!' | b'^', rest @ ..] => (true, rest),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut at = 0;
+    while at < class.len() {
+        if at + 2 < class.len() && class[at + 1] == b'-' {
+            found |= (class[at]..=class[at + 2]).contains(&c);
+            at += 3;
+        } else {
+            found |= class[at] == c;
+            at += 1;
+        }
+    }
+    found != negated && c != b'/'
+}
+
+/// The `.gitignore` files that apply while walking down from the workspace root.
+pub(super) struct Gitignores {
+    root: PathBuf,
+    /// Each directory entered, relative to the root, with the rules of its ignore file.
+    files: Vec<(PathBuf, Vec<Rule>)>,
+}
+
+impl Gitignores {
+    /// Rules for listing `dir`: those of the root and of every directory down to `dir`.
+    pub(super) fn for_dir(root: &Path, dir: &Path) -> Self {
+        let mut ignores = Self {
+            root: root.to_path_buf(),
+            files: Vec::new(),
+        };
+        let mut cur = PathBuf::new();
+        ignores.enter(&cur);
+        for part in normal(dir).components() {
+            cur.push(part);
+            ignores.enter(&cur);
+        }
+        ignores
+    }
+
+    /// Take in the ignore file of `dir`, if any, until the matching `leave`.
+    pub(super) fn enter(&mut self, dir: &Path) {
+        let dir = normal(dir);
+        let rules = fs::read_to_string(self.root.join(&dir).join(".gitignore"))
+            .map(|text| text.lines().filter_map(Rule::parse).collect())
+            .unwrap_or_default();
+        self.files.push((dir, rules));
+    }
+
+    pub(super) fn leave(&mut self) {
+        self.files.pop();
+    }
+
+    /// Whether `path`, relative to the root, is ignored. Deeper files and later lines win.
+    pub(super) fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let path = normal(path);
+        if path.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+        let mut ignored = false;
+        for (dir, rules) in &self.files {
+            let Ok(rel) = path.strip_prefix(dir) else {
+                continue;
+            };
+            let rel = rel
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            for rule in rules {
+                if rule.matches(&rel, is_dir) {
+                    ignored = !rule.negated;
+                }
+            }
+        }
+        ignored
+    }
+}
+
+/// `path` without `.` components, so `./src` and `src` compare equal.
+fn normal(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|part| matches!(part, Component::Normal(_)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_follow_git_semantics() {
+        assert!(glob(b"*.log", b"debug.log"));
+        assert!(!glob(b"*.log", b"logs/debug.log"));
+        assert!(glob(b"**/cache", b"a/b/cache"));
+        assert!(glob(b"**/cache", b"cache"));
+        assert!(glob(b"docs/**/*.md", b"docs/a/b/c.md"));
+        assert!(glob(b"*.py[cod]", b"x.pyc"));
+        assert!(!glob(b"*.py[!cod]", b"x.pyc"));
+        assert!(glob(b"file?.txt", b"file1.txt"));
+    }
+
+    #[test]
+    fn ignore_files_apply_relative_to_their_directory() {
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root =
+            std::env::temp_dir().join(format!("please-gitignore-{}-{stamp}", std::process::id()));
+        fs::create_dir_all(root.join("app").join("static")).unwrap();
+        fs::write(
+            root.join(".gitignore"),
+            "# build output\ntarget/\n*.log\n!keep.log\n/secrets.env\n",
+        )
+        .unwrap();
+        fs::write(root.join("app").join(".gitignore"), "static/\n").unwrap();
+
+        let mut ignores = Gitignores::for_dir(&root, Path::new("."));
+        let top = [
+            ignores.is_ignored(Path::new("./target"), true),
+            ignores.is_ignored(Path::new("target"), false),
+            ignores.is_ignored(Path::new("debug.log"), false),
+            ignores.is_ignored(Path::new("keep.log"), false),
+            ignores.is_ignored(Path::new("secrets.env"), false),
+            ignores.is_ignored(Path::new(".git"), true),
+        ];
+        ignores.enter(Path::new("app"));
+        let nested = [
+            ignores.is_ignored(Path::new("app/static"), true),
+            ignores.is_ignored(Path::new("app/secrets.env"), false),
+            ignores.is_ignored(Path::new("app/trace.log"), false),
+        ];
+        ignores.leave();
+        let after = ignores.is_ignored(Path::new("static"), true);
+        let from_app =
+            Gitignores::for_dir(&root, Path::new("app")).is_ignored(Path::new("app/static"), true);
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(top, [true, false, true, false, true, true]);
+        assert_eq!(nested, [true, false, true]);
+        assert!(!after);
+        assert!(from_app);
+    }
+}
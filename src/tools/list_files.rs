@@ -1,4 +1,5 @@
-use super::common::{Param, ParamType, Stride, resolve_path_within_cwd};
+use super::common::{Param, ParamType, Stride, is_excluded_dir, resolve_path_within_cwd};
+use super::gitignore::Gitignores;
 use serde::Deserialize;
 use std::fs;
 use std::path::Path;
@@ -9,6 +10,12 @@ pub struct Args {
     path: String,
     #[serde(default = "default_depth")]
     max_depth: usize,
+    /// Leave out what `.gitignore` files ignore, instead of a fixed set of build directories.
+    #[serde(default = "default_true")]
+    respect_gitignore: bool,
+    /// Return `{ path, kind, size_bytes, modified }` objects instead of bare paths.
+    #[serde(default)]
+    detailed: bool,
 }
 
 fn default_dot() -> String {
@@ -19,6 +26,10 @@ fn default_depth() -> usize {
     0
 }
 
+fn default_true() -> bool {
+    true
+}
+
 pub async fn call(args: Args, _stride: Stride) -> serde_json::Value {
     let root = match resolve_path_within_cwd(&args.path) {
         Ok(p) => p,
@@ -28,22 +39,16 @@ pub async fn call(args: Args, _stride: Stride) -> serde_json::Value {
         return serde_json::json!({ "error": format!("path does not exist: {}", root.display()) });
     }
 
-    let mut out: Vec<String> = Vec::new();
+    let mut out: Vec<(String, fs::Metadata)> = Vec::new();
     let max_depth = args.max_depth;
 
-    fn is_excluded_dir(name: &str) -> bool {
-        matches!(
-            name,
-            "target" | "node_modules" | "dist" | "build" | "lib" | "out"
-        )
-    }
-
     fn walk(
         cur: &Path,
         base: &Path,
         depth: usize,
         max_depth: usize,
-        out: &mut Vec<String>,
+        ignores: &mut Option<Gitignores>,
+        out: &mut Vec<(String, fs::Metadata)>,
     ) -> std::io::Result<()> {
         if depth > max_depth {
             return Ok(());
@@ -51,21 +56,29 @@ pub async fn call(args: Args, _stride: Stride) -> serde_json::Value {
         for entry in fs::read_dir(cur)? {
             let entry = entry?;
             let path = entry.path();
-            if path.is_dir() {
-                let name = entry.file_name();
-                let name = name.to_string_lossy();
-                if is_excluded_dir(&name) {
-                    continue;
-                }
+            let is_dir = path.is_dir();
+            let skip = match ignores {
+                Some(ignores) => ignores.is_ignored(&path, is_dir),
+                None => is_dir && is_excluded_dir(&entry.file_name().to_string_lossy()),
+            };
+            if skip {
+                continue;
             }
             let rel = path.strip_prefix(base).unwrap_or(&path).to_path_buf();
             let mut s = rel.display().to_string();
             if path.is_dir() && !s.ends_with('/') {
                 s.push('/');
             }
-            out.push(s);
-            if path.is_dir() {
-                walk(&path, base, depth + 1, max_depth, out)?;
+            out.push((s, entry.metadata()?));
+            if is_dir && depth < max_depth {
+                if let Some(ignores) = ignores {
+                    ignores.enter(&path);
+                }
+                let walked = walk(&path, base, depth + 1, max_depth, ignores, out);
+                if let Some(ignores) = ignores {
+                    ignores.leave();
+                }
+                walked?;
             }
         }
         Ok(())
@@ -75,10 +88,43 @@ pub async fn call(args: Args, _stride: Stride) -> serde_json::Value {
     } else {
         root.parent().unwrap_or(Path::new(".")).to_path_buf()
     };
-    if let Err(e) = walk(&root, &base, 0, max_depth, &mut out) {
+    // Without git, the fixed set of build directories stands in for ignore rules.
+    let is_repo = Path::new(".git").exists() || Path::new(".gitignore").is_file();
+    let mut ignores =
+        (args.respect_gitignore && is_repo).then(|| Gitignores::for_dir(Path::new("."), &base));
+    if let Err(e) = walk(&root, &base, 0, max_depth, &mut ignores, &mut out) {
         return serde_json::json!({ "error": e.to_string() });
     }
-    serde_json::json!(out)
+    if args.detailed {
+        let detailed = out
+            .iter()
+            .map(|(path, metadata)| describe(path, metadata))
+            .collect::<Vec<_>>();
+        return serde_json::json!(detailed);
+    }
+    serde_json::json!(out.into_iter().map(|(path, _)| path).collect::<Vec<_>>())
+}
+
+/// One listed entry with what its metadata says about it.
+fn describe(path: &str, metadata: &fs::Metadata) -> serde_json::Value {
+    let kind = if metadata.is_symlink() {
+        "symlink"
+    } else if metadata.is_dir() {
+        "dir"
+    } else {
+        "file"
+    };
+    let modified = metadata.modified().ok().and_then(|time| {
+        time::OffsetDateTime::from(time)
+            .format(&time::format_description::well_known::Rfc3339)
+            .ok()
+    });
+    serde_json::json!({
+        "path": path,
+        "kind": kind,
+        "size_bytes": metadata.len(),
+        "modified": modified,
+    })
 }
 
 pub fn spec() -> (&'static str, &'static str, Vec<Param>) {
@@ -98,6 +144,48 @@ pub fn spec() -> (&'static str, &'static str, Vec<Param>) {
                 param_type: ParamType::Number,
                 required: false,
             },
+            Param {
+                name: "respect_gitignore",
+                desc: "Leave out files that .gitignore ignores; default true",
+                param_type: ParamType::Boolean,
+                required: false,
+            },
+            Param {
+                name: "detailed",
+                desc: "Return path, kind, size_bytes and modified for each entry; default false",
+                param_type: ParamType::Boolean,
+                required: false,
+            },
         ],
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detailed_entries_carry_kind_size_and_time() {
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("please-list-{}-{stamp}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("notes.txt"), "twelve bytes").unwrap();
+
+        let file = describe("notes.txt", &fs::metadata(root.join("notes.txt")).unwrap());
+        let dir = describe("src/", &fs::metadata(root.join("src")).unwrap());
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(file["path"], "notes.txt");
+        assert_eq!(file["kind"], "file");
+        assert_eq!(file["size_bytes"], 12);
+        let modified = file["modified"].as_str().unwrap();
+        assert!(
+            modified.starts_with("20") && modified.contains('T'),
+            "{modified}"
+        );
+        assert_eq!(dir["kind"], "dir");
+    }
+}
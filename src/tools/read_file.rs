@@ -2,36 +2,94 @@ use super::common::{Param, ParamType, Stride, resolve_path_within_cwd};
 use serde::Deserialize;
 use std::io::Read;
 
+pub const NAME: &str = "read_file";
+
 #[derive(Deserialize)]
 pub struct Args {
     path: String,
     #[serde(default = "default_max_bytes")]
     max_bytes: usize,
+    /// First line to return, 1-based
+    #[serde(default)]
+    start_line: Option<usize>,
+    /// Last line to return, inclusive
+    #[serde(default)]
+    end_line: Option<usize>,
+    /// Return binary files as lossy text anyway
+    #[serde(default)]
+    force: bool,
 }
 
 fn default_max_bytes() -> usize {
     512 * 1024
 }
 
+/// Bytes looked at to tell binary files from text.
+const SNIFF_BYTES: usize = 8 * 1024;
+
+/// Whether the start of a file looks binary: any NUL byte, or more than one byte in ten a control
+/// character that text does not use. Other encodings than UTF-8 still count as text.
+fn looks_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(SNIFF_BYTES)];
+    if sample.contains(&0) {
+        return true;
+    }
+    let control = sample
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    control * 10 > sample.len()
+}
+
 pub async fn call(args: Args, _stride: Stride) -> serde_json::Value {
-    let res = (|| -> Result<String, String> {
+    let res = (|| -> Result<serde_json::Value, String> {
         let rel = resolve_path_within_cwd(&args.path).map_err(|e| e.to_string())?;
         let file = std::fs::File::open(rel).map_err(|e| e.to_string())?;
+        let size = file.metadata().map_err(|e| e.to_string())?.len();
         let mut buf: Vec<u8> = Vec::with_capacity(std::cmp::min(args.max_bytes, 1024 * 1024));
         let mut limited = std::io::Read::take(file, args.max_bytes as u64);
         limited.read_to_end(&mut buf).map_err(|e| e.to_string())?;
-        Ok(String::from_utf8_lossy(&buf).to_string())
+        if !args.force && looks_binary(&buf) {
+            return Ok(serde_json::json!({ "error": "binary file", "size_bytes": size }));
+        }
+        let text = String::from_utf8_lossy(&buf).to_string();
+        if args.start_line.is_none() && args.end_line.is_none() {
+            return Ok(serde_json::json!(text));
+        }
+        let whole = size <= args.max_bytes as u64;
+        Ok(serde_json::json!(line_range(
+            &text,
+            args.start_line,
+            args.end_line,
+            whole
+        )))
     })();
 
     match res {
-        Ok(s) => serde_json::json!(s),
+        Ok(value) => value,
         Err(e) => serde_json::json!({ "error": e }),
     }
 }
 
+/// The requested lines under a header like `lines 40-80 of 1200`, clamped to the lines there are.
+/// Lines are counted within the bytes read, so a file cut by `max_bytes` has "at least" that many.
+fn line_range(text: &str, start: Option<usize>, end: Option<usize>, whole: bool) -> String {
+    let lines = text.split_inclusive('\n').collect::<Vec<_>>();
+    let total = lines.len();
+    let of = if whole { "of" } else { "of at least" };
+    if total == 0 {
+        return format!("lines 0-0 {of} 0\n");
+    }
+    let start = start.unwrap_or(1).clamp(1, total);
+    let end = end.unwrap_or(total).clamp(start, total);
+    let mut out = format!("lines {start}-{end} {of} {total}\n");
+    out.extend(lines[start - 1..end].iter().copied());
+    out
+}
+
 pub fn spec() -> (&'static str, &'static str, Vec<Param>) {
     (
-        "read_file",
+        NAME,
         "Read a file's content with a byte limit",
         vec![
             Param {
@@ -46,6 +104,65 @@ pub fn spec() -> (&'static str, &'static str, Vec<Param>) {
                 param_type: ParamType::Number,
                 required: false,
             },
+            Param {
+                name: "start_line",
+                desc: "First line to return, 1-based; with end_line, returns only that range",
+                param_type: ParamType::Number,
+                required: false,
+            },
+            Param {
+                name: "end_line",
+                desc: "Last line to return, inclusive; defaults to the last line",
+                param_type: ParamType::Number,
+                required: false,
+            },
+            Param {
+                name: "force",
+                desc: "Return a binary file as lossy text instead of refusing; default false",
+                param_type: ParamType::Boolean,
+                required: false,
+            },
         ],
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_files_are_told_apart_from_text() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x10";
+        assert!(looks_binary(png));
+        let object = [0x7fu8, b'E', b'L', b'F', 2, 1, 1, 3, 4, 5, 6, 7, 8, 2, 1];
+        assert!(looks_binary(&object));
+
+        let utf8 = "// Größe in Bytes — 大小\n\tfn size() -> u64 { 0 }\r\n\x1b[1mbold\x1b[0m\n";
+        assert!(!looks_binary(utf8.as_bytes()));
+        assert!(!looks_binary(b""));
+        let latin1 = b"caf\xe9 cr\xe8me\n";
+        assert!(!looks_binary(latin1));
+    }
+
+    #[test]
+    fn line_ranges_are_clamped_and_headed() {
+        let text = "one\ntwo\nthree\nfour";
+        assert_eq!(
+            line_range(text, Some(2), Some(3), true),
+            "lines 2-3 of 4\ntwo\nthree\n"
+        );
+        assert_eq!(
+            line_range(text, Some(3), Some(99), false),
+            "lines 3-4 of at least 4\nthree\nfour"
+        );
+        assert_eq!(
+            line_range(text, Some(0), Some(1), true),
+            "lines 1-1 of 4\none\n"
+        );
+        assert_eq!(
+            line_range(text, Some(9), None, true),
+            "lines 4-4 of 4\nfour"
+        );
+        assert_eq!(line_range("", Some(5), None, true), "lines 0-0 of 0\n");
+    }
+}
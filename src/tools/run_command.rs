@@ -5,7 +5,7 @@ use std::collections::HashMap;
 use std::process::{ExitStatus, Stdio};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::Mutex as AsyncMutex;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::task::JoinHandle;
@@ -13,6 +13,7 @@ use tokio::task::JoinHandle;
 const DEFAULT_COMMAND_WAIT: Duration = Duration::from_secs(40);
 const INTERRUPT_GRACE: Duration = Duration::from_secs(3);
 const OUTPUT_DRAIN_GRACE: Duration = Duration::from_secs(1);
+const DEFAULT_OUTPUT_BYTES: usize = 64 * 1024;
 const MAX_CAPTURE_BYTES: usize = 256 * 1024;
 const MAX_LIVE_BYTES: usize = 1024 * 1024;
 pub const NAME: &str = "run_command";
@@ -25,40 +26,172 @@ pub struct Args {
     /// Seconds to wait before returning control to the model.
     #[serde(default)]
     wait_seconds: Option<f64>,
+    /// Seconds the command may run in all before it is stopped, across later waits as well.
+    #[serde(default)]
+    timeout_seconds: Option<f64>,
+    /// Bytes of each stream to return; the rest is counted but dropped.
+    #[serde(default)]
+    max_output_bytes: Option<usize>,
+    /// Which end of a stream to keep when it runs over.
+    #[serde(default)]
+    keep: Keep,
+    /// Variables set for the command on top of the inherited environment.
+    #[serde(default)]
+    env: HashMap<String, String>,
+    /// Text written to the command's stdin, which is closed afterwards.
+    #[serde(default)]
+    stdin: Option<String>,
+}
+
+/// What a command is given besides its argv.
+#[derive(Debug, Default)]
+struct CommandInput {
+    env: HashMap<String, String>,
+    /// Without it, stdin is `/dev/null`.
+    stdin: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Keep {
+    #[default]
+    Head,
+    Tail,
+}
+
+/// How much of each stream a result carries, and which end of it.
+#[derive(Debug, Clone, Copy)]
+struct CaptureLimit {
+    max_bytes: usize,
+    keep: Keep,
+}
+
+impl Default for CaptureLimit {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_OUTPUT_BYTES,
+            keep: Keep::Head,
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 struct CapturedOutput {
     bytes: Vec<u8>,
     omitted: usize,
+    limit: CaptureLimit,
 }
 
 impl CapturedOutput {
+    fn new(limit: CaptureLimit) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
     fn push(&mut self, chunk: &[u8]) {
-        let remaining = MAX_CAPTURE_BYTES.saturating_sub(self.bytes.len());
-        let kept = remaining.min(chunk.len());
-        self.bytes.extend_from_slice(&chunk[..kept]);
-        self.omitted += chunk.len() - kept;
+        match self.limit.keep {
+            Keep::Head => {
+                let remaining = self.limit.max_bytes.saturating_sub(self.bytes.len());
+                let kept = remaining.min(chunk.len());
+                self.bytes.extend_from_slice(&chunk[..kept]);
+                self.omitted += chunk.len() - kept;
+            }
+            Keep::Tail => {
+                self.bytes.extend_from_slice(chunk);
+                let excess = self.bytes.len().saturating_sub(self.limit.max_bytes);
+                self.bytes.drain(..excess);
+                self.omitted += excess;
+            }
+        }
     }
 
-    fn text(&self) -> String {
-        String::from_utf8_lossy(&self.bytes).to_string()
+    /// The kept bytes as text, and how many bytes were left out in all.
+    /// A character split by the cut is dropped whole rather than shown as a replacement.
+    fn text(&self) -> (String, usize) {
+        let kept = match self.limit.keep {
+            _ if self.omitted == 0 => &self.bytes[..],
+            Keep::Head => trim_split_char_end(&self.bytes),
+            Keep::Tail => trim_split_char_start(&self.bytes),
+        };
+        let omitted = self.omitted + self.bytes.len() - kept.len();
+        (String::from_utf8_lossy(kept).to_string(), omitted)
+    }
+}
+
+fn is_continuation_byte(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+/// Drop continuation bytes at the start whose leading byte was cut off.
+fn trim_split_char_start(bytes: &[u8]) -> &[u8] {
+    let split = bytes
+        .iter()
+        .take(3)
+        .take_while(|&&byte| is_continuation_byte(byte))
+        .count();
+    &bytes[split..]
+}
+
+/// Drop a character at the end that lost some of its continuation bytes.
+fn trim_split_char_end(bytes: &[u8]) -> &[u8] {
+    let Some(lead) = (bytes.len().saturating_sub(4)..bytes.len())
+        .rev()
+        .find(|&at| !is_continuation_byte(bytes[at]))
+    else {
+        return bytes;
+    };
+    let width = match bytes[lead] {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 1,
+    };
+    if bytes.len() - lead < width {
+        &bytes[..lead]
+    } else {
+        bytes
+    }
+}
+
+/// The requested share of each stream, within the hard ceiling.
+fn capture_limit(max_output_bytes: Option<usize>, keep: Keep) -> CaptureLimit {
+    CaptureLimit {
+        max_bytes: max_output_bytes
+            .unwrap_or(DEFAULT_OUTPUT_BYTES)
+            .min(MAX_CAPTURE_BYTES),
+        keep,
     }
 }
 
 type SharedOutput = Arc<Mutex<CapturedOutput>>;
+/// Where output goes as it arrives; each wait on a carried command points it at a new pane.
+type LiveOutput = Arc<Mutex<Option<UnboundedSender<String>>>>;
 
 /// Child process kept alive across model subturns.
 struct RunningCommand {
     started: Instant,
+    /// When the command is stopped if it is still running.
+    deadline: Option<Instant>,
     pid: u32,
     child: tokio::process::Child,
     stdout_output: SharedOutput,
     stderr_output: SharedOutput,
+    live_output: LiveOutput,
     stdout_task: JoinHandle<()>,
     stderr_task: JoinHandle<()>,
 }
 
+impl RunningCommand {
+    /// Send output arriving from now on to `live_output`, or nowhere.
+    fn stream_to(&self, live_output: Option<UnboundedSender<String>>) {
+        if let Ok(mut current) = self.live_output.lock() {
+            *current = live_output;
+        }
+    }
+}
+
 #[derive(Default)]
 pub(super) struct RunningCommands {
     commands: AsyncMutex<HashMap<u32, RunningCommand>>,
@@ -95,12 +228,16 @@ enum CommandEnd {
         status: Option<ExitStatus>,
         killed: bool,
     },
+    TimedOut {
+        status: Option<ExitStatus>,
+        killed: bool,
+    },
 }
 
 async fn read_stream<R: tokio::io::AsyncRead + Unpin>(
     mut reader: R,
     output: SharedOutput,
-    live_output: Option<UnboundedSender<String>>,
+    live_output: LiveOutput,
 ) {
     let mut live_sent = 0usize;
     let mut live_notice_sent = false;
@@ -117,7 +254,9 @@ async fn read_stream<R: tokio::io::AsyncRead + Unpin>(
             output.push(chunk);
         }
 
-        if let Some(tx) = live_output.as_ref() {
+        if let Ok(live_output) = live_output.lock()
+            && let Some(tx) = live_output.as_ref()
+        {
             let live_remaining = MAX_LIVE_BYTES.saturating_sub(live_sent);
             let live_kept = live_remaining.min(n);
             if live_kept > 0 {
@@ -261,11 +400,29 @@ fn wait_duration(wait_seconds: Option<f64>) -> Result<Duration, String> {
     let Some(wait_seconds) = wait_seconds else {
         return Ok(DEFAULT_COMMAND_WAIT);
     };
-    if !wait_seconds.is_finite() || wait_seconds < 0.0 {
-        return Err("waitSeconds must be a finite non-negative number".to_string());
+    seconds_duration("waitSeconds", wait_seconds)
+}
+
+/// The command's own timeout, else the configured default; `None` lets it run until killed.
+fn timeout_duration(timeout_seconds: Option<f64>) -> Result<Option<Duration>, String> {
+    match timeout_seconds {
+        Some(timeout_seconds) => seconds_duration("timeoutSeconds", timeout_seconds).map(Some),
+        None => Ok(crate::config::get().command_timeout),
+    }
+}
+
+fn seconds_duration(name: &str, seconds: f64) -> Result<Duration, String> {
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(format!("{name} must be a finite non-negative number"));
     }
-    Duration::try_from_secs_f64(wait_seconds)
-        .map_err(|_| "waitSeconds is too large to represent".to_string())
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("{name} is too large to represent"))
+}
+
+/// The wait slice, cut short where the command's deadline falls inside it.
+fn wait_until_deadline(wait_for: Duration, deadline: Option<Instant>) -> Duration {
+    deadline.map_or(wait_for, |deadline| {
+        wait_for.min(deadline.saturating_duration_since(Instant::now()))
+    })
 }
 
 fn command_output(
@@ -273,12 +430,16 @@ fn command_output(
     stdout: CapturedOutput,
     stderr: CapturedOutput,
 ) -> serde_json::Value {
+    let (stdout, stdout_omitted) = stdout.text();
+    let (stderr, stderr_omitted) = stderr.text();
     json!({
         "runningFor": format!("{:.1}s", started.elapsed().as_secs_f64()),
-        "stdout": stdout.text(),
-        "stdoutBytesOmitted": stdout.omitted,
-        "stderr": stderr.text(),
-        "stderrBytesOmitted": stderr.omitted,
+        "stdout": stdout,
+        "stdoutTruncated": stdout_omitted > 0,
+        "stdoutBytesOmitted": stdout_omitted,
+        "stderr": stderr,
+        "stderrTruncated": stderr_omitted > 0,
+        "stderrBytesOmitted": stderr_omitted,
     })
 }
 
@@ -309,6 +470,21 @@ fn command_result(
                     json!("call control_command with action=\"wait\" and this pid to wait longer, or action=\"kill\" and this pid to stop it"),
                 );
             }
+            CommandEnd::TimedOut { status, killed } => {
+                output.insert("ok".to_string(), json!(false));
+                output.insert("status".to_string(), json!("killed"));
+                output.insert("error".to_string(), json!("timed out"));
+                output.insert(
+                    "kill".to_string(),
+                    json!({
+                        "signal": "SIGINT",
+                        "killedAfterGrace": killed,
+                    }),
+                );
+                if let Some(status) = status {
+                    output.insert("exitCode".to_string(), json!(status.code()));
+                }
+            }
             CommandEnd::Killed { status, killed } => {
                 output.insert("ok".to_string(), json!(false));
                 output.insert("status".to_string(), json!("killed"));
@@ -351,13 +527,21 @@ fn running_command_result(command: &RunningCommand) -> serde_json::Value {
 
 async fn spawn_command(
     argv: &[String],
+    input: CommandInput,
+    timeout: Option<Duration>,
+    capture: CaptureLimit,
     live_output: Option<UnboundedSender<String>>,
 ) -> std::io::Result<RunningCommand> {
     let mut cmd = tokio::process::Command::new(&argv[0]);
     if argv.len() > 1 {
         cmd.args(&argv[1..]);
     }
-    cmd.stdin(Stdio::null())
+    let stdin = match input.stdin {
+        Some(_) => Stdio::piped(),
+        None => Stdio::null(),
+    };
+    cmd.envs(&input.env)
+        .stdin(stdin)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .kill_on_drop(true);
@@ -374,9 +558,17 @@ async fn spawn_command(
 
     let stdout_pipe = child.stdout.take();
     let stderr_pipe = child.stderr.take();
+    // Written from its own task: a command that fills its stdout pipe before reading all of
+    // stdin would otherwise wait on us while we wait on it.
+    if let (Some(mut stdin_pipe), Some(content)) = (child.stdin.take(), input.stdin) {
+        tokio::spawn(async move {
+            let _ = stdin_pipe.write_all(content.as_bytes()).await;
+        });
+    }
 
-    let stdout_output = SharedOutput::default();
-    let stderr_output = SharedOutput::default();
+    let stdout_output = Arc::new(Mutex::new(CapturedOutput::new(capture)));
+    let stderr_output = Arc::new(Mutex::new(CapturedOutput::new(capture)));
+    let live_output = Arc::new(Mutex::new(live_output));
     let stdout_live_output = live_output.clone();
     let stdout_for_task = stdout_output.clone();
     let stdout_task = tokio::spawn(async move {
@@ -385,18 +577,22 @@ async fn spawn_command(
         }
     });
     let stderr_for_task = stderr_output.clone();
+    let stderr_live_output = live_output.clone();
     let stderr_task = tokio::spawn(async move {
         if let Some(stderr) = stderr_pipe {
-            read_stream(stderr, stderr_for_task, live_output).await;
+            read_stream(stderr, stderr_for_task, stderr_live_output).await;
         }
     });
 
+    let started = Instant::now();
     Ok(RunningCommand {
-        started: Instant::now(),
+        started,
+        deadline: timeout.map(|timeout| started + timeout),
         pid,
         child,
         stdout_output,
         stderr_output,
+        live_output,
         stdout_task,
         stderr_task,
     })
@@ -404,7 +600,10 @@ async fn spawn_command(
 
 async fn start_command(
     argv: Vec<String>,
+    input: CommandInput,
     wait_for: Duration,
+    timeout: Option<Duration>,
+    capture: CaptureLimit,
     commands: Arc<RunningCommands>,
     live_output: Option<UnboundedSender<String>>,
 ) -> serde_json::Value {
@@ -412,13 +611,14 @@ async fn start_command(
         return json!({ "error": "argv must be non-empty" });
     }
 
-    let mut command = match spawn_command(&argv, live_output).await {
+    let mut command = match spawn_command(&argv, input, timeout, capture, live_output).await {
         Ok(command) => command,
         Err(error) => return json!({ "error": error.to_string() }),
     };
     let pid = command.pid;
     let mut guard = ProcessGroupGuard::armed(pid);
 
+    let wait_for = wait_until_deadline(wait_for, command.deadline);
     let status = match wait_for_exit(&mut command.child, wait_for).await {
         Ok(status) => status,
         Err(error) => return json!({ "error": error.to_string() }),
@@ -428,8 +628,17 @@ async fn start_command(
         guard.disarm();
         return output;
     }
+    if command
+        .deadline
+        .is_some_and(|deadline| Instant::now() >= deadline)
+    {
+        let output = time_out(command).await;
+        guard.disarm();
+        return output;
+    }
 
     let output = running_command_result(&command);
+    command.stream_to(None);
     commands.commands.lock().await.insert(pid, command);
     guard.disarm();
     output
@@ -451,7 +660,9 @@ pub(super) async fn wait_by_pid(
         return json!({ "error": format!("unknown pid `{pid}`") });
     };
     let mut guard = ProcessGroupGuard::armed(command.pid);
+    command.stream_to(stride.live_output());
 
+    let wait_for = wait_until_deadline(wait_for, command.deadline);
     let status = match wait_for_exit(&mut command.child, wait_for).await {
         Ok(status) => status,
         Err(error) => return json!({ "error": error.to_string() }),
@@ -461,8 +672,17 @@ pub(super) async fn wait_by_pid(
         guard.disarm();
         return output;
     }
+    if command
+        .deadline
+        .is_some_and(|deadline| Instant::now() >= deadline)
+    {
+        let output = time_out(command).await;
+        guard.disarm();
+        return output;
+    }
 
     let output = running_command_result(&command);
+    command.stream_to(None);
     commands.commands.lock().await.insert(pid, command);
     guard.disarm();
     output
@@ -487,24 +707,203 @@ pub(super) async fn kill_by_pid(pid: u32, stride: Stride) -> serde_json::Value {
         Err(error) => return json!({ "error": error.to_string() }),
     }
 
-    interrupt_child(&mut command.child);
-    let status = match wait_for_exit(&mut command.child, INTERRUPT_GRACE).await {
-        Ok(status) => status,
+    let (status, killed) = match interrupt_then_kill(&mut command.child).await {
+        Ok(stopped) => stopped,
         Err(error) => return json!({ "error": error.to_string() }),
     };
-    let (status, killed) = match status {
-        Some(status) => (Some(status), false),
-        None => {
-            kill_child(&mut command.child);
-            (command.child.wait().await.ok(), true)
-        }
-    };
 
     let output = finish_command(command, CommandEnd::Killed { status, killed }).await;
     guard.disarm();
     output
 }
 
+/// Stop a command that outlived its timeout the same way `kill_by_pid` would.
+async fn time_out(mut command: RunningCommand) -> serde_json::Value {
+    let (status, killed) = match interrupt_then_kill(&mut command.child).await {
+        Ok(stopped) => stopped,
+        Err(error) => return json!({ "error": error.to_string() }),
+    };
+    finish_command(command, CommandEnd::TimedOut { status, killed }).await
+}
+
+/// Interrupt, then kill after grace. Returns the exit status, if any, and whether it took a kill.
+async fn interrupt_then_kill(
+    child: &mut tokio::process::Child,
+) -> std::io::Result<(Option<ExitStatus>, bool)> {
+    interrupt_child(child);
+    match wait_for_exit(child, INTERRUPT_GRACE).await? {
+        Some(status) => Ok((Some(status), false)),
+        None => {
+            kill_child(child);
+            Ok((child.wait().await.ok(), true))
+        }
+    }
+}
+
+/// Lines of stdin shown in the approval prompt before the rest is counted instead.
+const STDIN_PREVIEW_LINES: usize = 20;
+
+/// Describe a command for the approval prompt: argv as a shell would read it, then any
+/// environment and stdin it is given, since those can change what it does as much as argv.
+pub fn summarize_command_for_preview(
+    argv: &[String],
+    env: &[(String, String)],
+    stdin: Option<&str>,
+) -> String {
+    let words = argv.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>();
+    let mut preview = format!("run: {}", words.join(" "));
+    for (name, value) in env {
+        preview.push_str(&format!("\nenv: {name}={}", shell_quote(value)));
+    }
+    if let Some(stdin) = stdin {
+        let lines = stdin.lines().collect::<Vec<_>>();
+        preview.push_str(&format!("\nstdin: {} bytes", stdin.len()));
+        for line in lines.iter().take(STDIN_PREVIEW_LINES) {
+            preview.push_str(&format!("\n  {line}"));
+        }
+        if lines.len() > STDIN_PREVIEW_LINES {
+            let more = lines.len() - STDIN_PREVIEW_LINES;
+            preview.push_str(&format!("\n  ... {more} more lines"));
+        }
+    }
+    preview
+}
+
+/// Quote a word for display the way a POSIX shell would need it.
+fn shell_quote(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// Programs whose usual job is to delete, move, or rewrite files in place.
+const DESTRUCTIVE_PROGRAMS: &[&str] = &[
+    "rm", "rmdir", "mv", "dd", "shred", "truncate", "mkfs", "chmod", "chown", "tee",
+];
+
+/// Reasons a command may write outside the workspace or destroy data, for the approval prompt.
+/// This is a heuristic over argv and `sh -c` scripts; an empty list proves nothing.
+pub fn write_warnings(argv: &[String], workspace: &std::path::Path) -> Vec<String> {
+    let is_shell = argv.first().is_some_and(|program| {
+        matches!(
+            program.rsplit('/').next().unwrap_or_default(),
+            "sh" | "bash" | "zsh" | "dash" | "fish"
+        )
+    });
+    // Shell scripts are split on whitespace and separators, which is crude but catches the common forms.
+    let mut words = Vec::new();
+    let mut redirects = false;
+    for (index, arg) in argv.iter().enumerate() {
+        if is_shell && index > 0 && argv[index - 1] == "-c" {
+            redirects |= redirects_output(arg);
+            words.extend(
+                arg.split(|c: char| c.is_whitespace() || matches!(c, ';' | '&' | '|' | '(' | ')'))
+                    .filter(|word| !word.is_empty())
+                    .map(str::to_string),
+            );
+        } else {
+            words.push(arg.clone());
+        }
+    }
+
+    let mut warnings = Vec::new();
+    if redirects {
+        warnings.push("writes files through shell redirection (`>`)".to_string());
+    }
+    for (index, word) in words.iter().enumerate() {
+        let program = word.rsplit('/').next().unwrap_or_default();
+        let destructive = DESTRUCTIVE_PROGRAMS.contains(&program)
+            || program.starts_with("mkfs.")
+            || word == "-delete";
+        let warning = format!("runs `{program}`, which can delete or overwrite files");
+        if destructive && !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+        let path = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '>' || c == '<');
+        // The program itself commonly lives outside the workspace.
+        let outside = index > 0
+            && ((path.starts_with('/') && !std::path::Path::new(path).starts_with(workspace))
+                || path.starts_with('~'));
+        if outside && !matches!(path, "/dev/null" | "/dev/stdout" | "/dev/stderr") {
+            warnings.push(format!("names a path outside the workspace: {path}"));
+        }
+    }
+    if words
+        .windows(2)
+        .any(|pair| pair[0] == "clean" && pair[1].starts_with("-f"))
+        || words.iter().any(|word| word == "--hard")
+    {
+        warnings.push("discards uncommitted git changes".to_string());
+    }
+    warnings
+}
+
+/// Whether a shell script sends output to a file with `>`, `>>`, `N>` or `&>`.
+/// Quoted text, descriptor duplication like `2>&1`, the null device and `=>`/`->` arrows
+/// do not count.
+fn redirects_output(script: &str) -> bool {
+    let chars = script.chars().collect::<Vec<_>>();
+    let mut quote = None;
+    let mut at = 0;
+    while at < chars.len() {
+        let c = chars[at];
+        at += 1;
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '\\' => at += 1,
+            None if matches!(c, '\'' | '"') => quote = Some(c),
+            None if c == '>' => {
+                let arrow = at >= 2 && matches!(chars[at - 2], '=' | '-');
+                if matches!(chars.get(at), Some('>' | '|')) {
+                    at += 1;
+                }
+                let duplicates = chars.get(at) == Some(&'&');
+                let target = chars[at..]
+                    .iter()
+                    .skip_while(|c| c.is_whitespace())
+                    .take_while(|c| !c.is_whitespace() && !matches!(c, ';' | '&' | '|' | ')'))
+                    .collect::<String>();
+                let discarded =
+                    matches!(target.as_str(), "/dev/null" | "/dev/stdout" | "/dev/stderr");
+                if !arrow && !duplicates && !discarded {
+                    return true;
+                }
+            }
+            None => {}
+        }
+    }
+    false
+}
+
+/// Run a verification command on behalf of another tool, with the default wait.
+pub(super) async fn verify(argv: Vec<String>, stride: Stride) -> serde_json::Value {
+    let commands = stride.running_commands();
+    let timeout = crate::config::get().command_timeout;
+    start_command(
+        argv,
+        CommandInput::default(),
+        DEFAULT_COMMAND_WAIT,
+        timeout,
+        CaptureLimit::default(),
+        commands,
+        stride.live_output(),
+    )
+    .await
+}
+
+/// Run a command the user asked for outside any turn, stopping it if it outlives the default wait.
+/// No approval is asked; the user named the command themselves.
+pub async fn run_for_context(argv: Vec<String>) -> serde_json::Value {
+    let stride = Stride::default();
+    let output = verify(argv, stride.clone()).await;
+    stride.kill_running_commands().await;
+    output
+}
+
 /// Run a command and optionally stream bounded stdout/stderr chunks to live output.
 /// The returned JSON includes bounded stdout/stderr plus omitted byte counters.
 pub async fn call(args: Args, stride: Stride) -> serde_json::Value {
@@ -516,18 +915,36 @@ pub async fn call(args: Args, stride: Stride) -> serde_json::Value {
         Ok(wait_for) => wait_for,
         Err(error) => return json!({ "error": error }),
     };
-    start_command(args.argv, wait_for, commands, stride.live_output()).await
+    let timeout = match timeout_duration(args.timeout_seconds) {
+        Ok(timeout) => timeout,
+        Err(error) => return json!({ "error": error }),
+    };
+    let capture = capture_limit(args.max_output_bytes, args.keep);
+    let input = CommandInput {
+        env: args.env,
+        stdin: args.stdin,
+    };
+    start_command(
+        args.argv,
+        input,
+        wait_for,
+        timeout,
+        capture,
+        commands,
+        stride.live_output(),
+    )
+    .await
 }
 
 pub fn spec() -> (&'static str, &'static str, Vec<Param>) {
     (
         NAME,
-        "Start a command by argv. Output is capped. Commands still running after waitSeconds, default 40, return their pid instead of being interrupted.",
+        "Start a command by argv. Each stream is capped at maxOutputBytes, default 64 KiB. Commands still running after waitSeconds, default 40, return their pid instead of being interrupted.",
         vec![
             Param {
                 name: "argv",
                 desc: "Argument vector for a new command: [program, ...args]",
-                param_type: ParamType::String,
+                param_type: ParamType::Array,
                 required: true,
             },
             Param {
@@ -536,6 +953,36 @@ pub fn spec() -> (&'static str, &'static str, Vec<Param>) {
                 param_type: ParamType::Number,
                 required: false,
             },
+            Param {
+                name: "timeoutSeconds",
+                desc: "Seconds after which the command is stopped even if waited on again",
+                param_type: ParamType::Number,
+                required: false,
+            },
+            Param {
+                name: "maxOutputBytes",
+                desc: "Bytes of stdout and of stderr to return; defaults to 65536, at most 262144",
+                param_type: ParamType::Number,
+                required: false,
+            },
+            Param {
+                name: "keep",
+                desc: "Which end of a longer stream to return; defaults to head, use tail for build logs",
+                param_type: ParamType::Choice(&["head", "tail"]),
+                required: false,
+            },
+            Param {
+                name: "env",
+                desc: "Environment variables to set, as an object of names to values, e.g. {\"GIT_PAGER\": \"cat\"}",
+                param_type: ParamType::Object,
+                required: false,
+            },
+            Param {
+                name: "stdin",
+                desc: "Text to feed the command on stdin; without it stdin is empty",
+                param_type: ParamType::String,
+                required: false,
+            },
         ],
     )
 }
@@ -544,6 +991,100 @@ pub fn spec() -> (&'static str, &'static str, Vec<Param>) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn previews_show_quoted_argv_env_and_stdin() {
+        let argv = ["sh", "-c", "echo 'hi' > x"].map(String::from);
+        let env = [("GIT_PAGER".to_string(), "less -R".to_string())];
+        let stdin = (1..=22).map(|n| format!("line {n}\n")).collect::<String>();
+        let preview = summarize_command_for_preview(&argv, &env, Some(&stdin));
+        let lines = preview.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], r#"run: sh -c 'echo '\''hi'\'' > x'"#);
+        assert_eq!(lines[1], "env: GIT_PAGER='less -R'");
+        assert_eq!(lines[2], format!("stdin: {} bytes", stdin.len()));
+        assert_eq!(lines[3], "  line 1");
+        assert_eq!(lines.last(), Some(&"  ... 2 more lines"));
+        assert_eq!(
+            summarize_command_for_preview(&["ls".to_string()], &[], None),
+            "run: ls"
+        );
+    }
+
+    fn warnings_for(argv: &[&str]) -> Vec<String> {
+        let argv = argv.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        write_warnings(&argv, std::path::Path::new("/work/repo"))
+    }
+
+    #[test]
+    fn long_output_is_cut_to_whole_characters_at_either_end() {
+        let limit = |keep| capture_limit(Some(4), keep);
+        let mut head = CapturedOutput::new(limit(Keep::Head));
+        head.push("aéé".as_bytes());
+        head.push(b"zz");
+        assert_eq!(head.text(), ("aé".to_string(), 4));
+
+        let mut tail = CapturedOutput::new(limit(Keep::Tail));
+        tail.push(b"zz");
+        tail.push("ééb".as_bytes());
+        assert_eq!(tail.text(), ("éb".to_string(), 4));
+
+        let mut short = CapturedOutput::new(limit(Keep::Tail));
+        short.push("é".as_bytes());
+        assert_eq!(short.text(), ("é".to_string(), 0));
+        assert_eq!(
+            capture_limit(Some(usize::MAX), Keep::Head).max_bytes,
+            MAX_CAPTURE_BYTES
+        );
+    }
+
+    #[test]
+    fn broad_writes_are_called_out() {
+        assert!(warnings_for(&["cargo", "fmt"]).is_empty());
+        assert!(warnings_for(&["cat", "/work/repo/src/main.rs"]).is_empty());
+        assert_eq!(
+            warnings_for(&["sh", "-c", "echo hi >> ~/.bashrc"]),
+            [
+                "writes files through shell redirection (`>`)",
+                "names a path outside the workspace: ~/.bashrc",
+            ]
+        );
+        assert_eq!(
+            warnings_for(&["/bin/rm", "-rf", "/tmp/x"]),
+            [
+                "runs `rm`, which can delete or overwrite files",
+                "names a path outside the workspace: /tmp/x",
+            ]
+        );
+        assert_eq!(
+            warnings_for(&["git", "reset", "--hard"]),
+            ["discards uncommitted git changes"]
+        );
+    }
+
+    #[test]
+    fn only_real_redirections_count_as_writes() {
+        for script in [
+            "cargo test 2>&1",
+            "cargo test 2>&1 | tail -n 20",
+            "echo oops >&2",
+            "make 2>/dev/null",
+            "echo 'a > b' \"x >> y\"",
+            "grep -n '=>' src/*.rs",
+            "awk '$1 -> $2' f",
+        ] {
+            assert!(!redirects_output(script), "{script}");
+        }
+        for script in [
+            "echo hi > out.txt",
+            "echo hi>>log",
+            "cargo build 2> errors.txt",
+            "cargo build &>build.log",
+            "cargo test 2>&1 > all.log",
+        ] {
+            assert!(redirects_output(script), "{script}");
+        }
+        assert!(warnings_for(&["sh", "-c", "cargo test 2>&1"]).is_empty());
+    }
+
     #[cfg(unix)]
     fn pid_exists(pid: u32) -> bool {
         let status = std::process::Command::new("kill")
@@ -588,6 +1129,11 @@ mod tests {
                     "printf hello; printf problem >&2".to_string(),
                 ],
                 wait_seconds: None,
+                timeout_seconds: None,
+                max_output_bytes: None,
+                keep: Keep::Head,
+                env: HashMap::new(),
+                stdin: None,
             },
             Stride::default(),
         )
@@ -601,6 +1147,36 @@ mod tests {
         assert_eq!(result["stderrBytesOmitted"], 0);
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn env_and_stdin_reach_the_command_without_blocking_on_big_input() {
+        let result = call(
+            Args {
+                argv: vec![
+                    "sh".to_string(),
+                    "-c".to_string(),
+                    "printf '%s:' \"$GREETING\"; cat".to_string(),
+                ],
+                wait_seconds: Some(10.0),
+                timeout_seconds: None,
+                max_output_bytes: None,
+                keep: Keep::Tail,
+                env: HashMap::from([("GREETING".to_string(), "hi".to_string())]),
+                stdin: Some(format!("{}there", "x".repeat(1024 * 1024))),
+            },
+            Stride::default(),
+        )
+        .await;
+
+        assert_eq!(result["status"], "finished");
+        assert!(result["stdout"].as_str().unwrap().ends_with("xthere"));
+        assert_eq!(result["stdoutTruncated"], true);
+        assert_eq!(
+            result["stdoutBytesOmitted"],
+            3 + 1024 * 1024 + 5 - DEFAULT_OUTPUT_BYTES
+        );
+    }
+
     #[cfg(unix)]
     #[tokio::test]
     async fn finished_command_cleans_redirected_background_child() {
@@ -612,6 +1188,11 @@ mod tests {
                     "sleep 999 >/dev/null 2>&1 & echo $!".to_string(),
                 ],
                 wait_seconds: None,
+                timeout_seconds: None,
+                max_output_bytes: None,
+                keep: Keep::Head,
+                env: HashMap::new(),
+                stdin: None,
             },
             Stride::default(),
         )
@@ -640,6 +1221,11 @@ mod tests {
                 Args {
                     argv: vec!["sh".to_string(), "-c".to_string(), script],
                     wait_seconds: Some(60.0),
+                    timeout_seconds: None,
+                    max_output_bytes: None,
+                    keep: Keep::Head,
+                    env: HashMap::new(),
+                    stdin: None,
                 },
                 Stride::default(),
             )
@@ -665,6 +1251,36 @@ mod tests {
         assert!(eventually_dead(pid).await);
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn commands_past_their_timeout_are_stopped_with_partial_output() {
+        let stride = Stride::default();
+        let started = Instant::now();
+        let result = call(
+            Args {
+                argv: vec![
+                    "sh".to_string(),
+                    "-c".to_string(),
+                    "printf partial; exec sleep 30".to_string(),
+                ],
+                wait_seconds: Some(30.0),
+                timeout_seconds: Some(0.2),
+                max_output_bytes: None,
+                keep: Keep::Head,
+                env: HashMap::new(),
+                stdin: None,
+            },
+            stride.clone(),
+        )
+        .await;
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(result["error"], "timed out");
+        assert_eq!(result["status"], "killed");
+        assert_eq!(result["stdout"], "partial");
+        assert!(stride.running_command_pids().await.is_empty());
+    }
+
     #[cfg(unix)]
     #[tokio::test]
     async fn long_command_can_be_waited_instead_of_interrupted() {
@@ -677,6 +1293,11 @@ mod tests {
                     "sleep 0.15; printf done".to_string(),
                 ],
                 wait_seconds: Some(0.02),
+                timeout_seconds: None,
+                max_output_bytes: None,
+                keep: Keep::Head,
+                env: HashMap::new(),
+                stdin: None,
             },
             stride.clone(),
         )
@@ -692,6 +1313,42 @@ mod tests {
         assert_eq!(result["stdout"], "done");
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn output_streams_live_to_whichever_call_is_waiting() {
+        let (first_tx, mut first_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (later_tx, mut later_rx) = tokio::sync::mpsc::unbounded_channel();
+        let stride = Stride::default();
+        let result = call(
+            Args {
+                argv: vec![
+                    "sh".to_string(),
+                    "-c".to_string(),
+                    "printf one; sleep 0.2; printf two".to_string(),
+                ],
+                wait_seconds: Some(0.1),
+                timeout_seconds: None,
+                max_output_bytes: None,
+                keep: Keep::Head,
+                env: HashMap::new(),
+                stdin: None,
+            },
+            stride.with_live_output(Some(first_tx)),
+        )
+        .await;
+        assert_eq!(result["status"], "running");
+        let pid = result["pid"].as_u64().unwrap() as u32;
+
+        let result = wait_by_pid(pid, Some(2.0), stride.with_live_output(Some(later_tx))).await;
+        assert_eq!(result["stdout"], "onetwo");
+
+        let drain = |rx: &mut tokio::sync::mpsc::UnboundedReceiver<String>| {
+            std::iter::from_fn(|| rx.try_recv().ok()).collect::<String>()
+        };
+        assert_eq!(drain(&mut first_rx), "one");
+        assert_eq!(drain(&mut later_rx), "two");
+    }
+
     #[cfg(unix)]
     #[tokio::test]
     async fn running_result_includes_partial_output() {
@@ -704,6 +1361,11 @@ mod tests {
                     "printf start; printf err >&2; sleep 999".to_string(),
                 ],
                 wait_seconds: Some(0.02),
+                timeout_seconds: None,
+                max_output_bytes: None,
+                keep: Keep::Head,
+                env: HashMap::new(),
+                stdin: None,
             },
             stride.clone(),
         )
@@ -730,6 +1392,11 @@ mod tests {
                     "printf one; sleep 0.2; printf two; sleep 999".to_string(),
                 ],
                 wait_seconds: Some(0.05),
+                timeout_seconds: None,
+                max_output_bytes: None,
+                keep: Keep::Head,
+                env: HashMap::new(),
+                stdin: None,
             },
             stride.clone(),
         )
@@ -756,6 +1423,11 @@ mod tests {
             Args {
                 argv: vec!["sh".to_string(), "-c".to_string(), "sleep 999".to_string()],
                 wait_seconds: Some(0.02),
+                timeout_seconds: None,
+                max_output_bytes: None,
+                keep: Keep::Head,
+                env: HashMap::new(),
+                stdin: None,
             },
             stride.clone(),
         )
@@ -782,6 +1454,11 @@ mod tests {
                     "sleep 0.05; printf after".to_string(),
                 ],
                 wait_seconds: Some(0.01),
+                timeout_seconds: None,
+                max_output_bytes: None,
+                keep: Keep::Head,
+                env: HashMap::new(),
+                stdin: None,
             },
             stride.clone(),
         )
@@ -0,0 +1,346 @@
+use super::common::{Param, ParamType, Stride, is_excluded_dir, resolve_path_within_cwd};
+use super::gitignore::Gitignores;
+use super::sensitive::is_sensitive_path;
+use crate::config::ReadApproval;
+use serde::Deserialize;
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+pub const NAME: &str = "search_files";
+/// Most matches one call may ask for.
+const MAX_MATCHES: usize = 1000;
+/// Characters of a matching or context line shown; minified files have enormous lines.
+const MAX_LINE_CHARS: usize = 300;
+
+#[derive(Deserialize)]
+pub struct Args {
+    /// Literal text to look for, or a regular expression with `regex`
+    pattern: String,
+    #[serde(default)]
+    regex: bool,
+    #[serde(default = "default_dot")]
+    path: String,
+    /// Lines to include before and after each match, like `grep -C`
+    #[serde(default)]
+    context_lines: usize,
+    #[serde(default = "default_max_matches", alias = "max_results")]
+    max_matches: usize,
+}
+
+fn default_dot() -> String {
+    ".".to_string()
+}
+
+fn default_max_matches() -> usize {
+    100
+}
+
+/// One matching line with its surroundings.
+#[derive(Debug, PartialEq, Eq)]
+struct Match {
+    /// 1-based line number
+    line: usize,
+    text: String,
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+/// What a line has to contain to match.
+enum Pattern {
+    Literal(String),
+    Regex(regex::Regex),
+}
+
+impl Pattern {
+    fn new(pattern: &str, is_regex: bool) -> Result<Self, regex::Error> {
+        if is_regex {
+            regex::Regex::new(pattern).map(Self::Regex)
+        } else {
+            Ok(Self::Literal(pattern.to_string()))
+        }
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        match self {
+            Self::Literal(text) => line.contains(text.as_str()),
+            Self::Regex(regex) => regex.is_match(line),
+        }
+    }
+}
+
+/// The line cut to `MAX_LINE_CHARS`, marked with an ellipsis where it was cut.
+fn clip_line(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((at, _)) => format!("{}…", &line[..at]),
+        None => line.to_string(),
+    }
+}
+
+/// Find lines matching `pattern`, each with up to `context` lines around it.
+fn find_matches(text: &str, pattern: &Pattern, context: usize) -> Vec<Match> {
+    let lines = text.lines().collect::<Vec<_>>();
+    let owned = |range: &[&str]| range.iter().map(|s| clip_line(s)).collect::<Vec<_>>();
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| pattern.is_match(line))
+        .map(|(idx, line)| Match {
+            line: idx + 1,
+            text: clip_line(line),
+            before: owned(&lines[idx.saturating_sub(context)..idx]),
+            after: owned(&lines[idx + 1..(idx + 1 + context).min(lines.len())]),
+        })
+        .collect()
+}
+
+fn walk(
+    cur: &Path,
+    args: &Args,
+    pattern: &Pattern,
+    ignores: &mut Option<Gitignores>,
+    out: &mut Vec<serde_json::Value>,
+    truncated: &mut bool,
+) -> std::io::Result<()> {
+    if cur.is_file() {
+        // Binary and non-UTF-8 files fail to read as text and are skipped.
+        let Ok(text) = fs::read_to_string(cur) else {
+            return Ok(());
+        };
+        for found in find_matches(&text, pattern, args.context_lines) {
+            if out.len() >= args.max_matches.min(MAX_MATCHES) {
+                *truncated = true;
+                return Ok(());
+            }
+            let mut entry = json!({
+                "path": cur.display().to_string(),
+                "line": found.line,
+                "text": found.text,
+            });
+            if args.context_lines > 0 {
+                entry["before"] = json!(found.before);
+                entry["after"] = json!(found.after);
+            }
+            out.push(entry);
+        }
+        return Ok(());
+    }
+    let config = crate::config::get();
+    // Without a prompt per file, a guarded search leaves sensitive files out entirely.
+    let skip_sensitive = config.read_approval != ReadApproval::Off;
+    let mut entries = fs::read_dir(cur)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let file_type = entry.file_type()?;
+        // Links are only followed to files in the workspace: a linked directory may loop or lead out.
+        if file_type.is_symlink()
+            && (path.is_dir() || resolve_path_within_cwd(&path.to_string_lossy()).is_err())
+        {
+            continue;
+        }
+        let is_dir = file_type.is_dir();
+        if !is_dir && !path.is_file() {
+            continue;
+        }
+        let skip = match ignores {
+            Some(ignores) => ignores.is_ignored(&path, is_dir),
+            None => is_dir && is_excluded_dir(&name),
+        };
+        if skip || (is_dir && name.starts_with('.')) {
+            continue;
+        }
+        if skip_sensitive && is_sensitive_path(&path.to_string_lossy(), &config.sensitive_paths) {
+            continue;
+        }
+        if is_dir && let Some(ignores) = ignores {
+            ignores.enter(&path);
+        }
+        let walked = walk(&path, args, pattern, ignores, out, truncated);
+        if is_dir && let Some(ignores) = ignores {
+            ignores.leave();
+        }
+        walked?;
+        if *truncated {
+            break;
+        }
+    }
+    Ok(())
+}
+
+pub async fn call(args: Args, _stride: Stride) -> serde_json::Value {
+    if args.pattern.is_empty() {
+        return json!({ "error": "pattern must be non-empty" });
+    }
+    let pattern = match Pattern::new(&args.pattern, args.regex) {
+        Ok(pattern) => pattern,
+        Err(e) => return json!({ "error": format!("bad regex: {e}") }),
+    };
+    let root = match resolve_path_within_cwd(&args.path) {
+        Ok(p) => p,
+        Err(e) => return json!({ "error": e.to_string() }),
+    };
+    if !root.exists() {
+        return json!({ "error": format!("path does not exist: {}", root.display()) });
+    }
+    // Without git, the fixed set of build directories stands in for ignore rules.
+    let is_repo = Path::new(".git").exists() || Path::new(".gitignore").is_file();
+    let base = if root.is_dir() {
+        root.clone()
+    } else {
+        root.parent().unwrap_or(Path::new(".")).to_path_buf()
+    };
+    let mut ignores = is_repo.then(|| Gitignores::for_dir(Path::new("."), &base));
+    let mut matches = Vec::new();
+    let mut truncated = false;
+    if let Err(e) = walk(
+        &root,
+        &args,
+        &pattern,
+        &mut ignores,
+        &mut matches,
+        &mut truncated,
+    ) {
+        return json!({ "error": e.to_string() });
+    }
+    json!({ "matches": matches, "truncated": truncated })
+}
+
+pub fn spec() -> (&'static str, &'static str, Vec<Param>) {
+    (
+        NAME,
+        "Search files under a path for lines containing literal text or matching a regex, leaving out what .gitignore ignores",
+        vec![
+            Param {
+                name: "pattern",
+                desc: "Literal text to find, or a regex when regex is true; case-sensitive",
+                param_type: ParamType::String,
+                required: true,
+            },
+            Param {
+                name: "regex",
+                desc: "Treat pattern as a regular expression; default false",
+                param_type: ParamType::Boolean,
+                required: false,
+            },
+            Param {
+                name: "path",
+                desc: "File or directory to search; defaults to current directory",
+                param_type: ParamType::String,
+                required: false,
+            },
+            Param {
+                name: "context_lines",
+                desc: "Lines to include before and after each match; default 0",
+                param_type: ParamType::Number,
+                required: false,
+            },
+            Param {
+                name: "max_matches",
+                desc: "Stop after this many matches; default 100, at most 1000",
+                param_type: ParamType::Number,
+                required: false,
+            },
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(root: &Path) -> Vec<serde_json::Value> {
+        let args = Args {
+            pattern: "needle".into(),
+            regex: false,
+            path: ".".into(),
+            context_lines: 0,
+            max_matches: 100,
+        };
+        let pattern = Pattern::new(&args.pattern, false).unwrap();
+        let (mut out, mut truncated) = (Vec::new(), false);
+        walk(root, &args, &pattern, &mut None, &mut out, &mut truncated).unwrap();
+        out
+    }
+
+    /// A fresh directory holding `a.txt` with one match.
+    fn scratch(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("please-{name}-{}", std::process::id()));
+        fs::create_dir_all(dir.join("root")).unwrap();
+        fs::write(dir.join("root").join("a.txt"), "needle\n").unwrap();
+        dir
+    }
+
+    fn found_paths(found: &[serde_json::Value]) -> Vec<String> {
+        found
+            .iter()
+            .map(|entry| entry["path"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_symlink_loop_is_not_followed() {
+        let dir = scratch("search-loop");
+        let root = dir.join("root");
+        std::os::unix::fs::symlink(&root, root.join("loop")).unwrap();
+        let found = search(&root);
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(
+            found_paths(&found),
+            [root.join("a.txt").display().to_string()]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_out_of_the_workspace_are_not_searched() {
+        use std::os::unix::fs::symlink;
+        let dir = scratch("search-escape");
+        let (root, outside) = (dir.join("root"), dir.join("outside"));
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("secret.txt"), "needle\n").unwrap();
+        symlink(&outside, root.join("escape")).unwrap();
+        symlink(outside.join("secret.txt"), root.join("leak.txt")).unwrap();
+        let found = search(&root);
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(
+            found_paths(&found),
+            [root.join("a.txt").display().to_string()]
+        );
+    }
+
+    #[test]
+    fn context_is_clamped_to_the_file_edges() {
+        let text = "fn a() {}\nfn b() {}\nfn c() {}\n";
+        let literal = |text: &str| Pattern::new(text, false).unwrap();
+        let found = find_matches(text, &literal("fn a"), 2);
+        assert_eq!(
+            found,
+            [Match {
+                line: 1,
+                text: "fn a() {}".into(),
+                before: vec![],
+                after: vec!["fn b() {}".into(), "fn c() {}".into()],
+            }]
+        );
+        let found = find_matches(text, &literal("fn c"), 1);
+        assert_eq!(found[0].before, ["fn b() {}"]);
+        assert!(found[0].after.is_empty());
+    }
+
+    #[test]
+    fn regexes_match_and_long_lines_are_clipped() {
+        let long = "x".repeat(MAX_LINE_CHARS + 50);
+        let text = format!("let a = 1;\nconst B: u8 = 2;\n{long} let c\n");
+        let found = find_matches(&text, &Pattern::new(r"^(let|const) \w", true).unwrap(), 0);
+        assert_eq!(found.iter().map(|m| m.line).collect::<Vec<_>>(), [1, 2]);
+
+        let found = find_matches(&text, &Pattern::new("let c", false).unwrap(), 0);
+        assert_eq!(found[0].text.chars().count(), MAX_LINE_CHARS + 1);
+        assert!(found[0].text.ends_with('…'));
+        assert!(Pattern::new("(", true).is_err());
+    }
+}
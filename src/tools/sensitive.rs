@@ -0,0 +1,135 @@
+//! Recognize paths that likely hold secrets, for the optional read approval.
+use std::path::Path;
+
+/// Used when `PLEASE_SENSITIVE_PATHS` is not set.
+/// A trailing `/` names a directory anywhere in the path; other patterns match the file name.
+pub const DEFAULT_SENSITIVE_PATTERNS: &[&str] = &[
+    ".env",
+    ".env.*",
+    "*.key",
+    "*.pem",
+    "*.p12",
+    "id_rsa",
+    "id_ecdsa",
+    "id_ed25519",
+    ".netrc",
+    ".npmrc",
+    ".pypirc",
+    "credentials*",
+    "secrets/",
+    ".ssh/",
+    ".gnupg/",
+];
+
+/// File names differ only by case on macOS's usual file systems, so `.ENV` is `.env` there.
+const FOLD_CASE: bool = cfg!(target_os = "macos");
+
+/// Whether `path`, or the file it resolves to through symlinks, matches one of `patterns`,
+/// where `*` stands for any run of characters.
+pub fn is_sensitive_path(path: &str, patterns: &[String]) -> bool {
+    let resolved = std::fs::canonicalize(path).ok();
+    std::iter::once(Path::new(path))
+        .chain(resolved.as_deref())
+        .any(|path| matches_patterns(path, patterns, FOLD_CASE))
+}
+
+fn matches_patterns(path: &Path, patterns: &[String], fold_case: bool) -> bool {
+    let fold = |text: &str| {
+        if fold_case {
+            text.to_lowercase()
+        } else {
+            text.to_string()
+        }
+    };
+    let components = path
+        .components()
+        .filter_map(|component| component.as_os_str().to_str())
+        .map(fold)
+        .collect::<Vec<_>>();
+    let Some(name) = components.last() else {
+        return false;
+    };
+    patterns
+        .iter()
+        .map(|pattern| fold(pattern))
+        .any(|pattern| match pattern.strip_suffix('/') {
+            Some(dir) => components.iter().any(|component| wildcard(dir, component)),
+            None => wildcard(&pattern, name),
+        })
+}
+
+fn wildcard(pattern: &str, text: &str) -> bool {
+    let Some((head, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut text) = text.strip_prefix(head) else {
+        return false;
+    };
+    let mut parts = rest.split('*').peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            return text.len() >= part.len() && text.ends_with(part);
+        }
+        match text.find(part) {
+            Some(at) => text = &text[at + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_recognized_by_name_and_directory() {
+        let patterns = DEFAULT_SENSITIVE_PATTERNS
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect::<Vec<_>>();
+        for path in [
+            ".env",
+            "app/.env.local",
+            "certs/server.key",
+            "secrets/db.txt",
+            "a/secrets",
+        ] {
+            assert!(is_sensitive_path(path, &patterns), "{path}");
+        }
+        for path in [
+            "src/main.rs",
+            "environment.md",
+            "keys.rs",
+            "docs/secrets.md",
+        ] {
+            assert!(!is_sensitive_path(path, &patterns), "{path}");
+        }
+    }
+
+    #[test]
+    fn case_is_folded_only_when_asked() {
+        let patterns = vec![".env".to_string(), ".ssh/".to_string()];
+        assert!(matches_patterns(Path::new(".ENV"), &patterns, true));
+        assert!(matches_patterns(
+            Path::new("home/.SSH/config"),
+            &patterns,
+            true
+        ));
+        assert!(!matches_patterns(Path::new(".ENV"), &patterns, false));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_are_judged_by_what_they_point_at() {
+        let dir = std::env::temp_dir().join(format!("please-sensitive-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("id_ed25519"), "key").unwrap();
+        let link = dir.join("notes");
+        std::os::unix::fs::symlink(dir.join("id_ed25519"), &link).unwrap();
+        let patterns = vec!["id_ed25519".to_string()];
+        let sensitive = is_sensitive_path(&link.to_string_lossy(), &patterns);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(sensitive);
+    }
+}
@@ -0,0 +1,104 @@
+use super::apply_patch::write_verbatim_within_cwd;
+use super::common::{Param, ParamType, Stride, resolve_path_within_cwd};
+use serde::Deserialize;
+
+pub const NAME: &str = "write_file";
+
+#[derive(Deserialize)]
+pub struct Args {
+    path: String,
+    content: String,
+    /// Create missing parent directories; on unless the model turns it off
+    #[serde(default = "create_dirs_by_default")]
+    create_dirs: bool,
+}
+
+fn create_dirs_by_default() -> bool {
+    true
+}
+
+pub async fn call(args: Args, _stride: Stride) -> serde_json::Value {
+    match write(&args) {
+        Ok(()) => serde_json::json!({
+            "ok": true,
+            "path": args.path,
+            "bytes_written": args.content.len(),
+        }),
+        Err(e) => serde_json::json!({ "error": e.to_string() }),
+    }
+}
+
+fn write(args: &Args) -> std::io::Result<()> {
+    if !args.create_dirs {
+        let rel = resolve_path_within_cwd(&args.path)?; // sanitized relative path
+        if let Some(parent) = rel.parent()
+            && !parent.as_os_str().is_empty()
+            && !parent.is_dir()
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "{} does not exist; pass create_dirs: true",
+                    parent.display()
+                ),
+            ));
+        }
+    }
+    write_verbatim_within_cwd(&args.path, &args.content)
+}
+
+pub fn spec() -> (&'static str, &'static str, Vec<Param>) {
+    (
+        NAME,
+        "Write a file with exactly the given content, replacing whatever it held before",
+        vec![
+            Param {
+                name: "path",
+                desc: "Relative path of the file to write",
+                param_type: ParamType::String,
+                required: true,
+            },
+            Param {
+                name: "content",
+                desc: "Full content of the file, written verbatim",
+                param_type: ParamType::String,
+                required: true,
+            },
+            Param {
+                name: "create_dirs",
+                desc: "Create missing parent directories; default true",
+                param_type: ParamType::Boolean,
+                required: false,
+            },
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_replaced_and_parents_made_only_when_allowed() {
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = format!("target/please-write-{}-{stamp}", std::process::id());
+        let path = format!("{root}/nested/out.txt");
+        let args = |content: &str, create_dirs| Args {
+            path: path.clone(),
+            content: content.to_string(),
+            create_dirs,
+        };
+
+        let refused = write(&args("first", false)).unwrap_err();
+        write(&args("first", true)).unwrap();
+        write(&args("second", false)).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(refused.kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(content, "second");
+    }
+}